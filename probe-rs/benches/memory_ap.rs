@@ -0,0 +1,109 @@
+//! Benchmarks for the block transfers of APv2 memory access ports.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use probe_rs::{
+    architecture::arm::{
+        ap_v2::memory_ap::{read_block, read_block_into},
+        ArmError,
+    },
+    MemoryInterface,
+};
+
+const BASE: u64 = 0x1000;
+const CSW: u64 = BASE + 0xD00;
+const TAR: u64 = BASE + 0xD04;
+const DRW: u64 = BASE + 0xD0C;
+
+/// A minimal memory AP which returns the TAR as the data read from DRW.
+#[derive(Default)]
+struct EchoAp {
+    csw: u32,
+    tar: u32,
+}
+
+impl MemoryInterface<ArmError> for EchoAp {
+    fn supports_native_64bit_access(&mut self) -> bool {
+        false
+    }
+
+    fn read_64(&mut self, _address: u64, _data: &mut [u64]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(64))
+    }
+
+    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), ArmError> {
+        for d in data {
+            *d = match address {
+                CSW => self.csw,
+                DRW => {
+                    let value = self.tar;
+                    self.tar = self.tar.wrapping_add(4);
+                    value
+                }
+                _ => 0,
+            };
+        }
+        Ok(())
+    }
+
+    fn read_16(&mut self, _address: u64, _data: &mut [u16]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(16))
+    }
+
+    fn read_8(&mut self, _address: u64, _data: &mut [u8]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(8))
+    }
+
+    fn write_64(&mut self, _address: u64, _data: &[u64]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(64))
+    }
+
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), ArmError> {
+        for d in data {
+            match address {
+                CSW => self.csw = *d,
+                TAR => self.tar = *d,
+                // Writes to DRW, TAR2 and the other registers are ignored.
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn write_16(&mut self, _address: u64, _data: &[u16]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(16))
+    }
+
+    fn write_8(&mut self, _address: u64, _data: &[u8]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(8))
+    }
+
+    fn supports_8bit_transfers(&self) -> Result<bool, ArmError> {
+        Ok(false)
+    }
+
+    fn flush(&mut self) -> Result<(), ArmError> {
+        Ok(())
+    }
+}
+
+fn block_read(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read 256 words");
+    let mut ap = EchoAp {
+        csw: 0x8000_0052,
+        ..Default::default()
+    };
+
+    group.bench_function("read_block", |b| {
+        b.iter(|| read_block(&mut ap, BASE, 0x2000_0000, 256).unwrap())
+    });
+
+    let mut buffer = [0u32; 256];
+    group.bench_function("read_block_into", |b| {
+        b.iter(|| read_block_into(&mut ap, BASE, 0x2000_0000, &mut buffer).unwrap())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, block_read);
+criterion_main!(benches);
@@ -0,0 +1,120 @@
+//! Benchmarks for the throughput of APv2 memory AP transfers under each address increment mode,
+//! using the mock memory AP with a simulated latency for every register access.
+//!
+//! The latency defaults to 1 µs and can be changed with the `MOCK_AP_LATENCY_NS` environment
+//! variable.
+
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use probe_rs::architecture::arm::ap_v2::{
+    memory_ap::{
+        mock::MockMemoryAp, read_block_into, read_data, transfer_batch, write_register, Endianness,
+        Transfer, AUTO_INCREMENT_BLOCK_SIZE,
+    },
+    AddressIncrement, DataSize, CSW, TAR, TAR2,
+};
+
+const BASE: u64 = 0x1_0000;
+const WORDS: usize = 256;
+
+fn mock_ap() -> MockMemoryAp {
+    let latency = std::env::var("MOCK_AP_LATENCY_NS")
+        .ok()
+        .and_then(|ns| ns.parse().ok())
+        .unwrap_or(1000);
+    let mut mock = MockMemoryAp::with_pattern(BASE);
+    mock.transfer_latency = Duration::from_nanos(latency);
+    mock
+}
+
+/// Reads `out.len()` words starting at `address` with transfers of `size` and the address
+/// increment `increment`, programming the TAR before every word if the TAR is not incremented.
+///
+/// Every DRW access transfers one word, so `size` must be a word unless the transfers are packed.
+fn read_words(
+    mock: &mut MockMemoryAp,
+    size: DataSize,
+    increment: AddressIncrement,
+    address: u64,
+    out: &mut [u32],
+) {
+    let csw = CSW::builder().size(size).addr_inc(increment).build();
+    write_register(mock, BASE, csw).unwrap();
+    for (i, word) in out.iter_mut().enumerate() {
+        let address = address + i as u64 * 4;
+        if i == 0 || increment == AddressIncrement::Off || address % AUTO_INCREMENT_BLOCK_SIZE == 0
+        {
+            write_register(
+                mock,
+                BASE,
+                TAR {
+                    address: address as u32,
+                },
+            )
+            .unwrap();
+            write_register(mock, BASE, TAR2 { address: 0 }).unwrap();
+        }
+        *word = read_data(mock, BASE, Endianness::Little).unwrap();
+    }
+}
+
+fn address_increment(c: &mut Criterion) {
+    let mut group = c.benchmark_group("address increment");
+    group.throughput(Throughput::Elements(WORDS as u64));
+    let mut mock = mock_ap();
+    let mut buffer = [0u32; WORDS];
+
+    // Packing only differs from single increments for sub-word sizes, where it transfers four
+    // bytes per DRW access instead of one.
+    for (size, increment) in [
+        (DataSize::U32, AddressIncrement::Off),
+        (DataSize::U32, AddressIncrement::Single),
+        (DataSize::U8, AddressIncrement::Packed),
+    ] {
+        let id = BenchmarkId::from_parameter(format!("{increment:?} {size:?}"));
+        group.bench_function(id, |b| {
+            b.iter(|| read_words(&mut mock, size, increment, 0x100, &mut buffer))
+        });
+    }
+
+    group.finish();
+}
+
+fn tar_programming(c: &mut Criterion) {
+    let mut group = c.benchmark_group("TAR programming");
+    group.throughput(Throughput::Elements(WORDS as u64));
+    let mut mock = mock_ap();
+    let mut buffer = [0u32; WORDS];
+
+    // 256 words fit exactly into one auto-increment block, or straddle two.
+    group.bench_function("within one 1 KB block", |b| {
+        b.iter(|| read_block_into(&mut mock, BASE, 0x400, &mut buffer).unwrap())
+    });
+    group.bench_function("across a 1 KB boundary", |b| {
+        b.iter(|| read_block_into(&mut mock, BASE, 0x600, &mut buffer).unwrap())
+    });
+
+    // Consecutive reads reuse the TAR, scattered reads program it for every word.
+    let consecutive: Vec<_> = (0..WORDS as u64)
+        .map(|i| Transfer::Read {
+            addr: 0x400 + i * 4,
+        })
+        .collect();
+    let scattered: Vec<_> = (0..WORDS as u64)
+        .map(|i| Transfer::Read {
+            addr: 0x400 + i * 16,
+        })
+        .collect();
+    group.bench_function("batch reusing the TAR", |b| {
+        b.iter(|| transfer_batch(&mut mock, BASE, &consecutive))
+    });
+    group.bench_function("batch programming the TAR", |b| {
+        b.iter(|| transfer_batch(&mut mock, BASE, &scattered))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, address_increment, tar_programming);
+criterion_main!(benches);
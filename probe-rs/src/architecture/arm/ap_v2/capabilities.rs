@@ -0,0 +1,33 @@
+//! The transfer sizes and address increments a memory access port supports.
+//!
+//! The capabilities are detected with [`MemApCapabilities::probe`], and checked by
+//! [`CSW::validate`](super::registers::CSW::validate).
+
+use super::registers::DataSize;
+
+/// The transfer sizes and address increments a memory access port supports.
+///
+/// The default capabilities are a guess until the AP has been probed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemApCapabilities {
+    /// Whether the AP supports byte and halfword transfers, or `None` if this has not been
+    /// probed yet, in which case they are assumed to be supported.
+    pub sub_word: Option<bool>,
+    /// Whether the AP supports packed transfers, see
+    /// [`AddressIncrement::Packed`](super::registers::AddressIncrement::Packed), or `None` if
+    /// this has not been probed yet.
+    pub packed: Option<bool>,
+    /// The largest transfer size of the bus behind the AP.
+    pub max_size: DataSize,
+}
+
+impl Default for MemApCapabilities {
+    /// Byte, halfword and word transfers, with sub-word transfers and packing not probed yet.
+    fn default() -> Self {
+        Self {
+            sub_word: None,
+            packed: None,
+            max_size: DataSize::U32,
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! Typed access to the registers of ADIv5 access ports through the AP-v2 register definitions.
+//!
+//! The registers of an APv2 memory access port are defined at their ADIv6
+//! offsets, e.g. `0xD00` for the CSW. An ADIv5 (AP-v1) access port has the same registers, but
+//! in a 256 byte register file which is not memory mapped: the debug port's SELECT register
+//! selects the AP and a bank of four registers within it, and the access addresses one of those
+//! four. This module translates between both, so the same register types work for either:
+//! [`ApV1RegisterFile`] maps the register file of an ADIv5 access port to the ADIv6 offsets, so
+//! a [`MemoryAp`] handle created with [`MemoryAp::new_v1`] accesses it like an ADIv6 one.
+
+use crate::{
+    architecture::arm::{
+        dp::{DpAddress, SelectV1},
+        ArmError, DapAccess, FullyQualifiedApAddress,
+    },
+    MemoryInterface,
+};
+
+use super::{
+    memory_ap::{AdiVersion, ApConfig, MemoryAp},
+    registers::Register,
+};
+
+/// The ADIv6 offsets which make up the register file of an ADIv5 access port.
+pub(super) const APV1_REGISTER_FILE: std::ops::RangeInclusive<u16> = 0xD00..=0xDFF;
+
+/// The location of an AP-v2 register in the register file of an ADIv5 access port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ApV1Location {
+    address: u8,
+}
+
+impl ApV1Location {
+    /// The location of the register at `offset` in the ADIv6 layout, or `None` if the register
+    /// is outside the `0xD00..=0xDFF` region, which ADIv5 access ports do not implement.
+    pub const fn from_offset(offset: u16) -> Option<Self> {
+        if offset < *APV1_REGISTER_FILE.start() || offset > *APV1_REGISTER_FILE.end() {
+            return None;
+        }
+        Some(Self {
+            address: (offset & 0xFF) as u8,
+        })
+    }
+
+    /// The location of the register `R`, see [`ApV1Location::from_offset`].
+    pub const fn of<R: Register>() -> Option<Self> {
+        Self::from_offset(R::ADDRESS)
+    }
+
+    /// The address of the register within the register file, as passed to
+    /// [`DapAccess::read_raw_ap_register`].
+    pub const fn address(self) -> u8 {
+        self.address
+    }
+
+    /// The bank of four registers which contains the register, selected by `SELECT.APBANKSEL`.
+    pub const fn bank(self) -> u8 {
+        self.address >> 4
+    }
+
+    /// The index of the register within its bank, addressed by `A[3:2]` of the access.
+    pub const fn index(self) -> u8 {
+        (self.address >> 2) & 0b11
+    }
+
+    /// The value of the SELECT register of a DPv1 or DPv2 debug port which selects the bank of
+    /// the register in the AP `apsel`.
+    pub fn select(self, apsel: u8) -> SelectV1 {
+        let mut select = SelectV1(0);
+        select.set_ap_sel(apsel);
+        select.set_ap_bank_sel(self.bank());
+        select
+    }
+}
+
+/// The register file of an ADIv5 access port, accessed through the debug port and mapped to the
+/// ADIv6 offsets of the AP-v2 registers.
+///
+/// A 32 bit access at an address in `0xD00..=0xDFF` accesses the register at that offset in the
+/// ADIv6 layout with [`DapAccess::read_raw_ap_register`] or
+/// [`DapAccess::write_raw_ap_register`], which program the debug port's SELECT register with the
+/// AP and the bank of the register, see [`ApV1Location::select`]. Accesses to other addresses
+/// fail with [`ArmError::OutOfBounds`], and accesses of other sizes with
+/// [`ArmError::UnsupportedTransferWidth`].
+pub struct ApV1RegisterFile<'dap, D: DapAccess + ?Sized> {
+    dap: &'dap mut D,
+    ap: FullyQualifiedApAddress,
+}
+
+impl<'dap, D: DapAccess + ?Sized> ApV1RegisterFile<'dap, D> {
+    /// The register file of the ADIv5 access port `apsel` behind the debug port `dp`.
+    pub fn new(dap: &'dap mut D, dp: DpAddress, apsel: u8) -> Self {
+        Self {
+            dap,
+            ap: FullyQualifiedApAddress::v1_with_dp(dp, apsel),
+        }
+    }
+
+    /// The address of the access port.
+    pub fn ap(&self) -> &FullyQualifiedApAddress {
+        &self.ap
+    }
+
+    /// The address of the register at `address` within the ADIv5 register file.
+    fn location(address: u64) -> Result<u8, ArmError> {
+        u16::try_from(address)
+            .ok()
+            .and_then(ApV1Location::from_offset)
+            .map(ApV1Location::address)
+            .ok_or(ArmError::OutOfBounds)
+    }
+}
+
+impl<D: DapAccess + ?Sized> MemoryInterface<ArmError> for ApV1RegisterFile<'_, D> {
+    fn supports_native_64bit_access(&mut self) -> bool {
+        false
+    }
+
+    fn read_64(&mut self, _address: u64, _data: &mut [u64]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(64))
+    }
+
+    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), ArmError> {
+        for (i, word) in data.iter_mut().enumerate() {
+            let addr = Self::location(address + i as u64 * 4)?;
+            *word = self.dap.read_raw_ap_register(&self.ap, addr)?;
+        }
+        Ok(())
+    }
+
+    fn read_16(&mut self, _address: u64, _data: &mut [u16]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(16))
+    }
+
+    fn read_8(&mut self, _address: u64, _data: &mut [u8]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(8))
+    }
+
+    fn write_64(&mut self, _address: u64, _data: &[u64]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(64))
+    }
+
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), ArmError> {
+        for (i, word) in data.iter().enumerate() {
+            let addr = Self::location(address + i as u64 * 4)?;
+            self.dap.write_raw_ap_register(&self.ap, addr, *word)?;
+        }
+        Ok(())
+    }
+
+    fn write_16(&mut self, _address: u64, _data: &[u16]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(16))
+    }
+
+    fn write_8(&mut self, _address: u64, _data: &[u8]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(8))
+    }
+
+    fn supports_8bit_transfers(&self) -> Result<bool, ArmError> {
+        Ok(false)
+    }
+
+    fn flush(&mut self) -> Result<(), ArmError> {
+        self.dap.flush()
+    }
+}
+
+impl<'iface, 'dap, D: DapAccess + ?Sized> MemoryAp<'iface, ApV1RegisterFile<'dap, D>> {
+    /// Creates a handle for the ADIv5 access port whose register file is `registers`.
+    ///
+    /// The registers are addressed at their ADIv6 offsets from a base address of zero, which
+    /// `registers` translates to the AP register accesses of the debug port. The ADI version of
+    /// the handle is [`AdiVersion::V5`], so registers outside the ADIv5 register file fail before
+    /// any transfer, see [`MemoryAp::register_address`].
+    pub fn new_v1(registers: &'iface mut ApV1RegisterFile<'dap, D>) -> Self {
+        let config = ApConfig {
+            adi_version: AdiVersion::V5,
+            ..ApConfig::new(0)
+        };
+        Self::with_config(registers, config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApV1Location, ApV1RegisterFile};
+    use crate::{
+        architecture::arm::{
+            ap_v2::{
+                memory_ap::{mock::MockMemoryAp, AdiVersion, MemoryAp, MemoryApError},
+                registers::{
+                    Register, BASE, BASE2, BD0, BD3, CFG, CSW, DRW, IDR, MBT, T0TR, TAR, TAR2,
+                },
+            },
+            dp::DpAddress,
+            ArmError,
+        },
+        MemoryInterface,
+    };
+
+    #[test]
+    fn registers_map_to_their_apv1_bank() {
+        let location = |offset| {
+            let location = ApV1Location::from_offset(offset).unwrap();
+            (location.address(), location.bank(), location.index())
+        };
+
+        assert_eq!(location(CSW::ADDRESS), (0x00, 0x0, 0));
+        assert_eq!(location(TAR::ADDRESS), (0x04, 0x0, 1));
+        assert_eq!(location(TAR2::ADDRESS), (0x08, 0x0, 2));
+        assert_eq!(location(DRW::ADDRESS), (0x0C, 0x0, 3));
+        assert_eq!(location(BD0::ADDRESS), (0x10, 0x1, 0));
+        assert_eq!(location(BD3::ADDRESS), (0x1C, 0x1, 3));
+        assert_eq!(location(MBT::ADDRESS), (0x20, 0x2, 0));
+        assert_eq!(location(T0TR::ADDRESS), (0x30, 0x3, 0));
+        assert_eq!(location(BASE2::ADDRESS), (0xF0, 0xF, 0));
+        assert_eq!(location(CFG::ADDRESS), (0xF4, 0xF, 1));
+        assert_eq!(location(BASE::ADDRESS), (0xF8, 0xF, 2));
+        assert_eq!(location(IDR::ADDRESS), (0xFC, 0xF, 3));
+
+        let select = ApV1Location::of::<IDR>().unwrap().select(2);
+        assert_eq!(u32::from(select), 0x0200_00F0);
+
+        // The integration and CoreSight management registers have no ADIv5 equivalent.
+        assert_eq!(ApV1Location::from_offset(0xF00), None);
+        assert_eq!(ApV1Location::from_offset(0xCFC), None);
+    }
+
+    #[test]
+    fn handles_access_an_apv1_port_through_select() {
+        const APSEL: u8 = 2;
+        let mut mock = MockMemoryAp::with_pattern(0x2000);
+        let expected = mock.words(0x40, 4);
+        let mut registers = ApV1RegisterFile::new(&mut mock, DpAddress::Default, APSEL);
+        let mut ap = MemoryAp::new_v1(&mut registers);
+        assert_eq!(ap.adi_version(), AdiVersion::V5);
+
+        let idr: IDR = ap.read_register().unwrap();
+        assert_eq!(idr.CLASS, IDR::MEM_AP_CLASS);
+        assert_eq!(ap.read_block(0x40, 4).unwrap(), expected);
+
+        // Registers outside the ADIv5 register file are rejected before any transfer.
+        assert!(matches!(
+            ap.read_raw(0xF00),
+            Err(MemoryApError::NotInApV1RegisterFile { offset: 0xF00, .. })
+        ));
+        assert!(matches!(
+            registers.read_word_32(0xF00),
+            Err(ArmError::OutOfBounds)
+        ));
+
+        // The IDR is in bank 0xF, the CSW, TAR and DRW of the block read in bank 0x0.
+        let select =
+            |register: u16| u32::from(ApV1Location::from_offset(register).unwrap().select(APSEL));
+        assert_eq!(mock.selects, [select(IDR::ADDRESS), select(CSW::ADDRESS)]);
+        assert_eq!(mock.selects, [0x0200_00F0, 0x0200_0000]);
+        assert_eq!(mock.tar_writes, 1);
+    }
+}
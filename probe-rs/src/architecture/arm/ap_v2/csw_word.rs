@@ -0,0 +1,172 @@
+//! Accessors for the fields of raw CSW words.
+//!
+//! These work on the `u32` as it is transferred to and from the AP, without going through a
+//! [`CSW`](super::registers::CSW). They are `const` and do not allocate, for hosts which keep CSW
+//! values in tables or build them in `const` context.
+
+use super::registers::{AddressIncrement, DataSize, InvalidAddressIncrement, InvalidDataSizeError};
+
+const DBG_SW_ENABLE: u32 = 1 << 31;
+const PROT_SHIFT: u32 = 24;
+const PROT_MASK: u32 = 0x7F << PROT_SHIFT;
+const S_DEVICE_EN: u32 = 1 << 23;
+const MTE: u32 = 1 << 15;
+const MODE_SHIFT: u32 = 8;
+const MODE_MASK: u32 = 0xF << MODE_SHIFT;
+const TR_IN_PROG: u32 = 1 << 7;
+const DEVICE_EN: u32 = 1 << 6;
+
+const fn set_flag(word: u32, flag: u32, value: bool) -> u32 {
+    if value {
+        word | flag
+    } else {
+        word & !flag
+    }
+}
+
+/// Returns the `SIZE` field of `word`.
+pub const fn csw_get_size(word: u32) -> Result<DataSize, InvalidDataSizeError> {
+    DataSize::from_bits(word)
+}
+
+/// Returns `word` with the `SIZE` field replaced by `size`.
+pub const fn csw_set_size(word: u32, size: DataSize) -> u32 {
+    (word & !DataSize::MASK) | size.bits()
+}
+
+/// Returns the `AddrInc` field of `word`.
+pub const fn csw_get_addr_inc(word: u32) -> Result<AddressIncrement, InvalidAddressIncrement> {
+    AddressIncrement::from_bits(word)
+}
+
+/// Returns `word` with the `AddrInc` field replaced by `increment`.
+pub const fn csw_set_addr_inc(word: u32, increment: AddressIncrement) -> u32 {
+    (word & !AddressIncrement::MASK) | increment.bits()
+}
+
+/// Returns the `Prot` field of `word`.
+pub const fn csw_get_prot(word: u32) -> u8 {
+    ((word & PROT_MASK) >> PROT_SHIFT) as u8
+}
+
+/// Returns `word` with the `Prot` field replaced by `prot`. Bits of `prot` above the 7 bit
+/// field are ignored.
+pub const fn csw_set_prot(word: u32, prot: u8) -> u32 {
+    (word & !PROT_MASK) | (((prot as u32) << PROT_SHIFT) & PROT_MASK)
+}
+
+/// Returns the `Mode` field of `word`.
+pub const fn csw_get_mode(word: u32) -> u8 {
+    ((word & MODE_MASK) >> MODE_SHIFT) as u8
+}
+
+/// Returns `word` with the `Mode` field replaced by `mode`. Bits of `mode` above the 4 bit
+/// field are ignored.
+pub const fn csw_set_mode(word: u32, mode: u8) -> u32 {
+    (word & !MODE_MASK) | (((mode as u32) << MODE_SHIFT) & MODE_MASK)
+}
+
+/// Returns the `DbgSwEnable` bit of `word`.
+pub const fn csw_get_dbg_sw_enable(word: u32) -> bool {
+    word & DBG_SW_ENABLE != 0
+}
+
+/// Returns `word` with the `DbgSwEnable` bit set to `enable`.
+pub const fn csw_set_dbg_sw_enable(word: u32, enable: bool) -> u32 {
+    set_flag(word, DBG_SW_ENABLE, enable)
+}
+
+/// Returns the `MTE` bit of `word`.
+pub const fn csw_get_mte(word: u32) -> bool {
+    word & MTE != 0
+}
+
+/// Returns `word` with the `MTE` bit set to `enable`.
+pub const fn csw_set_mte(word: u32, enable: bool) -> u32 {
+    set_flag(word, MTE, enable)
+}
+
+/// Returns the read-only `SDeviceEn` bit of `word`.
+pub const fn csw_get_s_device_en(word: u32) -> bool {
+    word & S_DEVICE_EN != 0
+}
+
+/// Returns the read-only `TrInProg` bit of `word`.
+pub const fn csw_get_tr_in_prog(word: u32) -> bool {
+    word & TR_IN_PROG != 0
+}
+
+/// Returns the read-only `DeviceEn` bit of `word`.
+pub const fn csw_get_device_en(word: u32) -> bool {
+    word & DEVICE_EN != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::architecture::arm::ap_v2::registers::CSW;
+
+    const WORDS: [u32; 4] = [0x8000_0012, 0x8000_0052, 0xA300_80E0, 0x0000_0F21];
+
+    #[test]
+    fn getters_match_the_parsed_register() {
+        for word in WORDS {
+            let csw = CSW::try_from(word).unwrap();
+            assert_eq!(csw_get_size(word), Ok(csw.SIZE));
+            assert_eq!(csw_get_addr_inc(word), Ok(csw.AddrInc));
+            assert_eq!(csw_get_prot(word), csw.Prot);
+            assert_eq!(csw_get_mode(word), csw.Mode);
+            assert_eq!(csw_get_dbg_sw_enable(word), csw.DbgSwEnable);
+            assert_eq!(csw_get_mte(word), csw.MTE);
+            assert_eq!(csw_get_s_device_en(word), csw.SDeviceEn);
+            assert_eq!(csw_get_tr_in_prog(word), csw.TrInProg);
+            assert_eq!(csw_get_device_en(word), csw.DeviceEn);
+        }
+    }
+
+    #[test]
+    fn setters_match_the_register_encoding() {
+        for word in WORDS {
+            let mut csw = CSW::try_from(word).unwrap();
+            csw.SIZE = DataSize::U8;
+            csw.AddrInc = AddressIncrement::Packed;
+            csw.Prot = 0x55;
+            csw.Mode = 0x3;
+            csw.DbgSwEnable = !csw.DbgSwEnable;
+            csw.MTE = !csw.MTE;
+
+            let mut raw = csw_set_size(word, DataSize::U8);
+            raw = csw_set_addr_inc(raw, AddressIncrement::Packed);
+            raw = csw_set_prot(raw, 0x55);
+            raw = csw_set_mode(raw, 0x3);
+            raw = csw_set_dbg_sw_enable(raw, !csw_get_dbg_sw_enable(word));
+            raw = csw_set_mte(raw, !csw_get_mte(word));
+            assert_eq!(raw, u32::from(csw));
+            assert_eq!(CSW::try_from(raw).unwrap(), csw);
+        }
+    }
+
+    #[test]
+    fn reserved_encodings_are_reported() {
+        assert_eq!(
+            csw_get_size(0x8000_0017),
+            Err(InvalidDataSizeError { value: 0b111 })
+        );
+        assert_eq!(
+            csw_get_addr_inc(0x8000_0032),
+            Err(InvalidAddressIncrement { value: 0b11 })
+        );
+        // Values wider than the field do not leak into neighbouring fields.
+        assert_eq!(csw_set_prot(0, 0xFF), 0x7F00_0000);
+        assert_eq!(csw_set_mode(0, 0xFF), 0x0000_0F00);
+    }
+
+    #[test]
+    fn accessors_are_usable_in_const_context() {
+        const WORD: u32 = csw_set_addr_inc(
+            csw_set_size(0x8000_0000, DataSize::U16),
+            AddressIncrement::Off,
+        );
+        assert_eq!(WORD, 0x8000_0001);
+    }
+}
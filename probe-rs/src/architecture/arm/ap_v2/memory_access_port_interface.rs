@@ -9,7 +9,7 @@ use crate::{
     MemoryInterface,
 };
 
-use super::registers::{BASE, BASE2};
+use super::registers::{BaseAddrFormat, RomTableAddress, BASE, BASE2};
 use super::MaybeOwned;
 
 pub struct MemoryAccessPortInterface<'iface> {
@@ -38,6 +38,25 @@ impl<'iface> MemoryAccessPortInterface<'iface> {
         })
     }
 
+    /// Reads the address of the debug entry of this AP, usually its ROM table, from BASE and
+    /// BASE2. Returns `None` if the AP has no debug entry.
+    pub fn rom_table_address(&mut self) -> Result<Option<RomTableAddress>, ArmError> {
+        let base = BASE::try_from(
+            self.iface
+                .read_word_32(self.base + u64::from(BASE::ADDRESS))?,
+        )?;
+        // BASE2 only holds the upper address word in the ADIv5 format.
+        let base2 = if base.Format == BaseAddrFormat::ADIv5 {
+            Some(BASE2::try_from(
+                self.iface
+                    .read_word_32(self.base + u64::from(BASE2::ADDRESS))?,
+            )?)
+        } else {
+            None
+        };
+        Ok(RomTableAddress::from_base(base, base2))
+    }
+
     fn set_transaction_size(&mut self, size: DataSize) -> Result<(), ArmError> {
         let mut csw_raw = [0u32];
         self.iface
@@ -204,23 +223,21 @@ impl ArmMemoryInterface for MemoryAccessPortInterface<'_> {
     }
 
     fn base_address(&mut self) -> Result<u64, ArmError> {
-        let mut base = 0;
-        let mut base1 = 0;
-        self.iface.read_32(
-            self.base + u64::from(BASE::ADDRESS),
-            std::slice::from_mut(&mut base),
-        )?;
-        self.iface.read_32(
-            self.base + u64::from(BASE2::ADDRESS),
-            std::slice::from_mut(&mut base1),
-        )?;
-        let base = (u64::from(base1) << 32) | u64::from(base);
+        let base = self
+            .rom_table_address()?
+            .ok_or_else(|| {
+                ArmError::Other(format!(
+                    "{:x?} has no debug entry",
+                    self.fully_qualified_address()
+                ))
+            })?
+            .absolute();
         tracing::debug!(
             "{:x?}’s rom table is at: {:x}",
             self.fully_qualified_address(),
             base
         );
-        Ok(base & 0xFFFF_FFFF_FFFF_FFF0)
+        Ok(base)
     }
 
     fn get_swd_sequence(&mut self) -> Result<&mut dyn SwdSequence, DebugProbeError> {
@@ -0,0 +1,223 @@
+//! The access ports found during enumeration, and lookups over them.
+
+use std::cmp::Ordering;
+
+use crate::{
+    architecture::arm::{
+        ap_v2::registers::{MemApBusType, IDR},
+        ApV2Address, ArmError,
+    },
+    MemoryInterface,
+};
+
+use super::{transfer::read_register, MemoryApError};
+
+/// An access port found during enumeration, identified by its IDR.
+///
+/// Access ports are ordered by their address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApInfo {
+    /// The address of the AP, i.e. the base addresses of the APs leading to it.
+    pub address: ApV2Address,
+    /// The identification register of the AP.
+    pub idr: IDR,
+}
+
+impl ApInfo {
+    /// Reads the IDR of the AP at `base` in the address space `iface` of the AP at `parent`.
+    pub fn read<M>(iface: &mut M, parent: &ApV2Address, base: u64) -> Result<Self, MemoryApError>
+    where
+        M: MemoryInterface<ArmError> + ?Sized,
+    {
+        let idr = read_register(iface, base)?;
+        Ok(Self {
+            address: parent.clone().append(base),
+            idr,
+        })
+    }
+
+    /// The base address of the AP's register file, within the address space of its parent.
+    pub fn base(&self) -> u64 {
+        self.address.as_slice().last().copied().unwrap_or_default()
+    }
+
+    /// Returns whether the AP is a memory access port.
+    pub fn is_memory_ap(&self) -> bool {
+        self.idr.CLASS == IDR::MEM_AP_CLASS
+    }
+
+    /// The bus behind the AP, if it is a memory access port, see `IDR::bus_type`.
+    pub fn bus_type(&self) -> Option<MemApBusType> {
+        self.idr.bus_type()
+    }
+}
+
+impl PartialOrd for ApInfo {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ApInfo {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.address, u32::from(self.idr)).cmp(&(&other.address, u32::from(other.idr)))
+    }
+}
+
+/// The access ports found during enumeration, sorted by address.
+///
+/// [`enumerate_access_port_info`](crate::architecture::arm::ap_v2::enumerate_access_port_info)
+/// returns the access ports of a debug port.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApList {
+    aps: Vec<ApInfo>,
+}
+
+impl ApList {
+    /// Reads the IDR of the AP at each of `bases` in the address space `iface` of the AP at
+    /// `parent`.
+    pub fn read<M>(
+        iface: &mut M,
+        parent: &ApV2Address,
+        bases: impl IntoIterator<Item = u64>,
+    ) -> Result<Self, MemoryApError>
+    where
+        M: MemoryInterface<ArmError> + ?Sized,
+    {
+        bases
+            .into_iter()
+            .map(|base| ApInfo::read(iface, parent, base))
+            .collect()
+    }
+
+    /// Iterates over the APs, sorted by address.
+    pub fn iter(&self) -> std::slice::Iter<'_, ApInfo> {
+        self.aps.iter()
+    }
+
+    /// Iterates over the memory access ports, sorted by address.
+    pub fn memory_aps(&self) -> impl Iterator<Item = &ApInfo> {
+        self.aps.iter().filter(|ap| ap.is_memory_ap())
+    }
+
+    /// Returns the memory access port with the lowest address which has `bus_type` behind it.
+    pub fn find_by_bus_type(&self, bus_type: MemApBusType) -> Option<&ApInfo> {
+        self.aps.iter().find(|ap| ap.bus_type() == Some(bus_type))
+    }
+
+    /// Returns the AP at `address`.
+    pub fn get(&self, address: &ApV2Address) -> Option<&ApInfo> {
+        self.aps
+            .binary_search_by(|ap| ap.address.cmp(address))
+            .ok()
+            .map(|index| &self.aps[index])
+    }
+
+    /// The number of APs.
+    pub fn len(&self) -> usize {
+        self.aps.len()
+    }
+
+    /// Returns whether no APs were found.
+    pub fn is_empty(&self) -> bool {
+        self.aps.is_empty()
+    }
+
+    /// Returns the APs, sorted by address.
+    pub fn into_vec(self) -> Vec<ApInfo> {
+        self.aps
+    }
+}
+
+impl From<Vec<ApInfo>> for ApList {
+    fn from(mut aps: Vec<ApInfo>) -> Self {
+        aps.sort();
+        Self { aps }
+    }
+}
+
+impl FromIterator<ApInfo> for ApList {
+    fn from_iter<I: IntoIterator<Item = ApInfo>>(iter: I) -> Self {
+        Vec::from_iter(iter).into()
+    }
+}
+
+impl<'a> IntoIterator for &'a ApList {
+    type Item = &'a ApInfo;
+    type IntoIter = std::slice::Iter<'a, ApInfo>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApInfo, ApList};
+    use crate::architecture::arm::{
+        ap_v2::{
+            memory_ap::DryRunMemoryAp,
+            registers::{MemApBusType, Register, IDR},
+        },
+        ApV2Address,
+    };
+
+    fn idr(class: u8, ty: u8) -> IDR {
+        IDR {
+            REVISION: 0,
+            DESIGNER: 0x23B,
+            CLASS: class,
+            VARIANT: 0,
+            TYPE: ty,
+        }
+    }
+
+    #[test]
+    fn aps_are_sorted_and_looked_up_by_bus_type() {
+        // Three APs sharing one parent, with the register file of each at its base.
+        let mut parent = DryRunMemoryAp::new(0);
+        let topology = [
+            (0x3000, idr(IDR::MEM_AP_CLASS, 0x2)),
+            (0x1000, idr(0x0, 0x0)),
+            (0x2000, idr(IDR::MEM_AP_CLASS, 0x5)),
+        ];
+        for (base, idr) in topology {
+            parent.set_register(base as u16 + IDR::ADDRESS, idr.into());
+        }
+
+        let root = ApV2Address::root();
+        let aps = ApList::read(&mut parent, &root, topology.map(|(base, _)| base)).unwrap();
+        assert_eq!(aps.len(), 3);
+        assert_eq!(
+            aps.iter().map(ApInfo::base).collect::<Vec<_>>(),
+            [0x1000, 0x2000, 0x3000]
+        );
+        assert_eq!(
+            aps.memory_aps().map(ApInfo::base).collect::<Vec<_>>(),
+            [0x2000, 0x3000]
+        );
+        let ahb = aps.find_by_bus_type(MemApBusType::AmbaAhb5).unwrap();
+        assert_eq!(ahb.address, ApV2Address::new_with_tip(0x2000));
+        assert!(aps.find_by_bus_type(MemApBusType::AmbaAxi5).is_none());
+        let ap = aps.get(&ApV2Address::new_with_tip(0x1000));
+        assert_eq!(ap.map(ApInfo::is_memory_ap), Some(false));
+        assert!(aps.get(&ApV2Address::new_with_tip(0x4000)).is_none());
+
+        // An AP nested behind another one sorts after it.
+        let nested = ApList::read(&mut parent, &ApV2Address::new_with_tip(0x1000), [0x3000]);
+        let mut all = aps.clone().into_vec();
+        all.extend(nested.unwrap().into_vec());
+        let all = ApList::from(all);
+        assert_eq!(
+            all.iter()
+                .map(|ap| ap.address.as_slice())
+                .collect::<Vec<_>>(),
+            [&[0x1000][..], &[0x1000, 0x3000], &[0x2000], &[0x3000]]
+        );
+
+        // APs collected out of order are sorted by address.
+        let mut shuffled = aps.clone().into_vec();
+        shuffled.reverse();
+        assert_eq!(ApList::from(shuffled), aps);
+    }
+}
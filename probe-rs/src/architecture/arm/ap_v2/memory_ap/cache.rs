@@ -0,0 +1,63 @@
+//! Caching of memory reads through a memory access port.
+
+use std::collections::HashMap;
+
+/// The number of words served from and missed in the read cache of a [`MemoryAp`](super::MemoryAp).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    /// Words read from the cache.
+    pub hits: usize,
+    /// Words read from the target.
+    pub misses: usize,
+}
+
+/// The words read through a memory access port, by address.
+#[derive(Debug, Default)]
+pub(super) struct ReadCache {
+    words: HashMap<u64, u32>,
+    pub stats: CacheStats,
+}
+
+impl ReadCache {
+    /// Fills `out` with the cached words starting at `address`, if all of them are cached.
+    pub fn lookup(&mut self, address: u64, out: &mut [u32]) -> bool {
+        let addresses = (0..out.len() as u64).map(|i| address + i * 4);
+        let cached: Option<Vec<u32>> = addresses
+            .map(|address| self.words.get(&address).copied())
+            .collect();
+
+        match cached {
+            Some(words) => {
+                out.copy_from_slice(&words);
+                self.stats.hits += out.len();
+                true
+            }
+            None => {
+                self.stats.misses += out.len();
+                false
+            }
+        }
+    }
+
+    /// Caches the words of `data`, read starting at `address`.
+    pub fn insert(&mut self, address: u64, data: &[u32]) {
+        for (i, word) in data.iter().enumerate() {
+            self.words.insert(address + i as u64 * 4, *word);
+        }
+    }
+
+    /// Drops the cached words overlapping the `len` bytes starting at `address`.
+    pub fn invalidate(&mut self, address: u64, len: usize) {
+        let end = address + len as u64;
+        let mut word = address & !0b11;
+        while word < end {
+            self.words.remove(&word);
+            word += 4;
+        }
+    }
+
+    /// Drops all cached words.
+    pub fn clear(&mut self) {
+        self.words.clear();
+    }
+}
@@ -0,0 +1,51 @@
+//! Cancellation of long running block transfers.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+/// Conditions under which a block transfer stops early.
+///
+/// The conditions are checked before every [`AUTO_INCREMENT_BLOCK_SIZE`](super::AUTO_INCREMENT_BLOCK_SIZE)
+/// chunk, so a cancelled transfer stops within one chunk without adding overhead to every word.
+#[derive(Debug, Clone, Default)]
+pub struct Cancellation {
+    deadline: Option<Instant>,
+    flag: Option<Arc<AtomicBool>>,
+}
+
+impl Cancellation {
+    /// Never cancels the transfer.
+    pub const fn none() -> Self {
+        Self {
+            deadline: None,
+            flag: None,
+        }
+    }
+
+    /// Cancels the transfer once `deadline` has passed.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Cancels the transfer once `flag` is set, e.g. from another thread.
+    pub fn with_flag(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.flag = Some(flag);
+        self
+    }
+
+    /// Returns whether the transfer should stop.
+    pub fn is_cancelled(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+            || self
+                .flag
+                .as_ref()
+                .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    }
+}
@@ -0,0 +1,149 @@
+//! Checksums of memory regions, computed while streaming the words from the target.
+
+use crate::{architecture::arm::ArmError, MemoryInterface};
+
+use super::{
+    transfer::{read_block_into, AUTO_INCREMENT_BLOCK_SIZE},
+    MemoryApError,
+};
+
+/// The number of words read at once, one auto-increment block.
+const CHUNK_WORDS: usize = (AUTO_INCREMENT_BLOCK_SIZE / 4) as usize;
+
+/// The reversed representation of the CRC-32 polynomial `0x04C11DB7`.
+const CRC32_POLYNOMIAL: u32 = 0xEDB8_8320;
+
+/// The lookup table for a byte-wise CRC-32.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ CRC32_POLYNOMIAL
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// The algorithm [`checksum_region`] folds the words of a region with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChecksumAlgo {
+    /// The CRC-32 used by zlib and Ethernet ("CRC-32/ISO-HDLC"), with the polynomial
+    /// `0x04C11DB7` in reflected form, an initial value and final XOR of `0xFFFFFFFF`.
+    ///
+    /// The bytes of each word are processed in memory order, so the result matches the CRC-32
+    /// of the image that was written.
+    #[default]
+    Crc32,
+    /// The wrapping sum of the 32 bit words.
+    Sum32,
+}
+
+impl ChecksumAlgo {
+    fn initial(self) -> u32 {
+        match self {
+            ChecksumAlgo::Crc32 => u32::MAX,
+            ChecksumAlgo::Sum32 => 0,
+        }
+    }
+
+    fn update(self, state: u32, words: &[u32]) -> u32 {
+        match self {
+            ChecksumAlgo::Crc32 => crc32_update(state, words.iter().flat_map(|w| w.to_le_bytes())),
+            ChecksumAlgo::Sum32 => words
+                .iter()
+                .fold(state, |sum, word| sum.wrapping_add(*word)),
+        }
+    }
+
+    fn finish(self, state: u32) -> u32 {
+        match self {
+            ChecksumAlgo::Crc32 => !state,
+            ChecksumAlgo::Sum32 => state,
+        }
+    }
+}
+
+fn crc32_update(crc: u32, bytes: impl IntoIterator<Item = u8>) -> u32 {
+    bytes.into_iter().fold(crc, |crc, byte| {
+        CRC32_TABLE[((crc ^ u32::from(byte)) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Computes the checksum of the `words` 32 bit words starting at `address`, read through the
+/// memory access port at `base`.
+///
+/// The words are read in blocks into a fixed scratch buffer and folded into the checksum, so
+/// verifying a region does not need host memory proportional to its size.
+pub fn checksum_region<M>(
+    iface: &mut M,
+    base: u64,
+    address: u64,
+    words: usize,
+    algo: ChecksumAlgo,
+) -> Result<u32, MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    let mut scratch = [0u32; CHUNK_WORDS];
+    let mut state = algo.initial();
+    let mut done = 0;
+
+    while done < words {
+        let count = (words - done).min(CHUNK_WORDS);
+        let chunk = &mut scratch[..count];
+        read_block_into(iface, base, address + done as u64 * 4, chunk)?;
+        state = algo.update(state, chunk);
+        done += count;
+    }
+
+    Ok(algo.finish(state))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{checksum_region, crc32_update, ChecksumAlgo};
+    use crate::architecture::arm::ap_v2::memory_ap::mock::MockMemoryAp;
+
+    const BASE: u64 = 0x2000;
+
+    #[test]
+    fn crc32_matches_the_check_value() {
+        assert_eq!(!crc32_update(u32::MAX, *b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn region_checksums_match_the_memory() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        // More than one scratch buffer, starting in the middle of an auto-increment block.
+        let (address, words) = (0x204, 700);
+        let bytes = mock.bytes(address as u64, words * 4);
+        let sum = mock
+            .words(address as u64, words)
+            .iter()
+            .fold(0u32, |sum, word| sum.wrapping_add(*word));
+
+        let crc = checksum_region(&mut mock, BASE, address as u64, words, ChecksumAlgo::Crc32);
+        assert_eq!(crc.unwrap(), !crc32_update(u32::MAX, bytes));
+        let checksum = checksum_region(&mut mock, BASE, address as u64, words, ChecksumAlgo::Sum32);
+        assert_eq!(checksum.unwrap(), sum);
+    }
+
+    #[test]
+    fn empty_region_checksum_is_zero() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        assert_eq!(
+            checksum_region(&mut mock, BASE, 0x100, 0, ChecksumAlgo::Crc32).unwrap(),
+            0
+        );
+    }
+}
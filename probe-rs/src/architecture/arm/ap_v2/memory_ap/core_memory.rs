@@ -0,0 +1,199 @@
+//! Access to the memory of a core in the byte order of its data.
+
+use crate::{architecture::arm::ArmError, MemoryInterface};
+
+use super::Endianness;
+
+/// Reads and writes the memory of a core through a [`MemoryInterface`], such as the
+/// [`MemoryAp`](super::MemoryAp) mapped at the core's bus, independent of the core's byte order.
+///
+/// All accesses are performed on a little-endian bus. The bytes of each word are arranged
+/// according to the configured byte order. The two words of a 64 bit value are ordered by the
+/// memory interface, e.g. with [`MemoryAp::set_word_order`](super::MemoryAp::set_word_order), so
+/// for a big-endian core both are set to [`Endianness::Big`].
+pub struct CoreMemory<'memory, M: ?Sized> {
+    memory: &'memory mut M,
+    byte_order: Endianness,
+}
+
+impl<'memory, M> CoreMemory<'memory, M>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    /// Creates a wrapper accessing `memory` in `byte_order`.
+    pub fn new(memory: &'memory mut M, byte_order: Endianness) -> Self {
+        Self { memory, byte_order }
+    }
+
+    /// The byte order in which values are read and written.
+    pub fn byte_order(&self) -> Endianness {
+        self.byte_order
+    }
+
+    /// Reads the 32 bit value at `address`.
+    pub fn read_word_32(&mut self, address: u64) -> Result<u32, ArmError> {
+        let mut value = [0];
+        self.read_32(address, &mut value)?;
+        Ok(value[0])
+    }
+
+    /// Reads the 64 bit value at `address`, which must be 8 byte aligned.
+    pub fn read_word_64(&mut self, address: u64) -> Result<u64, ArmError> {
+        let mut value = [0];
+        self.read_64(address, &mut value)?;
+        Ok(value[0])
+    }
+
+    /// Writes the 32 bit value `value` to `address`.
+    pub fn write_word_32(&mut self, address: u64, value: u32) -> Result<(), ArmError> {
+        self.write_32(address, &[value])
+    }
+
+    /// Writes the 64 bit value `value` to `address`, which must be 8 byte aligned.
+    pub fn write_word_64(&mut self, address: u64, value: u64) -> Result<(), ArmError> {
+        self.write_64(address, &[value])
+    }
+
+    /// Reads the consecutive 32 bit values starting at `address` into `data`.
+    pub fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), ArmError> {
+        self.memory.read_32(address, data)?;
+        for value in data.iter_mut() {
+            *value = self.byte_order.apply(*value);
+        }
+        Ok(())
+    }
+
+    /// Reads the consecutive 64 bit values starting at `address` into `data`.
+    pub fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), ArmError> {
+        if address % 8 != 0 {
+            return Err(ArmError::alignment_error(address, 8));
+        }
+        self.memory.read_64(address, data)?;
+        for value in data.iter_mut() {
+            *value = self.convert_halves(*value);
+        }
+        Ok(())
+    }
+
+    /// Writes the 32 bit values of `data` to consecutive addresses starting at `address`.
+    pub fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), ArmError> {
+        let words: Vec<u32> = data
+            .iter()
+            .map(|value| self.byte_order.apply(*value))
+            .collect();
+        self.memory.write_32(address, &words)
+    }
+
+    /// Writes the 64 bit values of `data` to consecutive addresses starting at `address`.
+    pub fn write_64(&mut self, address: u64, data: &[u64]) -> Result<(), ArmError> {
+        if address % 8 != 0 {
+            return Err(ArmError::alignment_error(address, 8));
+        }
+        let values: Vec<u64> = data
+            .iter()
+            .map(|value| self.convert_halves(*value))
+            .collect();
+        self.memory.write_64(address, &values)
+    }
+
+    /// Converts both words of a 64 bit value between the little-endian bus and the byte order
+    /// of the core, leaving their order to the memory interface.
+    fn convert_halves(&self, value: u64) -> u64 {
+        let low = self.byte_order.apply(value as u32);
+        let high = self.byte_order.apply((value >> 32) as u32);
+        (u64::from(high) << 32) | u64::from(low)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CoreMemory;
+    use crate::architecture::arm::ap_v2::{
+        memory_ap::{mock::MockMemoryAp, Endianness, MemoryAp},
+        registers::CFG,
+    };
+
+    const BASE: u64 = 0x2000;
+
+    fn bytes(mock: &MockMemoryAp, address: u64) -> [u8; 8] {
+        mock.bytes(address, 8).try_into().unwrap()
+    }
+
+    #[test]
+    fn words_are_composed_in_byte_order() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let expected = bytes(&mock, 0x40);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+
+        let mut memory = CoreMemory::new(&mut ap, Endianness::Little);
+        assert_eq!(
+            memory.read_word_64(0x40).unwrap(),
+            u64::from_le_bytes(expected)
+        );
+        assert_eq!(memory.read_word_32(0x40).unwrap(), 0x4443_4241);
+
+        ap.set_word_order(Endianness::Big);
+        let mut memory = CoreMemory::new(&mut ap, Endianness::Big);
+        assert_eq!(
+            memory.read_word_64(0x40).unwrap(),
+            u64::from_be_bytes(expected)
+        );
+        assert_eq!(memory.read_word_32(0x40).unwrap(), 0x4142_4344);
+    }
+
+    #[test]
+    fn words_are_ordered_by_the_handle() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let expected = bytes(&mock, 0x40);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+
+        // Big-endian words, with the lower word at the lower address.
+        let mut memory = CoreMemory::new(&mut ap, Endianness::Big);
+        let low = u32::from_be_bytes(expected[..4].try_into().unwrap());
+        let high = u32::from_be_bytes(expected[4..].try_into().unwrap());
+        assert_eq!(
+            memory.read_word_64(0x40).unwrap(),
+            (u64::from(high) << 32) | u64::from(low)
+        );
+    }
+
+    #[test]
+    fn written_words_round_trip_in_byte_order() {
+        let value = 0x0102_0304_0506_0708;
+        for byte_order in [Endianness::Little, Endianness::Big] {
+            let mut mock = MockMemoryAp::with_pattern(BASE);
+            let mut ap = MemoryAp::new(&mut mock, BASE);
+            ap.set_word_order(byte_order);
+            let mut memory = CoreMemory::new(&mut ap, byte_order);
+
+            memory.write_64(0x80, &[value, !value]).unwrap();
+            let mut read = [0; 2];
+            memory.read_64(0x80, &mut read).unwrap();
+            assert_eq!(read, [value, !value], "{byte_order:?}");
+
+            let expected = match byte_order {
+                Endianness::Little => value.to_le_bytes(),
+                Endianness::Big => value.to_be_bytes(),
+            };
+            assert_eq!(bytes(&mock, 0x80), expected, "{byte_order:?}");
+        }
+    }
+
+    #[test]
+    fn unaligned_64_bit_accesses_are_rejected() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        let mut memory = CoreMemory::new(&mut ap, Endianness::Little);
+
+        assert!(memory.read_word_64(0x44).is_err());
+        assert!(memory.write_word_64(0x44, 0).is_err());
+    }
+
+    #[test]
+    fn byte_order_follows_cfg() {
+        let mut cfg = CFG::try_from(0).unwrap();
+        assert_eq!(Endianness::of(&cfg), Endianness::Little);
+        cfg.BE = true;
+        assert_eq!(Endianness::of(&cfg), Endianness::Big);
+    }
+}
@@ -0,0 +1,206 @@
+//! A summary of the identity and capabilities of a memory access port.
+
+use std::fmt;
+
+use crate::{
+    architecture::arm::{
+        ap_v2::registers::{DataSize, MemApBusType, CFG, IDR},
+        ArmError,
+    },
+    MemoryInterface,
+};
+
+use super::{AdiVersion, BusKind, MemApCapabilities, MemoryAp, MemoryApError};
+
+/// The identity and capabilities of a memory access port, see [`MemoryAp::describe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApDescription {
+    /// The base address of the AP's register file.
+    pub base: u64,
+    /// The version of the Arm Debug Interface the AP is accessed with.
+    pub adi_version: AdiVersion,
+    /// The identification register of the AP.
+    pub idr: IDR,
+    /// The configuration register of the AP, reporting its extensions.
+    pub cfg: CFG,
+    /// The transfer sizes and increments the AP supports.
+    pub capabilities: MemApCapabilities,
+}
+
+impl ApDescription {
+    /// The name of the designer of the AP, decoded from the JEP106 code in `IDR.DESIGNER`.
+    pub fn designer(&self) -> Option<&'static str> {
+        let designer = self.idr.DESIGNER;
+        jep106::JEP106Code::new((designer >> 7) as u8, (designer & 0x7F) as u8).get()
+    }
+
+    /// The bus behind the AP, decoded from `IDR.TYPE`.
+    pub fn bus_type(&self) -> Option<MemApBusType> {
+        self.idr.bus_type()
+    }
+
+    /// The transfer sizes the AP supports, from the smallest.
+    pub fn data_sizes(&self) -> impl Iterator<Item = DataSize> + '_ {
+        [
+            DataSize::U8,
+            DataSize::U16,
+            DataSize::U32,
+            DataSize::U64,
+            DataSize::U128,
+            DataSize::U256,
+        ]
+        .into_iter()
+        .filter(|size| {
+            (self.capabilities.sub_word != Some(false) || *size >= DataSize::U32)
+                && *size <= self.capabilities.max_size
+        })
+    }
+}
+
+impl fmt::Display for ApDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Memory AP at {:#x} ({:?})", self.base, self.adi_version)?;
+        writeln!(
+            f,
+            "  Designer: {} ({:#05x})",
+            self.designer().unwrap_or("unknown"),
+            self.idr.DESIGNER
+        )?;
+        writeln!(
+            f,
+            "  Revision: {}, variant: {}",
+            self.idr.REVISION, self.idr.VARIANT
+        )?;
+        match (self.idr.CLASS, self.bus_type()) {
+            (IDR::MEM_AP_CLASS, Some(bus)) => writeln!(f, "  Class: MEM-AP, bus: {bus:?}")?,
+            (IDR::MEM_AP_CLASS, None) => {
+                writeln!(f, "  Class: MEM-AP, bus: reserved ({:#x})", self.idr.TYPE)?
+            }
+            (class, _) => writeln!(f, "  Class: {class:#x}")?,
+        }
+
+        let extensions: Vec<_> = [
+            (self.cfg.LA, "LA"),
+            (self.cfg.LD, "LD"),
+            (self.cfg.BE, "BE"),
+            (self.cfg.RME, "RME"),
+        ]
+        .into_iter()
+        .filter_map(|(implemented, name)| implemented.then_some(name))
+        .collect();
+        if extensions.is_empty() {
+            writeln!(f, "  Extensions: none")?;
+        } else {
+            writeln!(f, "  Extensions: {}", extensions.join(" "))?;
+        }
+
+        let sizes: Vec<_> = self.data_sizes().map(|size| format!("{size:?}")).collect();
+        write!(f, "  Data sizes: {}", sizes.join(" "))?;
+        if self.capabilities.packed == Some(true) {
+            write!(f, ", packed")?;
+        }
+        Ok(())
+    }
+}
+
+impl<M> MemoryAp<'_, M>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    /// Describes the identity and capabilities of this AP, e.g. for an info command.
+    ///
+    /// The first call reads the IDR and CFG and probes the capabilities of the AP, see
+    /// [`MemApCapabilities::probe`], which become the capabilities of this handle. Later calls
+    /// return the same description without any transfers. The kind of bus is detected from the
+    /// IDR if it is not known yet, see [`MemoryAp::set_bus`]. An APB-AP is not probed, as it
+    /// only supports the word transfers its bus restricts the capabilities to.
+    ///
+    /// This is not `#[must_use]`: calling it only to adopt the probed capabilities, and dropping
+    /// the description, is fine.
+    pub fn describe(&mut self) -> Result<ApDescription, MemoryApError> {
+        if let Some(description) = self.state.description {
+            return Ok(description);
+        }
+
+        let idr: IDR = self.read_register()?;
+        let cfg: CFG = self.read_register()?;
+        if !self.state.bus_known {
+            self.set_bus(BusKind::of(&idr));
+        }
+        let capabilities = match self.config.bus {
+            BusKind::Apb => self.config.capabilities,
+            BusKind::Generic => MemApCapabilities::probe(self.iface, self.config.base)?,
+        };
+        self.set_capabilities(capabilities);
+
+        let description = ApDescription {
+            base: self.config.base,
+            adi_version: self.config.adi_version,
+            idr,
+            cfg,
+            capabilities,
+        };
+        self.state.description = Some(description);
+        Ok(description)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::architecture::arm::ap_v2::{
+        memory_ap::{mock::MockMemoryAp, BusKind, MemoryAp},
+        registers::{DataSize, MemApBusType, Register, CFG, CSW, IDR},
+    };
+
+    const BASE: u64 = 0x2000;
+
+    #[test]
+    fn description_summarizes_the_ap() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.set_register(CFG::ADDRESS, 0b101);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+
+        let description = ap.describe().unwrap();
+        assert_eq!(description.designer(), Some("ARM Ltd"));
+        assert_eq!(description.bus_type(), Some(MemApBusType::AmbaAhb5));
+        assert_eq!(
+            description.data_sizes().collect::<Vec<_>>(),
+            [
+                DataSize::U8,
+                DataSize::U16,
+                DataSize::U32,
+                DataSize::U64,
+                DataSize::U128,
+                DataSize::U256
+            ]
+        );
+        assert_eq!(
+            description.to_string(),
+            "Memory AP at 0x2000 (V6)\n\
+             \x20 Designer: ARM Ltd (0x23b)\n\
+             \x20 Revision: 0, variant: 0\n\
+             \x20 Class: MEM-AP, bus: AmbaAhb5\n\
+             \x20 Extensions: LD BE\n\
+             \x20 Data sizes: U8 U16 U32 U64 U128 U256, packed"
+        );
+    }
+
+    #[test]
+    fn apb_aps_are_not_probed() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        // AMBA APB4 or APB5, with large data, which the bus does not allow anyway.
+        mock.set_register(IDR::ADDRESS, 0x0477_0006);
+        mock.set_register(CFG::ADDRESS, 0b100);
+        // Probing reads the CSW through the parent interface, which fails here.
+        mock.faulting_registers.insert(CSW::ADDRESS);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+
+        let description = ap.describe().unwrap();
+        assert_eq!(ap.config().bus, BusKind::Apb);
+        assert_eq!(
+            description.data_sizes().collect::<Vec<_>>(),
+            [DataSize::U32]
+        );
+        assert_eq!(description.capabilities.packed, Some(false));
+    }
+}
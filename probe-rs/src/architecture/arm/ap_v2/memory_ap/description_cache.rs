@@ -0,0 +1,272 @@
+//! Keeping the descriptions of memory access ports across reconnects to the same target.
+
+use std::collections::HashMap;
+
+use crate::{
+    architecture::arm::{
+        ap_v2::registers::DataSize,
+        dp::{DpAccess, DpAddress, DPIDR, TARGETID},
+        ArmError, DapAccess,
+    },
+    MemoryInterface,
+};
+
+use super::{ApDescription, BusKind, MemoryAp, MemoryApError};
+
+/// The identity of the target behind a debug port, which keys a [`DescriptionCache`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TargetIdentity {
+    /// The raw value of DPIDR, holding the designer, part number and version of the debug port.
+    pub dpidr: u32,
+    /// The raw value of TARGETID, holding the designer and part number of the target. Only debug
+    /// ports of version 2 and later implement it.
+    pub targetid: Option<u32>,
+}
+
+impl TargetIdentity {
+    /// Reads the identity of the target behind the debug port `dp`.
+    pub fn read<D>(iface: &mut D, dp: DpAddress) -> Result<Self, ArmError>
+    where
+        D: DapAccess,
+    {
+        let dpidr: DPIDR = iface.read_dp_register(dp)?;
+        let targetid = if dpidr.version() >= 2 {
+            Some(iface.read_dp_register::<TARGETID>(dp)?.into())
+        } else {
+            None
+        };
+        Ok(Self {
+            dpidr: dpidr.into(),
+            targetid,
+        })
+    }
+}
+
+/// The descriptions of the memory access ports of one target, kept for a session so that
+/// reconnecting to the same target, e.g. in an edit-flash-debug loop, does not read the IDR and
+/// CFG and probe the capabilities of every AP again.
+///
+/// The descriptions are kept per debug port and AP base address, and belong to the
+/// [`TargetIdentity`] passed to [`DescriptionCache::connect`], which has to be called on every
+/// connection. If the identity differs from the previous one,
+/// the cached descriptions are dropped and read again.
+///
+/// Two boards with the same part number have the same identity, so a cached description may
+/// belong to another board of the same kind. For setups where the target can be swapped while
+/// connected, or the identity is not trusted, disable the cache with
+/// [`DescriptionCache::set_enabled`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescriptionCache {
+    enabled: bool,
+    identity: Option<TargetIdentity>,
+    descriptions: HashMap<(DpAddress, u64), ApDescription>,
+}
+
+impl DescriptionCache {
+    /// Creates an empty, enabled cache.
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            identity: None,
+            descriptions: HashMap::new(),
+        }
+    }
+
+    /// Enables or disables the cache. A disabled cache is emptied, and keeps no descriptions.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.clear();
+        }
+    }
+
+    /// Returns whether the cache keeps descriptions.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Records a connection to the target with `identity`.
+    ///
+    /// Returns whether the cached descriptions were kept, which is the case if the identity is
+    /// the same as on the previous connection.
+    pub fn connect(&mut self, identity: TargetIdentity) -> bool {
+        let kept = self.identity == Some(identity);
+        if !kept {
+            self.descriptions.clear();
+            self.identity = Some(identity);
+        }
+        kept
+    }
+
+    /// Returns the cached description of the AP at `base` behind the debug port `dp`.
+    pub fn get(&self, dp: DpAddress, base: u64) -> Option<&ApDescription> {
+        self.descriptions.get(&(dp, base))
+    }
+
+    /// Caches the `description` of an AP behind the debug port `dp` of the connected target.
+    ///
+    /// Nothing is cached if the cache is disabled or no target is connected.
+    pub fn insert(&mut self, dp: DpAddress, description: ApDescription) {
+        if self.enabled && self.identity.is_some() {
+            self.descriptions
+                .insert((dp, description.base), description);
+        }
+    }
+
+    /// Drops all cached descriptions and the identity of the connected target.
+    pub fn clear(&mut self) {
+        self.identity = None;
+        self.descriptions.clear();
+    }
+}
+
+impl Default for DescriptionCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> MemoryAp<'_, M>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    /// Describes this AP behind the debug port `dp` like [`MemoryAp::describe`], but takes the
+    /// description from `cache` if it holds one for the connected target, without any transfers.
+    ///
+    /// A description read from the AP is added to `cache`. Either way, its capabilities become
+    /// the capabilities of this handle. A cached description also takes the place of the IDR and
+    /// CFG reads which would otherwise detect the bus, the large physical address extension and
+    /// 64 bit transfers on the first memory transfer.
+    pub fn describe_cached(
+        &mut self,
+        cache: &mut DescriptionCache,
+        dp: DpAddress,
+    ) -> Result<ApDescription, MemoryApError> {
+        if let Some(description) = cache.get(dp, self.config.base).copied() {
+            if !self.state.bus_known {
+                self.set_bus(BusKind::of(&description.idr));
+            }
+            self.set_capabilities(description.capabilities);
+            self.state.large_address = Some(description.cfg.LA);
+            self.state.native_64bit = Some(description.capabilities.max_size >= DataSize::U64);
+            self.state.description = Some(description);
+            return Ok(description);
+        }
+
+        let description = self.describe()?;
+        cache.insert(dp, description);
+        Ok(description)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DescriptionCache, TargetIdentity};
+    use crate::{
+        architecture::arm::{
+            ap_v2::{
+                memory_ap::{mock::MockMemoryAp, MemoryAp},
+                registers::{Register, CFG, IDR},
+            },
+            dp::DpAddress,
+        },
+        MemoryInterface,
+    };
+
+    const BASE: u64 = 0x2000;
+    const DP: DpAddress = DpAddress::Default;
+
+    const BOARD: TargetIdentity = TargetIdentity {
+        dpidr: 0x6ba0_2477,
+        targetid: Some(0x0001_0927),
+    };
+
+    /// Connects to the target with `identity` and describes the AP, returning its IDR.
+    fn reconnect(
+        mock: &mut MockMemoryAp,
+        cache: &mut DescriptionCache,
+        identity: TargetIdentity,
+    ) -> IDR {
+        cache.connect(identity);
+        MemoryAp::new(mock, BASE)
+            .describe_cached(cache, DP)
+            .unwrap()
+            .idr
+    }
+
+    #[test]
+    fn reconnects_reuse_the_description_of_the_same_target() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut cache = DescriptionCache::new();
+        let idr = reconnect(&mut mock, &mut cache, BOARD);
+        assert_eq!(u32::from(idr), 0x0477_0005);
+
+        // The registers are not read again for the same target.
+        mock.set_register(IDR::ADDRESS, 0x0477_0006);
+        mock.set_register(CFG::ADDRESS, 0b10);
+        let description = {
+            cache.connect(BOARD);
+            let mut ap = MemoryAp::new(&mut mock, BASE);
+            let description = ap.describe_cached(&mut cache, DP).unwrap();
+            assert_eq!(ap.config().capabilities, description.capabilities);
+            description
+        };
+        assert_eq!(u32::from(description.idr), 0x0477_0005);
+        assert!(!description.cfg.LA);
+
+        // A differing target ID forces a re-read.
+        let other = TargetIdentity {
+            targetid: Some(0x0001_1927),
+            ..BOARD
+        };
+        assert_eq!(
+            u32::from(reconnect(&mut mock, &mut cache, other)),
+            0x0477_0006
+        );
+        assert!(cache.get(DP, BASE).unwrap().cfg.LA);
+
+        // The same base address behind another debug port is another AP.
+        assert!(cache.get(DpAddress::Multidrop(0x0100_2927), BASE).is_none());
+    }
+
+    #[test]
+    fn cache_hits_do_not_access_the_ap() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut cache = DescriptionCache::new();
+        reconnect(&mut mock, &mut cache, BOARD);
+
+        mock.accesses = 0;
+        cache.connect(BOARD);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.describe_cached(&mut cache, DP).unwrap();
+        drop(ap);
+        assert_eq!(mock.accesses, 0);
+
+        // Neither are the IDR and CFG read by the first memory transfers.
+        mock.faulting_registers.extend([IDR::ADDRESS, CFG::ADDRESS]);
+        let expected = mock.words(0x40, 2);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.describe_cached(&mut cache, DP).unwrap();
+        assert_eq!(ap.read_block(0x40, 2).unwrap(), expected);
+        assert_eq!(
+            ap.read_word_64(0x40).unwrap(),
+            (u64::from(expected[1]) << 32) | u64::from(expected[0])
+        );
+    }
+
+    #[test]
+    fn disabled_cache_always_reads_the_registers() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut cache = DescriptionCache::new();
+        reconnect(&mut mock, &mut cache, BOARD);
+        cache.set_enabled(false);
+        assert!(cache.get(DP, BASE).is_none());
+
+        mock.set_register(IDR::ADDRESS, 0x0477_0006);
+        assert_eq!(
+            u32::from(reconnect(&mut mock, &mut cache, BOARD)),
+            0x0477_0006
+        );
+        assert!(cache.get(DP, BASE).is_none());
+    }
+}
@@ -0,0 +1,247 @@
+//! Recording the register transfers of a memory access port without issuing them.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+};
+
+use crate::{
+    architecture::arm::{
+        ap_v2::registers::{Register, BD0, BD3, CSW, DRW},
+        ArmError,
+    },
+    MemoryInterface,
+};
+
+use super::raw_register_name;
+
+/// A register transfer recorded by a [`DryRunMemoryAp`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlannedTransfer {
+    /// A read of the register at `offset`.
+    Read {
+        /// The offset of the register in the AP's register file.
+        offset: u16,
+    },
+    /// A write of `value` to the register at `offset`.
+    Write {
+        /// The offset of the register in the AP's register file.
+        offset: u16,
+        /// The written value.
+        value: u32,
+    },
+}
+
+impl PlannedTransfer {
+    /// The offset of the accessed register in the AP's register file.
+    pub fn offset(&self) -> u16 {
+        match self {
+            PlannedTransfer::Read { offset } | PlannedTransfer::Write { offset, .. } => *offset,
+        }
+    }
+
+    /// The name of the accessed register, or `"raw"` if it has no type.
+    pub fn register_name(&self) -> &'static str {
+        raw_register_name(self.offset())
+    }
+}
+
+impl fmt::Display for PlannedTransfer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlannedTransfer::Read { .. } => write!(f, "read {}", self.register_name()),
+            PlannedTransfer::Write { value, .. } => {
+                write!(f, "write {} {value:#010x}", self.register_name())
+            }
+        }
+    }
+}
+
+/// A [`MemoryInterface`] for the parent of a memory access port which records the register
+/// transfers instead of issuing them, e.g. to check the exact transfers of a block read in a
+/// test, or to generate a transfer script.
+///
+/// Reads of the data registers (DRW and BD0 to BD3) return the values queued with
+/// [`DryRunMemoryAp::push_read`] in order, and zero once the queue is empty. Reads of the other
+/// registers return the value last written to them, or set with
+/// [`DryRunMemoryAp::set_register`], so the CSW reads back as configured. The CSW starts out at
+/// its reset value, all other registers at zero.
+///
+/// Only 32 bit accesses to the register file of the AP at `base` are supported, as issued by the
+/// functions of this module and [`MemoryAp`](super::MemoryAp) for ADIv6.
+#[derive(Debug, Clone)]
+pub struct DryRunMemoryAp {
+    base: u64,
+    transfers: Vec<PlannedTransfer>,
+    registers: HashMap<u16, u32>,
+    reads: VecDeque<u32>,
+}
+
+impl DryRunMemoryAp {
+    /// Creates a recorder for the AP whose register file is at `base`.
+    pub fn new(base: u64) -> Self {
+        Self {
+            base,
+            transfers: Vec::new(),
+            registers: HashMap::from([(CSW::ADDRESS, CSW::default().into())]),
+            reads: VecDeque::new(),
+        }
+    }
+
+    /// Queues `value` to be returned by a following read of a data register.
+    pub fn push_read(&mut self, value: u32) {
+        self.reads.push_back(value);
+    }
+
+    /// Sets the value reads of the register at `offset` return until it is written.
+    pub fn set_register(&mut self, offset: u16, value: u32) {
+        self.registers.insert(offset, value);
+    }
+
+    /// The transfers recorded so far, in the order they were issued.
+    pub fn transfers(&self) -> &[PlannedTransfer] {
+        &self.transfers
+    }
+
+    /// Returns the transfers recorded so far, and starts a new recording.
+    pub fn take_transfers(&mut self) -> Vec<PlannedTransfer> {
+        std::mem::take(&mut self.transfers)
+    }
+
+    fn offset(&self, address: u64) -> u16 {
+        address.wrapping_sub(self.base) as u16
+    }
+
+    fn read(&mut self, address: u64) -> u32 {
+        let offset = self.offset(address);
+        self.transfers.push(PlannedTransfer::Read { offset });
+        match offset {
+            DRW::ADDRESS | BD0::ADDRESS..=BD3::ADDRESS => self.reads.pop_front().unwrap_or(0),
+            _ => self.registers.get(&offset).copied().unwrap_or(0),
+        }
+    }
+
+    fn write(&mut self, address: u64, value: u32) {
+        let offset = self.offset(address);
+        self.transfers
+            .push(PlannedTransfer::Write { offset, value });
+        self.registers.insert(offset, value);
+    }
+}
+
+impl MemoryInterface<ArmError> for DryRunMemoryAp {
+    fn supports_native_64bit_access(&mut self) -> bool {
+        false
+    }
+
+    fn read_64(&mut self, _address: u64, _data: &mut [u64]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(64))
+    }
+
+    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), ArmError> {
+        for (i, word) in data.iter_mut().enumerate() {
+            *word = self.read(address + i as u64 * 4);
+        }
+        Ok(())
+    }
+
+    fn read_16(&mut self, _address: u64, _data: &mut [u16]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(16))
+    }
+
+    fn read_8(&mut self, _address: u64, _data: &mut [u8]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(8))
+    }
+
+    fn write_64(&mut self, _address: u64, _data: &[u64]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(64))
+    }
+
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), ArmError> {
+        for (i, word) in data.iter().enumerate() {
+            self.write(address + i as u64 * 4, *word);
+        }
+        Ok(())
+    }
+
+    fn write_16(&mut self, _address: u64, _data: &[u16]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(16))
+    }
+
+    fn write_8(&mut self, _address: u64, _data: &[u8]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(8))
+    }
+
+    fn supports_8bit_transfers(&self) -> Result<bool, ArmError> {
+        Ok(false)
+    }
+
+    fn flush(&mut self) -> Result<(), ArmError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DryRunMemoryAp, PlannedTransfer};
+    use crate::architecture::arm::ap_v2::{
+        memory_ap::{read_block, MemoryAp, AUTO_INCREMENT_BLOCK_SIZE},
+        registers::{Register, CFG, CSW, DRW, TAR, TAR2},
+    };
+
+    const BASE: u64 = 0x2000;
+
+    #[test]
+    fn read_block_reprograms_the_tar_at_the_wrap_boundary() {
+        let mut dry_run = DryRunMemoryAp::new(BASE);
+        dry_run.push_read(0x1111_1111);
+        dry_run.push_read(0x2222_2222);
+
+        let address = AUTO_INCREMENT_BLOCK_SIZE - 4;
+        let data = read_block(&mut dry_run, BASE, address, 2).unwrap();
+        assert_eq!(data, [0x1111_1111, 0x2222_2222]);
+
+        let write = |offset, value| PlannedTransfer::Write { offset, value };
+        let read = |offset| PlannedTransfer::Read { offset };
+        assert_eq!(
+            dry_run.transfers(),
+            [
+                read(CSW::ADDRESS),
+                write(CSW::ADDRESS, 0x8000_0012),
+                write(TAR::ADDRESS, address as u32),
+                // TAR2 may hold the upper word of an earlier address.
+                write(TAR2::ADDRESS, 0),
+                read(DRW::ADDRESS),
+                write(TAR::ADDRESS, AUTO_INCREMENT_BLOCK_SIZE as u32),
+                read(DRW::ADDRESS),
+                write(CSW::ADDRESS, 0x8000_0012),
+            ]
+        );
+        assert_eq!(dry_run.transfers()[2].to_string(), "write TAR 0x000003fc");
+        assert_eq!(dry_run.take_transfers().len(), 8);
+        assert!(dry_run.transfers().is_empty());
+    }
+
+    #[test]
+    fn tar2_is_only_written_when_the_upper_word_changes() {
+        let mut dry_run = DryRunMemoryAp::new(BASE);
+        // CFG.LA, so that the AP accepts addresses above 4 GB.
+        dry_run.set_register(CFG::ADDRESS, 0b10);
+        let mut ap = MemoryAp::new(&mut dry_run, BASE);
+        let mut word = [0];
+        for address in [0x1000, 0x1_0000_1000, 0x1_0000_2000, 0x2000, 0x3000] {
+            ap.read_block_into(address, &mut word).unwrap();
+        }
+
+        let tar2_writes: Vec<_> = dry_run
+            .transfers()
+            .iter()
+            .filter_map(|transfer| match *transfer {
+                PlannedTransfer::Write { offset, value } if offset == TAR2::ADDRESS => Some(value),
+                _ => None,
+            })
+            .collect();
+        // The first write sets the unknown TAR2.
+        assert_eq!(tar2_writes, [0, 1, 0]);
+    }
+}
@@ -0,0 +1,160 @@
+//! Reading all registers of a memory access port for diagnostics.
+
+use crate::{
+    architecture::arm::{
+        ap_v2::registers::{LossyFields, Register, BASE, BASE2, CFG, CSW, IDR, TAR, TAR2},
+        ArmError,
+    },
+    MemoryInterface,
+};
+
+use super::{MemoryAp, MemoryApError};
+
+/// A register read by [`MemoryAp::try_read_all`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterRead<R> {
+    /// The raw value read from the register.
+    pub raw: u32,
+    /// The parsed register. Fields which did not parse hold their fallback value.
+    pub register: R,
+    /// The fields which did not parse, see [`Register::from_raw_lossy`].
+    pub lossy: LossyFields,
+}
+
+impl<R: Register> RegisterRead<R> {
+    fn parse(raw: u32) -> Self {
+        let (register, lossy) = R::from_raw_lossy(raw);
+        Self {
+            raw,
+            register,
+            lossy,
+        }
+    }
+
+    /// Returns whether some fields of the register did not parse.
+    pub fn is_lossy(&self) -> bool {
+        !self.lossy.is_empty()
+    }
+}
+
+/// The result of reading one register in [`MemoryAp::try_read_all`]: `None` if the AP does not
+/// implement the register, otherwise the register or the error reading it.
+pub type RegisterReadResult<R> = Option<Result<RegisterRead<R>, MemoryApError>>;
+
+/// The registers of a memory access port, each read independently, see
+/// [`MemoryAp::try_read_all`].
+///
+/// The data registers are not included, as reading them transfers memory.
+#[derive(Debug)]
+pub struct ApRegisterReadResult {
+    /// The control and status word register.
+    pub csw: RegisterReadResult<CSW>,
+    /// The lower word of the transfer address register.
+    pub tar: RegisterReadResult<TAR>,
+    /// The upper word of the transfer address register, if the AP implements large physical
+    /// addresses.
+    pub tar2: RegisterReadResult<TAR2>,
+    /// The configuration register.
+    pub cfg: RegisterReadResult<CFG>,
+    /// The lower word of the debug base address register.
+    pub base: RegisterReadResult<BASE>,
+    /// The upper word of the debug base address register, if the AP implements large physical
+    /// addresses.
+    pub base2: RegisterReadResult<BASE2>,
+    /// The identification register.
+    pub idr: RegisterReadResult<IDR>,
+}
+
+impl ApRegisterReadResult {
+    /// Returns whether every implemented register was read.
+    pub fn is_complete(&self) -> bool {
+        fn ok<R>(result: &RegisterReadResult<R>) -> bool {
+            !matches!(result, Some(Err(_)))
+        }
+        ok(&self.csw)
+            && ok(&self.tar)
+            && ok(&self.tar2)
+            && ok(&self.cfg)
+            && ok(&self.base)
+            && ok(&self.base2)
+            && ok(&self.idr)
+    }
+}
+
+impl<M> MemoryAp<'_, M>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    /// Reads all registers of this AP, continuing after registers which fail to read.
+    ///
+    /// This is a register dump for diagnosing partially broken targets: a register which faults
+    /// does not hide the others, and a register with invalid fields is parsed leniently and
+    /// flagged, see [`RegisterRead::is_lossy`]. TAR2 and BASE2 are only read if `CFG.LA` is set,
+    /// or if CFG itself could not be read.
+    pub fn try_read_all(&mut self) -> ApRegisterReadResult {
+        let cfg = self.try_read::<CFG>();
+        let large_address = match &cfg {
+            Ok(cfg) => cfg.register.LA,
+            Err(_) => true,
+        };
+
+        ApRegisterReadResult {
+            csw: Some(self.try_read()),
+            tar: Some(self.try_read()),
+            tar2: large_address.then(|| self.try_read()),
+            cfg: Some(cfg),
+            base: Some(self.try_read()),
+            base2: large_address.then(|| self.try_read()),
+            idr: Some(self.try_read()),
+        }
+    }
+
+    fn try_read<R: Register>(&mut self) -> Result<RegisterRead<R>, MemoryApError> {
+        Ok(RegisterRead::parse(self.read_register_raw::<R>()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::architecture::arm::ap_v2::{
+        memory_ap::{mock::MockMemoryAp, MemoryAp},
+        registers::{DataSize, Register, CFG, CSW},
+    };
+
+    const BASE: u64 = 0x2000;
+
+    #[test]
+    fn registers_are_read_independently() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        // A reserved CSW.SIZE.
+        mock.set_register(CSW::ADDRESS, 0x8000_0057);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+
+        let registers = ap.try_read_all();
+        assert!(registers.is_complete());
+        let csw = registers.csw.unwrap().unwrap();
+        assert!(csw.is_lossy());
+        assert_eq!(csw.raw, 0x8000_0057);
+        assert_eq!(csw.register.SIZE, DataSize::U32);
+        assert!(!registers.idr.unwrap().unwrap().is_lossy());
+        // The AP does not implement large physical addresses.
+        assert!(registers.tar2.is_none());
+        assert!(registers.base2.is_none());
+    }
+
+    #[test]
+    fn failed_reads_do_not_hide_the_other_registers() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.set_register(CFG::ADDRESS, 0b10);
+        mock.faulting_registers.insert(CFG::ADDRESS);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+
+        let registers = ap.try_read_all();
+        assert!(!registers.is_complete());
+        assert!(matches!(registers.cfg, Some(Err(_))));
+        // Without CFG, TAR2 and BASE2 might be implemented.
+        assert!(registers.tar2.unwrap().is_ok());
+        assert!(registers.base2.unwrap().is_ok());
+        assert_eq!(registers.csw.unwrap().unwrap().raw, 0x8000_0052);
+    }
+}
@@ -0,0 +1,170 @@
+//! Restoring the transfer configuration of a memory access port.
+
+use std::ops::{Deref, DerefMut};
+
+use crate::{
+    architecture::arm::{
+        ap_v2::registers::{AddressIncrement, DataSize, CSW},
+        ArmError,
+    },
+    MemoryInterface,
+};
+
+use super::{MemoryAp, MemoryApError};
+
+/// Restores the CSW of a [`MemoryAp`] when dropped, see [`MemoryAp::csw_guard`].
+///
+/// The guard dereferences to the handle, so the AP can be reconfigured and accessed through it.
+#[must_use = "the CSW is restored as soon as the guard is dropped"]
+pub struct CswGuard<'ap, 'iface, M: MemoryInterface<ArmError> + ?Sized> {
+    ap: &'ap mut MemoryAp<'iface, M>,
+    saved: Option<CSW>,
+}
+
+impl<M> CswGuard<'_, '_, M>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    /// The CSW which is restored.
+    pub fn saved(&self) -> CSW {
+        self.saved
+            .expect("the CSW is only taken when the guard is consumed")
+    }
+
+    /// Configures the transfer size and address increment of the next DRW accesses, keeping the
+    /// other fields of the saved CSW.
+    ///
+    /// The CSW is validated against the capabilities of the AP first, see [`CSW::validate`].
+    pub(super) fn configure(
+        &mut self,
+        size: DataSize,
+        increment: AddressIncrement,
+    ) -> Result<(), MemoryApError> {
+        let mut csw = self.saved();
+        csw.SIZE = size;
+        csw.AddrInc = increment;
+        csw.DbgSwEnable = true;
+        csw.validate(&self.ap.config.capabilities)?;
+        self.ap.write_register(csw)
+    }
+
+    /// Restores the saved CSW now, returning the error the drop would only log.
+    pub fn restore(mut self) -> Result<(), MemoryApError> {
+        match self.saved.take() {
+            Some(csw) => self.ap.write_register_value::<CSW>(csw.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'iface, M> Deref for CswGuard<'_, 'iface, M>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    type Target = MemoryAp<'iface, M>;
+
+    fn deref(&self) -> &Self::Target {
+        self.ap
+    }
+}
+
+impl<M> DerefMut for CswGuard<'_, '_, M>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.ap
+    }
+}
+
+impl<M> Drop for CswGuard<'_, '_, M>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    fn drop(&mut self) {
+        if let Some(csw) = self.saved.take() {
+            if let Err(err) = self.ap.write_register_value::<CSW>(csw.into()) {
+                tracing::warn!(
+                    "Failed to restore the CSW of the AP at {:#x}: {err}",
+                    self.ap.config.base
+                );
+            }
+        }
+    }
+}
+
+impl<'iface, M> MemoryAp<'iface, M>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    /// Saves the CSW and returns a guard which writes it back when dropped.
+    ///
+    /// This keeps helpers which change e.g. `CSW.SIZE` or `CSW.AddrInc` from leaving the AP in
+    /// their configuration, also if they return early with an error:
+    ///
+    /// ```ignore
+    /// let mut ap = ap.csw_guard()?;
+    /// ap.write_register(CSW::builder().size(DataSize::U8).build())?;
+    /// // ...
+    /// ```
+    pub fn csw_guard(&mut self) -> Result<CswGuard<'_, 'iface, M>, MemoryApError> {
+        let saved = self.read_register()?;
+        Ok(CswGuard {
+            ap: self,
+            saved: Some(saved),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::architecture::arm::{
+        ap_v2::{
+            memory_ap::{mock::MockMemoryAp, MemoryAp, MemoryApError},
+            registers::{AddressIncrement, DataSize, CSW},
+        },
+        ArmError,
+    };
+
+    const BASE: u64 = 0x2000;
+
+    /// Changes the CSW through a guard and then fails if `fail` is set.
+    fn reconfigure(ap: &mut MemoryAp<'_, MockMemoryAp>, fail: bool) -> Result<(), MemoryApError> {
+        let mut ap = ap.csw_guard()?;
+        let mut csw: CSW = ap.read_register()?;
+        csw.SIZE = DataSize::U8;
+        csw.AddrInc = AddressIncrement::Off;
+        ap.write_register(csw)?;
+        if fail {
+            return Err(ArmError::alignment_error(0x41, 4).into());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn csw_is_restored_after_success_and_error() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        let initial: CSW = ap.read_register().unwrap();
+
+        reconfigure(&mut ap, false).unwrap();
+        assert_eq!(ap.read_register::<CSW>().unwrap(), initial);
+
+        assert!(reconfigure(&mut ap, true).is_err());
+        assert_eq!(ap.read_register::<CSW>().unwrap(), initial);
+    }
+
+    #[test]
+    fn explicit_restore_reports_the_result() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        let initial: CSW = ap.read_register().unwrap();
+
+        let mut guard = ap.csw_guard().unwrap();
+        guard
+            .write_register(CSW::builder().size(DataSize::U16).build())
+            .unwrap();
+        guard.restore().unwrap();
+        assert_eq!(ap.read_register::<CSW>().unwrap(), initial);
+    }
+}
@@ -0,0 +1,310 @@
+//! Generic memory access through a [`MemoryAp`] handle.
+
+use crate::{
+    architecture::arm::{
+        ap_v2::registers::{AddressIncrement, DataSize, Register, DRW},
+        ArmError,
+    },
+    MemoryInterface,
+};
+
+use super::{
+    transfer::program_transfer_address, MemoryAp, MemoryApError, AUTO_INCREMENT_BLOCK_SIZE,
+};
+
+impl<M> MemoryAp<'_, M>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    /// Returns a handle for the memory access port whose register file is mapped at `base` in the
+    /// address space of this AP.
+    ///
+    /// This reaches APs which are only accessible through another AP, such as a debug AP of a
+    /// subsystem behind an APB-AP. Every register access of the returned handle is tunneled
+    /// through the DRW of this AP, so the register `R` of the nested AP is at
+    /// `base + R::ADDRESS` in this AP's address space.
+    pub fn nested(&mut self, base: u64) -> MemoryAp<'_, Self> {
+        MemoryAp::new(self, base)
+    }
+
+    /// Reads the bytes at `address..address + out.len()` from the words containing them.
+    fn read_bytes(&mut self, address: u64, out: &mut [u8]) -> Result<(), ArmError> {
+        let start = address & !0b11;
+        let end = (address + out.len() as u64).next_multiple_of(4);
+        let words = self.read_block(start, ((end - start) / 4) as usize)?;
+
+        let offset = (address - start) as usize;
+        let bytes = words.iter().flat_map(|word| word.to_le_bytes());
+        for (byte, value) in out.iter_mut().zip(bytes.skip(offset)) {
+            *byte = value;
+        }
+        Ok(())
+    }
+
+    /// Reads the 64 bit values of `data` starting at `address` with 64 bit transfers.
+    ///
+    /// The CSW is configured once, and the TAR only re-programmed when the transfer crosses an
+    /// [`AUTO_INCREMENT_BLOCK_SIZE`] boundary. Every value takes two DRW reads, least
+    /// significant word first.
+    fn read_native_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), MemoryApError> {
+        self.begin_transfer(address, data.len() * 8)?;
+        self.stream_native_64(address, data.len(), |iface, drw, index| {
+            let low = iface.read_word_32(drw)?;
+            let high = iface.read_word_32(drw)?;
+            data[index] = u64::from(low) | (u64::from(high) << 32);
+            Ok(())
+        })
+    }
+
+    /// Writes the 64 bit values of `data` starting at `address` with 64 bit transfers, see
+    /// [`MemoryAp::read_native_64`].
+    fn write_native_64(&mut self, address: u64, data: &[u64]) -> Result<(), MemoryApError> {
+        self.begin_transfer(address, data.len() * 8)?;
+        self.invalidate_cached(address, data.len() * 8);
+        self.stream_native_64(address, data.len(), |iface, drw, index| {
+            iface.write_word_32(drw, data[index] as u32)?;
+            iface.write_word_32(drw, (data[index] >> 32) as u32)
+        })
+    }
+
+    /// Performs `count` 64 bit transfers starting at `address`, calling `transfer` with the
+    /// interface, the address of the DRW and the index of every transfer.
+    fn stream_native_64(
+        &mut self,
+        address: u64,
+        count: usize,
+        mut transfer: impl FnMut(&mut M, u64, usize) -> Result<(), ArmError>,
+    ) -> Result<(), MemoryApError> {
+        if count == 0 {
+            return Ok(());
+        }
+        let base = self.config.base;
+        let mut ap = self.csw_guard()?;
+        ap.configure(DataSize::U64, AddressIncrement::Single)?;
+        let (iface, tar2) = ap.iface_and_tar2();
+        let drw = base + u64::from(DRW::ADDRESS);
+        let mut done = 0;
+        while done < count {
+            let address = address + done as u64 * 8;
+            let to_boundary = (AUTO_INCREMENT_BLOCK_SIZE - address % AUTO_INCREMENT_BLOCK_SIZE) / 8;
+            let end = count.min(done + to_boundary as usize);
+
+            program_transfer_address(iface, base, address, tar2)?;
+            for index in done..end {
+                transfer(iface, drw, index)?;
+            }
+            done = end;
+        }
+        ap.restore()
+    }
+}
+
+/// Lets code which is generic over a [`MemoryInterface`] access memory through the AP.
+///
+/// 64 bit values are transferred with 64 bit accesses if the AP implements the large data
+/// extension (`CFG.LD`) with 64 bit transfers, which is detected once per handle. The bus then
+/// orders the words of each value by its own rules, which is the least significant word at the
+/// lower address on a little-endian bus. Otherwise, each value is transferred as the two words at
+/// its address, in the order set with [`MemoryAp::set_word_order`]. The bytes within each word are
+/// not swapped on either path. Byte and halfword reads are performed as word reads, and byte and
+/// halfword writes fall back to a read-modify-write if the AP does not support them, see
+/// [`write_u8`](super::write_u8).
+impl<M> MemoryInterface<ArmError> for MemoryAp<'_, M>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    fn supports_native_64bit_access(&mut self) -> bool {
+        self.native_64bit().unwrap_or(false)
+    }
+
+    fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), ArmError> {
+        if address % 8 != 0 {
+            return Err(ArmError::alignment_error(address, 8));
+        }
+        if self.native_64bit()? {
+            return Ok(self.read_native_64(address, data)?);
+        }
+        let words = self.read_block(address, data.len() * 2)?;
+        for (value, words) in data.iter_mut().zip(words.chunks_exact(2)) {
+            *value = self.config.word_order.compose([words[0], words[1]]);
+        }
+        Ok(())
+    }
+
+    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), ArmError> {
+        Ok(self.read_block_into(address, data)?)
+    }
+
+    fn read_16(&mut self, address: u64, data: &mut [u16]) -> Result<(), ArmError> {
+        if address % 2 != 0 {
+            return Err(ArmError::alignment_error(address, 2));
+        }
+        let mut bytes = vec![0; data.len() * 2];
+        self.read_bytes(address, &mut bytes)?;
+        for (value, bytes) in data.iter_mut().zip(bytes.chunks_exact(2)) {
+            *value = u16::from_le_bytes([bytes[0], bytes[1]]);
+        }
+        Ok(())
+    }
+
+    fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), ArmError> {
+        self.read_bytes(address, data)
+    }
+
+    fn write_64(&mut self, address: u64, data: &[u64]) -> Result<(), ArmError> {
+        if address % 8 != 0 {
+            return Err(ArmError::alignment_error(address, 8));
+        }
+        if self.native_64bit()? {
+            return Ok(self.write_native_64(address, data)?);
+        }
+        let words: Vec<u32> = data
+            .iter()
+            .flat_map(|value| self.config.word_order.split(*value))
+            .collect();
+        Ok(self.write_block(address, &words)?)
+    }
+
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), ArmError> {
+        Ok(self.write_block(address, data)?)
+    }
+
+    fn write_16(&mut self, address: u64, data: &[u16]) -> Result<(), ArmError> {
+        for (i, value) in data.iter().enumerate() {
+            self.write_u16(address + i as u64 * 2, *value)?;
+        }
+        Ok(())
+    }
+
+    fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), ArmError> {
+        for (i, value) in data.iter().enumerate() {
+            self.write_u8(address + i as u64, *value)?;
+        }
+        Ok(())
+    }
+
+    fn supports_8bit_transfers(&self) -> Result<bool, ArmError> {
+        Ok(true)
+    }
+
+    fn flush(&mut self) -> Result<(), ArmError> {
+        self.invalidate();
+        self.iface.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        architecture::arm::{
+            ap_v2::{
+                memory_ap::{
+                    mock::MockMemoryAp, read_register, Endianness, MemoryAp,
+                    AUTO_INCREMENT_BLOCK_SIZE,
+                },
+                registers::{Register, CFG, CSW},
+            },
+            ArmError,
+        },
+        MemoryInterface,
+    };
+
+    const BASE: u64 = 0x2000;
+
+    #[test]
+    fn generic_accesses_match_memory() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let expected = mock.bytes(0x41, 6);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        let memory: &mut dyn MemoryInterface<ArmError> = &mut ap;
+
+        let mut bytes = [0; 6];
+        memory.read_8(0x41, &mut bytes).unwrap();
+        assert_eq!(bytes.to_vec(), expected);
+
+        memory.write_32(0x80, &[0x1122_3344, 0x5566_7788]).unwrap();
+        memory.write_word_8(0x81, 0xAA).unwrap();
+        assert_eq!(memory.read_word_32(0x80).unwrap(), 0x1122_AA44);
+        assert_eq!(memory.read_word_16(0x86).unwrap(), 0x5566);
+        assert_eq!(memory.read_word_64(0x80).unwrap(), 0x5566_7788_1122_AA44);
+    }
+
+    #[test]
+    fn nested_ap_is_reached_through_its_parent() {
+        const CHILD_BASE: u64 = 0x1_0000;
+        let child = MockMemoryAp::with_pattern(CHILD_BASE);
+        let expected = child.words(0x40, 4);
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.child = Some(Box::new(child));
+
+        let mut parent = MemoryAp::new(&mut mock, BASE);
+        let mut nested = parent.nested(CHILD_BASE);
+        assert_eq!(nested.read_block(0x40, 4).unwrap(), expected);
+        nested.write_block(0x40, &[0xDEAD_BEEF]).unwrap();
+
+        let child = mock.child.as_ref().unwrap();
+        assert_eq!(child.words(0x40, 1), [0xDEAD_BEEF]);
+        // The memory of the parent is untouched.
+        assert_eq!(
+            mock.words(0x40, 4),
+            MockMemoryAp::with_pattern(BASE).words(0x40, 4)
+        );
+    }
+
+    #[test]
+    fn wide_values_use_native_accesses_with_large_data() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.set_register(CFG::ADDRESS, 0b100);
+        let expected = mock.words(0x80, 2);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        // The word order only applies to the fallback path.
+        ap.set_word_order(Endianness::Big);
+        let memory: &mut dyn MemoryInterface<ArmError> = &mut ap;
+
+        assert!(memory.supports_native_64bit_access());
+        assert_eq!(
+            memory.read_word_64(0x80).unwrap(),
+            u64::from(expected[0]) | (u64::from(expected[1]) << 32)
+        );
+        memory.write_word_64(0x88, 0x1111_2222_3333_4444).unwrap();
+        assert_eq!(mock.words(0x88, 2), [0x3333_4444, 0x1111_2222]);
+
+        // A slice is streamed through the DRW, programming the TAR once per block.
+        let expected = mock.words(AUTO_INCREMENT_BLOCK_SIZE - 8, 4);
+        let before: CSW = read_register(&mut mock, BASE).unwrap();
+        mock.tar_writes = 0;
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        let mut values = [0; 2];
+        ap.read_64(AUTO_INCREMENT_BLOCK_SIZE - 8, &mut values)
+            .unwrap();
+        ap.write_64(0x100, &[1, 2, 3]).unwrap();
+        drop(ap);
+        assert_eq!(
+            values,
+            [
+                u64::from(expected[0]) | (u64::from(expected[1]) << 32),
+                u64::from(expected[2]) | (u64::from(expected[3]) << 32)
+            ]
+        );
+        assert_eq!(mock.words(0x100, 6), [1, 0, 2, 0, 3, 0]);
+        assert_eq!(mock.tar_writes, 3);
+        assert_eq!(read_register::<CSW, _>(&mut mock, BASE).unwrap(), before);
+    }
+
+    #[test]
+    fn wide_values_fall_back_to_word_pairs() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let expected = mock.words(0x80, 2);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_word_order(Endianness::Big);
+        let memory: &mut dyn MemoryInterface<ArmError> = &mut ap;
+
+        assert!(!memory.supports_native_64bit_access());
+        assert_eq!(
+            memory.read_word_64(0x80).unwrap(),
+            (u64::from(expected[0]) << 32) | u64::from(expected[1])
+        );
+        memory.write_word_64(0x88, 0x1111_2222_3333_4444).unwrap();
+        assert_eq!(mock.words(0x88, 2), [0x1111_2222, 0x3333_4444]);
+    }
+}
@@ -0,0 +1,270 @@
+//! Lazy iteration over the words of a memory region.
+
+use std::{cell::Cell, num::NonZeroUsize};
+
+use crate::{
+    architecture::arm::{
+        ap_v2::registers::{AddressIncrement, DataSize, CSW},
+        ArmError,
+    },
+    MemoryInterface,
+};
+
+use super::{
+    transfer::{self, WordReads},
+    write_register, Cancellation, Endianness, MemoryApError, AUTO_INCREMENT_BLOCK_SIZE,
+};
+
+/// The number of words read at once by [`WordIter`].
+const CHUNK_WORDS: usize = 64;
+
+/// Returns an iterator over the `count` 32 bit words starting at `address`.
+///
+/// See [`WordIter`].
+pub fn iter_words<M>(iface: &mut M, base: u64, address: u64, count: usize) -> WordIter<'_, M>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    WordIter::new(iface, base, address, count, false, None, None)
+}
+
+/// An iterator over the words of a memory region, created with [`iter_words`].
+///
+/// The words are read in chunks of up to 64 words with the TAR auto-increment enabled, never
+/// crossing an [`AUTO_INCREMENT_BLOCK_SIZE`] boundary within a chunk. Every chunk is read
+/// completely before the first of its words is returned, so dropping the iterator early never
+/// leaves the AP in the middle of a transfer. The CSW is restored when the iterator is dropped.
+///
+/// After an error has been returned, the iterator returns `None`.
+pub struct WordIter<'iface, M>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    iface: &'iface mut M,
+    base: u64,
+    /// The address of the next word to read from the target.
+    address: u64,
+    /// The number of words not read from the target yet.
+    remaining: usize,
+    buffer: [u32; CHUNK_WORDS],
+    buffered: std::ops::Range<usize>,
+    /// The CSW before the first chunk was read, restored on drop.
+    previous_csw: Option<CSW>,
+    failed: bool,
+    /// Whether the handle the iterator was created from discards the first DRW read after every
+    /// TAR write, see
+    /// [`MemoryAp::set_discard_first_read`](super::MemoryAp::set_discard_first_read).
+    discard_first_read: bool,
+    /// The burst limit of the handle the iterator was created from, see
+    /// [`MemoryAp::set_max_burst_words`](super::MemoryAp::set_max_burst_words).
+    max_burst_words: Option<NonZeroUsize>,
+    /// The value last written to TAR2 through the handle the iterator was created from, which
+    /// the iterator keeps up to date.
+    shared_tar2: Option<&'iface Cell<Option<u32>>>,
+    /// The value the iterator last wrote to TAR2, if it was not created from a handle.
+    tar2: Cell<Option<u32>>,
+}
+
+impl<'iface, M> WordIter<'iface, M>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    pub(super) fn new(
+        iface: &'iface mut M,
+        base: u64,
+        address: u64,
+        count: usize,
+        discard_first_read: bool,
+        max_burst_words: Option<NonZeroUsize>,
+        shared_tar2: Option<&'iface Cell<Option<u32>>>,
+    ) -> Self {
+        Self {
+            iface,
+            base,
+            address,
+            remaining: count,
+            buffer: [0; CHUNK_WORDS],
+            buffered: 0..0,
+            previous_csw: None,
+            failed: false,
+            discard_first_read,
+            max_burst_words,
+            shared_tar2,
+            tar2: Cell::new(None),
+        }
+    }
+
+    fn refill(&mut self) -> Result<(), MemoryApError> {
+        if self.address % 4 != 0 {
+            return Err(ArmError::alignment_error(self.address, 4).into());
+        }
+        if self.previous_csw.is_none() {
+            self.previous_csw = Some(transfer::configure_transfer(
+                self.iface,
+                self.base,
+                DataSize::U32,
+                AddressIncrement::Single,
+            )?);
+        }
+
+        let words_to_boundary =
+            ((AUTO_INCREMENT_BLOCK_SIZE - self.address % AUTO_INCREMENT_BLOCK_SIZE) / 4) as usize;
+        let len = self.remaining.min(words_to_boundary).min(CHUNK_WORDS);
+
+        let reads = WordReads {
+            endianness: Endianness::Little,
+            cancellation: &Cancellation::none(),
+            discard_first_read: self.discard_first_read,
+            max_burst_words: self.max_burst_words,
+            tar2: self.shared_tar2.unwrap_or(&self.tar2),
+        };
+        transfer::read_words(
+            self.iface,
+            self.base,
+            self.address,
+            &mut self.buffer[..len],
+            &reads,
+            &mut 0,
+        )?;
+
+        self.address += len as u64 * 4;
+        self.remaining -= len;
+        self.buffered = 0..len;
+        Ok(())
+    }
+}
+
+impl<M> Iterator for WordIter<'_, M>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    type Item = Result<u32, MemoryApError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        if self.buffered.is_empty() {
+            if self.remaining == 0 {
+                return None;
+            }
+            if let Err(error) = self.refill() {
+                self.failed = true;
+                return Some(Err(error));
+            }
+        }
+        self.buffered.next().map(|index| Ok(self.buffer[index]))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.failed {
+            return (0, Some(0));
+        }
+        let len = self.buffered.len() + self.remaining;
+        (len, Some(len))
+    }
+}
+
+impl<M> Drop for WordIter<'_, M>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    fn drop(&mut self) {
+        if let Some(csw) = self.previous_csw.take() {
+            if let Err(error) = write_register(self.iface, self.base, csw) {
+                tracing::warn!("Failed to restore the CSW after iterating words: {error}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use super::iter_words;
+    use crate::architecture::arm::ap_v2::{
+        memory_ap::{mock::MockMemoryAp, read_register, write_register, MemoryAp},
+        registers::{DataSize, CSW},
+    };
+
+    const BASE: u64 = 0x2000;
+
+    #[test]
+    fn iterates_across_wrap_boundary() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let expected = mock.words(0x300, 300);
+
+        let words: Vec<u32> = iter_words(&mut mock, BASE, 0x300, 300)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(words, expected);
+    }
+
+    #[test]
+    fn handle_iterators_follow_the_burst_limit() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let expected = mock.words(0x100, 10);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_max_burst_words(NonZeroUsize::new(4));
+
+        let words: Vec<u32> = ap
+            .iter_words(0x100, 10)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        drop(ap);
+        assert_eq!(words, expected);
+        // 4 + 4 + 2 words.
+        assert_eq!(mock.tar_writes, 3);
+    }
+
+    #[test]
+    fn handle_iterators_discard_the_first_read() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.pipelined_drw = true;
+        let expected = mock.words(0x100, 10);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_discard_first_read(true);
+
+        let words: Vec<u32> = ap
+            .iter_words(0x100, 10)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(words, expected);
+    }
+
+    #[test]
+    fn handle_iterators_reprogram_the_banked_window() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let expected = mock.words(0x100, 4);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+
+        assert_eq!(ap.read_banked(0x100).unwrap(), expected[0]);
+        let words: Vec<u32> = ap
+            .iter_words(0x200, 2)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(words.len(), 2);
+        // The iterator moved the TAR, so the banked access has to point it back at its window.
+        assert_eq!(ap.read_banked(0x108).unwrap(), expected[2]);
+    }
+
+    #[test]
+    fn early_drop_restores_csw() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        write_register(&mut mock, BASE, CSW::builder().size(DataSize::U8).build()).unwrap();
+        let expected = mock.words(0x40, 3);
+
+        let words: Vec<u32> = iter_words(&mut mock, BASE, 0x40, 1000)
+            .take(3)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(words, expected);
+
+        let csw: CSW = read_register(&mut mock, BASE).unwrap();
+        assert_eq!(csw.SIZE, DataSize::U8);
+    }
+}
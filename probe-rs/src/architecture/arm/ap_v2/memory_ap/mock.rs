@@ -0,0 +1,687 @@
+//! A memory access port backed by an in-memory buffer, for testing code built on the APv2
+//! register layer without a probe.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    architecture::arm::{
+        ap_v2::{
+            compat::{ApV1Location, APV1_REGISTER_FILE},
+            registers::{
+                AddressIncrement, DataSize, Register, BASE, BASE2, BD0, BD3, CFG, CSW, DRW, IDR,
+                T0TR, TAR, TAR2, TRR,
+            },
+        },
+        communication_interface::{ArmProbeInterface, SwdSequence},
+        dp::{Abort, DpAddress, DpRegister, DpRegisterAddress},
+        memory::{ArmMemoryInterface, Status},
+        ApV2Address, ArmError, DapAccess, DapError, FullyQualifiedApAddress,
+    },
+    probe::DebugProbeError,
+    MemoryInterface,
+};
+
+use super::AUTO_INCREMENT_BLOCK_SIZE;
+
+/// A memory access port whose register file is mapped at `base` in the address space of its
+/// parent.
+///
+/// The memory behind the AP is sparse: only the bytes mapped with [`MockMemoryAp::map`] exist,
+/// and DRW or banked data register accesses to any other byte fail with a FAULT response, as do
+/// accesses to registers the AP does not implement.
+///
+/// The TAR auto-increment only wraps within an [`AUTO_INCREMENT_BLOCK_SIZE`] block, like on real
+/// hardware that does not increment the upper bits of the TAR.
+///
+/// The IDR identifies the AP as an AHB5 MEM-AP, CFG reports no extensions and BASE reports no
+/// debug entry. Use [`MockMemoryAp::set_register`] to model other APs.
+///
+/// If CFG.ERR is set, `CSW.ERRSTOP` is implemented: a DRW access to one of the
+/// [`MockMemoryAp::faulting_addresses`] or to unmapped memory then sets `TRR.ERR`, and all
+/// following DRW accesses fail until it is cleared. Errors are always passed upstream, regardless of `CSW.ERRNPASS`.
+///
+/// As an [`ArmMemoryInterface`], the AP is the root AP of the default debug port, whose
+/// registers read as zero. Writes to its ABORT register are recorded in
+/// [`MockMemoryAp::aborts`], other accesses to the debug port are not supported. Through
+/// [`DapAccess`], the register file is also an ADIv5 access port: the AP register accesses of any
+/// ADIv5 AP address its `0xD00..=0xDFF` region, and the SELECT values a debug port would be
+/// programmed with for them are recorded in [`MockMemoryAp::selects`].
+///
+/// The banked data registers access the words of the 16 byte window the TAR points to,
+/// regardless of `CSW.SIZE`. Transfers larger than a word take one DRW access per word, least
+/// significant first.
+#[derive(Debug)]
+pub struct MockMemoryAp {
+    /// The base address of the AP's register file.
+    pub base: u64,
+    /// Number of register reads and writes.
+    pub accesses: usize,
+    /// Number of writes to the TAR register.
+    pub tar_writes: usize,
+    /// Number of upcoming DRW accesses that fail with a WAIT response.
+    pub drw_faults: usize,
+    /// Whether DRW reads are pipelined, returning the data of the previous access.
+    pub pipelined_drw: bool,
+    /// Whether the AP implements memory tagging. The tag of each 16 byte granule is the low
+    /// nibble of its granule index.
+    pub mte: bool,
+    /// Whether the AP only implements word transfers, so CSW.SIZE always reads as `U32`.
+    pub word_only: bool,
+    /// Transfer sizes the AP does not implement, so CSW.SIZE reads as `U32` after writing them.
+    pub unsupported_sizes: Vec<DataSize>,
+    /// Whether the AP does not implement packed transfers, so a packed CSW.AddrInc reads as
+    /// `Single`.
+    pub packed_unsupported: bool,
+    /// Whether debug access is locked, so CSW.DbgSwEnable always reads as zero.
+    pub debug_locked: bool,
+    /// Number of upcoming CSW reads which report a transfer in progress.
+    pub busy_polls: usize,
+    /// Number of upcoming DRW reads which return zero instead of the memory contents, modelling
+    /// a status word which becomes set after some polls.
+    pub zero_drw_reads: usize,
+    /// Addresses at which DRW accesses fail with a FAULT response.
+    pub faulting_addresses: HashSet<u64>,
+    /// Offsets of registers whose reads fail with a FAULT response.
+    pub faulting_registers: HashSet<u16>,
+    /// The simulated time every register access takes.
+    pub transfer_latency: Duration,
+    /// An access port whose register file is mapped at its `base` in the memory of this AP.
+    pub child: Option<Box<MockMemoryAp>>,
+    /// The values written to the ABORT register of the debug port.
+    pub aborts: Vec<u32>,
+    /// The values of the SELECT register of the debug port for the ADIv5 AP register accesses
+    /// through [`DapAccess`], recorded whenever an access selects another AP or bank.
+    pub selects: Vec<u32>,
+    drw_buffer: u32,
+    /// The index of the next word of a transfer larger than a word.
+    large_data_word: usize,
+    registers: HashMap<u16, u32>,
+    /// The mapped bytes of the memory behind the AP.
+    memory: BTreeMap<u64, u8>,
+}
+
+impl MockMemoryAp {
+    /// The size of the memory mapped at address zero by [`MockMemoryAp::with_pattern`].
+    pub const PATTERN_SIZE: u64 = 1 << 15;
+
+    /// Creates a MockMemoryAp with the first [`MockMemoryAp::PATTERN_SIZE`] bytes of the memory
+    /// mapped and filled with a pattern where each byte is equal to its own address plus one
+    /// modulo 255 (to avoid zeros).
+    pub fn with_pattern(base: u64) -> Self {
+        let mut mock = Self::new(base);
+        mock.memory = (0..Self::PATTERN_SIZE)
+            .map(|address| (address, (address % 255) as u8 + 1))
+            .collect();
+        mock
+    }
+
+    /// Creates a MockMemoryAp without any memory mapped.
+    pub fn new(base: u64) -> Self {
+        let mut registers = HashMap::new();
+        // DbgSwEnable, DeviceEn, AddrInc = Single, SIZE = U32
+        registers.insert(CSW::ADDRESS, 0x8000_0052);
+        registers.insert(TAR::ADDRESS, 0);
+        registers.insert(TAR2::ADDRESS, 0);
+        // ARM Ltd AMBA AHB5 MEM-AP
+        registers.insert(IDR::ADDRESS, 0x0477_0005);
+        registers.insert(CFG::ADDRESS, 0);
+        registers.insert(TRR::ADDRESS, 0);
+        // ADIv5 format, no debug entry present
+        registers.insert(BASE::ADDRESS, 0x0000_0002);
+        registers.insert(BASE2::ADDRESS, 0);
+        Self {
+            base,
+            accesses: 0,
+            tar_writes: 0,
+            drw_faults: 0,
+            pipelined_drw: false,
+            mte: false,
+            word_only: false,
+            unsupported_sizes: vec![],
+            packed_unsupported: false,
+            debug_locked: false,
+            busy_polls: 0,
+            zero_drw_reads: 0,
+            faulting_addresses: HashSet::new(),
+            faulting_registers: HashSet::new(),
+            transfer_latency: Duration::ZERO,
+            child: None,
+            aborts: vec![],
+            selects: vec![],
+            drw_buffer: 0,
+            large_data_word: 0,
+            registers,
+            memory: BTreeMap::new(),
+        }
+    }
+
+    /// Maps the bytes of `data` at `address`, replacing the bytes mapped there before.
+    pub fn map(&mut self, address: u64, data: &[u8]) {
+        for (offset, byte) in data.iter().enumerate() {
+            self.memory.insert(address + offset as u64, *byte);
+        }
+    }
+
+    /// Unmaps the `len` bytes at `address`, so accesses to them fault.
+    pub fn unmap(&mut self, address: u64, len: usize) {
+        for address in address..address + len as u64 {
+            self.memory.remove(&address);
+        }
+    }
+
+    /// Returns the `len` bytes of the mock memory at `address`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the bytes is not mapped.
+    pub fn bytes(&self, address: u64, len: usize) -> Vec<u8> {
+        (address..address + len as u64)
+            .map(|address| match self.memory.get(&address) {
+                Some(byte) => *byte,
+                None => panic!("MockMemoryAp: {address:#x} is not mapped"),
+            })
+            .collect()
+    }
+
+    /// Returns `count` words of the mock memory starting at `address`, see
+    /// [`MockMemoryAp::bytes`].
+    pub fn words(&self, address: u64, count: usize) -> Vec<u32> {
+        self.bytes(address, count * 4)
+            .chunks_exact(4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect()
+    }
+
+    /// Reads the `len` bytes at `address` in little-endian order, failing with a FAULT response
+    /// if any of them is not mapped.
+    fn load(&self, address: u64, len: usize) -> Result<u32, ArmError> {
+        (0..len).try_fold(0, |value, i| {
+            let byte = self
+                .memory
+                .get(&(address + i as u64))
+                .ok_or(ArmError::Dap(DapError::FaultResponse))?;
+            Ok(value | (u32::from(*byte) << (8 * i)))
+        })
+    }
+
+    /// Writes the low `len` bytes of `value` to `address` in little-endian order, failing with a
+    /// FAULT response without writing any of them if one is not mapped.
+    fn store(&mut self, address: u64, value: u32, len: usize) -> Result<(), ArmError> {
+        let addresses = address..address + len as u64;
+        if addresses
+            .clone()
+            .any(|address| !self.memory.contains_key(&address))
+        {
+            return Err(ArmError::Dap(DapError::FaultResponse));
+        }
+        for (i, address) in addresses.enumerate() {
+            self.memory.insert(address, (value >> (8 * i)) as u8);
+        }
+        Ok(())
+    }
+
+    /// Sets the value of the register at offset `address` from the base.
+    ///
+    /// Registers outside the architected register file at `0xD00..=0xDFF` become writable.
+    pub fn set_register(&mut self, address: u16, value: u32) {
+        self.registers.insert(address, value);
+    }
+
+    /// The current CSW, failing with a FAULT response if it selects a reserved value, e.g. a
+    /// reserved `CSW.SIZE` written as a raw value.
+    fn csw(&self) -> Result<CSW, ArmError> {
+        CSW::try_from(self.registers[&CSW::ADDRESS])
+            .map_err(|_| ArmError::Dap(DapError::FaultResponse))
+    }
+
+    fn tar(&self) -> u64 {
+        (u64::from(self.registers[&TAR2::ADDRESS]) << 32) | u64::from(self.registers[&TAR::ADDRESS])
+    }
+
+    /// Increments the TAR after a DRW access by the number of bytes it transferred.
+    fn increment_tar(&mut self, csw: &CSW) {
+        if csw.AddrInc == AddressIncrement::Off {
+            return;
+        }
+        let tar = self.tar();
+        let block = tar & !(AUTO_INCREMENT_BLOCK_SIZE - 1);
+        let offset = (tar + Self::transfer_bytes(csw) as u64) % AUTO_INCREMENT_BLOCK_SIZE;
+        let tar = block | offset;
+        self.registers.insert(TAR::ADDRESS, tar as u32);
+        self.registers.insert(TAR2::ADDRESS, (tar >> 32) as u32);
+    }
+
+    /// The number of bytes a DRW access transfers. Packed transfers always fill the whole word.
+    fn transfer_bytes(csw: &CSW) -> usize {
+        if csw.AddrInc == AddressIncrement::Packed {
+            4
+        } else {
+            csw.SIZE.to_byte_count()
+        }
+    }
+
+    /// Returns the child AP if the TAR points into its register file.
+    fn addressed_child(&mut self) -> Option<&mut MockMemoryAp> {
+        let tar = self.tar();
+        self.child
+            .as_deref_mut()
+            .filter(|child| (child.base..child.base + 0x1000).contains(&tar))
+    }
+
+    /// Returns the address of the word the next DRW access of a transfer larger than a word
+    /// accesses, and advances to the following word.
+    ///
+    /// The words are accessed least significant first, and the TAR is incremented after the
+    /// last one.
+    fn next_large_data_word(&mut self, csw: &CSW) -> u64 {
+        let address = self.tar() + self.large_data_word as u64 * 4;
+        self.large_data_word += 1;
+        if self.large_data_word == Self::transfer_bytes(csw) / 4 {
+            self.large_data_word = 0;
+            self.increment_tar(csw);
+        }
+        address
+    }
+
+    fn read_drw(&mut self) -> Result<u32, ArmError> {
+        let csw = self.csw()?;
+        let bytes = Self::transfer_bytes(&csw);
+        if bytes > 4 {
+            let address = self.next_large_data_word(&csw);
+            return self.load(address, 4);
+        }
+        let address = self.tar();
+        let lane = (address % 4) * 8;
+
+        let value = self.load(address, bytes)?;
+        let value = if bytes < 4 { value << lane } else { value };
+
+        self.increment_tar(&csw);
+        Ok(value)
+    }
+
+    fn write_drw(&mut self, value: u32) -> Result<(), ArmError> {
+        let csw = self.csw()?;
+        let bytes = Self::transfer_bytes(&csw);
+        if bytes > 4 {
+            let address = self.next_large_data_word(&csw);
+            return self.store(address, value, 4);
+        }
+        let address = self.tar();
+        let lane = (address % 4) * 8;
+        let value = if bytes < 4 { value >> lane } else { value };
+
+        self.store(address, value, bytes)?;
+        self.increment_tar(&csw);
+        Ok(())
+    }
+
+    /// Returns the memory address of the word the banked data register at `offset` accesses.
+    fn banked_address(&self, offset: u16) -> u64 {
+        (self.tar() & !0xF) + u64::from(offset - BD0::ADDRESS)
+    }
+
+    fn fault_drw(&mut self) -> Result<(), ArmError> {
+        if self.drw_faults > 0 {
+            self.drw_faults -= 1;
+            return Err(ArmError::Dap(DapError::WaitResponse));
+        }
+        let error_stop = self.csw()?.ERRSTOP;
+        if error_stop && self.registers[&TRR::ADDRESS] != 0 {
+            return Err(ArmError::Dap(DapError::FaultResponse));
+        }
+        if self.faulting_addresses.contains(&self.tar()) {
+            return Err(self.bus_error());
+        }
+        Ok(())
+    }
+
+    /// Records a faulting DRW access in `TRR.ERR` if `CSW.ERRSTOP` is set, and returns the FAULT
+    /// response it fails with.
+    fn bus_error(&mut self) -> ArmError {
+        if self.csw().is_ok_and(|csw| csw.ERRSTOP) {
+            self.registers.insert(TRR::ADDRESS, 1);
+        }
+        ArmError::Dap(DapError::FaultResponse)
+    }
+
+    /// Records the SELECT value for the register `addr` of the ADIv5 AP `ap`, see
+    /// [`MockMemoryAp::selects`], and returns the address of the register in the register file.
+    fn select_apv1_register(
+        &mut self,
+        ap: &FullyQualifiedApAddress,
+        addr: u8,
+    ) -> Result<u64, ArmError> {
+        let offset = *APV1_REGISTER_FILE.start() + u16::from(addr);
+        let location = ApV1Location::from_offset(offset).ok_or(ArmError::OutOfBounds)?;
+        let select = u32::from(location.select(ap.ap_v1()?));
+        if self.selects.last() != Some(&select) {
+            self.selects.push(select);
+        }
+        Ok(self.base + u64::from(offset))
+    }
+
+    /// Spins for the configured [`MockMemoryAp::transfer_latency`].
+    fn simulate_latency(&self) {
+        if self.transfer_latency.is_zero() {
+            return;
+        }
+        let start = Instant::now();
+        while start.elapsed() < self.transfer_latency {
+            std::hint::spin_loop();
+        }
+    }
+
+    fn read_register(&mut self, address: u64) -> Result<u32, ArmError> {
+        self.simulate_latency();
+        self.accesses += 1;
+        let offset = (address - self.base) as u16;
+        if self.faulting_registers.contains(&offset) {
+            return Err(ArmError::Dap(DapError::FaultResponse));
+        }
+        Ok(match offset {
+            DRW::ADDRESS => {
+                self.fault_drw()?;
+                let tar = self.tar();
+                let value = match self.addressed_child() {
+                    Some(child) => {
+                        let value = child.read_register(tar)?;
+                        self.increment_tar(&self.csw()?);
+                        value
+                    }
+                    None => self.read_drw().map_err(|_| self.bus_error())?,
+                };
+                let value = if self.pipelined_drw {
+                    std::mem::replace(&mut self.drw_buffer, value)
+                } else {
+                    value
+                };
+                if self.zero_drw_reads > 0 {
+                    self.zero_drw_reads -= 1;
+                    0
+                } else {
+                    value
+                }
+            }
+            BD0::ADDRESS..=BD3::ADDRESS => self.load(self.banked_address(offset), 4)?,
+            CSW::ADDRESS if self.busy_polls > 0 => {
+                self.busy_polls -= 1;
+                self.registers[&CSW::ADDRESS] | (1 << 7)
+            }
+            T0TR::ADDRESS if self.mte && self.csw().is_ok_and(|csw| csw.MTE) => {
+                ((self.tar() >> 4) & 0xF) as u32
+            }
+            _ => *self
+                .registers
+                .get(&offset)
+                .ok_or(ArmError::Dap(DapError::FaultResponse))?,
+        })
+    }
+
+    fn write_register(&mut self, address: u64, value: u32) -> Result<(), ArmError> {
+        self.simulate_latency();
+        self.accesses += 1;
+        let offset = (address - self.base) as u16;
+        match offset {
+            DRW::ADDRESS => {
+                self.fault_drw()?;
+                let tar = self.tar();
+                match self.addressed_child() {
+                    Some(child) => {
+                        child.write_register(tar, value)?;
+                        self.increment_tar(&self.csw()?);
+                    }
+                    None => self.write_drw(value).map_err(|_| self.bus_error())?,
+                }
+            }
+            BD0::ADDRESS..=BD3::ADDRESS => self.store(self.banked_address(offset), value, 4)?,
+            TAR::ADDRESS => {
+                self.tar_writes += 1;
+                self.large_data_word = 0;
+                self.registers.insert(offset, value);
+            }
+            CSW::ADDRESS => {
+                // CSW.MTE is RAZ/WI if memory tagging is not implemented.
+                let value = if self.mte { value } else { value & !(1 << 15) };
+                // CSW.ERRSTOP and CSW.ERRNPASS are RAZ/WI without error control.
+                let error_control = CFG::try_from(self.registers[&CFG::ADDRESS])
+                    .map_err(ArmError::RegisterParse)?
+                    .supports_error_control();
+                let value = if error_control {
+                    value
+                } else {
+                    value & !(0b11 << 16)
+                };
+                let value = if self.debug_locked {
+                    value & !(1 << 31)
+                } else {
+                    value
+                };
+                let unsupported = DataSize::from_bits(value)
+                    .is_ok_and(|size| self.unsupported_sizes.contains(&size));
+                let value = if self.word_only || unsupported {
+                    (value & !DataSize::MASK) | DataSize::U32.bits()
+                } else {
+                    value
+                };
+                let packed = AddressIncrement::Packed.bits();
+                let value = if self.packed_unsupported && value & AddressIncrement::MASK == packed {
+                    (value & !AddressIncrement::MASK) | AddressIncrement::Single.bits()
+                } else {
+                    value
+                };
+                self.large_data_word = 0;
+                self.registers.insert(offset, value);
+            }
+            TAR2::ADDRESS => {
+                self.large_data_word = 0;
+                self.registers.insert(offset, value);
+            }
+            TRR::ADDRESS => {
+                let trr = self.registers[&offset] & !value;
+                self.registers.insert(offset, trr);
+            }
+            // Implementation defined registers, added with `set_register`.
+            _ if !(0xD00..=0xDFF).contains(&offset) && self.registers.contains_key(&offset) => {
+                self.registers.insert(offset, value);
+            }
+            _ => return Err(ArmError::Dap(DapError::FaultResponse)),
+        }
+        Ok(())
+    }
+}
+
+impl MemoryInterface<ArmError> for MockMemoryAp {
+    fn supports_native_64bit_access(&mut self) -> bool {
+        false
+    }
+
+    fn read_64(&mut self, _address: u64, _data: &mut [u64]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(64))
+    }
+
+    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), ArmError> {
+        for (i, d) in data.iter_mut().enumerate() {
+            *d = self.read_register(address + (i as u64) * 4)?;
+        }
+        Ok(())
+    }
+
+    fn read_16(&mut self, _address: u64, _data: &mut [u16]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(16))
+    }
+
+    fn read_8(&mut self, _address: u64, _data: &mut [u8]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(8))
+    }
+
+    fn write_64(&mut self, _address: u64, _data: &[u64]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(64))
+    }
+
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), ArmError> {
+        for (i, d) in data.iter().enumerate() {
+            self.write_register(address + (i as u64) * 4, *d)?;
+        }
+        Ok(())
+    }
+
+    fn write_16(&mut self, _address: u64, _data: &[u16]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(16))
+    }
+
+    fn write_8(&mut self, _address: u64, _data: &[u8]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(8))
+    }
+
+    fn supports_8bit_transfers(&self) -> Result<bool, ArmError> {
+        Ok(false)
+    }
+
+    fn flush(&mut self) -> Result<(), ArmError> {
+        Ok(())
+    }
+}
+
+impl DapAccess for MockMemoryAp {
+    fn read_raw_dp_register(
+        &mut self,
+        _dp: DpAddress,
+        _addr: DpRegisterAddress,
+    ) -> Result<u32, ArmError> {
+        Ok(0)
+    }
+
+    fn write_raw_dp_register(
+        &mut self,
+        _dp: DpAddress,
+        addr: DpRegisterAddress,
+        value: u32,
+    ) -> Result<(), ArmError> {
+        if addr == Abort::ADDRESS {
+            self.aborts.push(value);
+        }
+        Ok(())
+    }
+
+    fn read_raw_ap_register(
+        &mut self,
+        ap: &FullyQualifiedApAddress,
+        addr: u8,
+    ) -> Result<u32, ArmError> {
+        let address = self.select_apv1_register(ap, addr)?;
+        self.read_register(address)
+    }
+
+    fn write_raw_ap_register(
+        &mut self,
+        ap: &FullyQualifiedApAddress,
+        addr: u8,
+        value: u32,
+    ) -> Result<(), ArmError> {
+        let address = self.select_apv1_register(ap, addr)?;
+        self.write_register(address, value)
+    }
+}
+
+impl ArmMemoryInterface for MockMemoryAp {
+    fn fully_qualified_address(&self) -> FullyQualifiedApAddress {
+        FullyQualifiedApAddress::v2_with_default_dp(ApV2Address::root())
+    }
+
+    fn base_address(&mut self) -> Result<u64, ArmError> {
+        Ok(self.base)
+    }
+
+    fn get_swd_sequence(&mut self) -> Result<&mut dyn SwdSequence, DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "get_swd_sequence",
+        })
+    }
+
+    fn get_arm_probe_interface(&mut self) -> Result<&mut dyn ArmProbeInterface, DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "get_arm_probe_interface",
+        })
+    }
+
+    fn get_dap_access(&mut self) -> Result<&mut dyn DapAccess, DebugProbeError> {
+        Ok(self)
+    }
+
+    fn generic_status(&mut self) -> Result<Status, ArmError> {
+        Ok(Status::V2(self.csw()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MockMemoryAp;
+    use crate::{
+        architecture::arm::{
+            ap_v2::memory_ap::{read_block, write_block, MemoryApError},
+            ArmError, DapError,
+        },
+        MemoryInterface,
+    };
+
+    const BASE: u64 = 0x2000;
+
+    fn is_fault<T>(result: Result<T, MemoryApError>) -> bool {
+        matches!(
+            result,
+            Err(MemoryApError::Arm(ArmError::Dap(DapError::FaultResponse)))
+        )
+    }
+
+    #[test]
+    fn unmapped_memory_faults() {
+        let mut mock = MockMemoryAp::new(BASE);
+        mock.map(0x100, &[1, 2, 3, 4]);
+        assert_eq!(
+            read_block(&mut mock, BASE, 0x100, 1).unwrap(),
+            [0x0403_0201]
+        );
+        assert!(is_fault(read_block(&mut mock, BASE, 0x100, 2)));
+        assert!(is_fault(write_block(&mut mock, BASE, 0x104, &[0])));
+
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.unmap(0x40, 1);
+        assert!(is_fault(read_block(&mut mock, BASE, 0x40, 1)));
+        // A faulting write leaves the memory unchanged.
+        let expected = mock.bytes(0x44, 4);
+        assert!(is_fault(write_block(&mut mock, BASE, 0x3C, &[0; 3])));
+        assert_eq!(mock.bytes(0x44, 4), expected);
+    }
+
+    #[test]
+    fn reserved_transfer_sizes_fault() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        // A reserved CSW.SIZE, as written by a raw CSW write.
+        mock.write_word_32(BASE + 0xD00, 0x8000_0057).unwrap();
+        assert!(matches!(
+            mock.read_word_32(BASE + 0xD0C),
+            Err(ArmError::Dap(DapError::FaultResponse))
+        ));
+        assert!(matches!(
+            mock.write_word_32(BASE + 0xD0C, 0),
+            Err(ArmError::Dap(DapError::FaultResponse))
+        ));
+    }
+
+    #[test]
+    fn unknown_registers_fault() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        assert!(matches!(
+            mock.read_word_32(BASE + 0xD50),
+            Err(ArmError::Dap(DapError::FaultResponse))
+        ));
+        assert!(matches!(
+            mock.write_word_32(BASE + 0x10, 0),
+            Err(ArmError::Dap(DapError::FaultResponse))
+        ));
+    }
+}
@@ -0,0 +1,2816 @@
+//! Typed access to the registers of an APv2 memory access port.
+//!
+//! The free functions in this module operate on the memory interface of the parent of a memory
+//! access port (`iface`) and the base address of this AP's register file in the parent's address
+//! space (`base`). [`MemoryAp`] bundles both into a handle which additionally carries the
+//! configuration for the transfers.
+
+mod ap_info;
+mod cache;
+mod cancel;
+mod checksum;
+mod core_memory;
+mod describe;
+mod description_cache;
+mod dry_run;
+mod dump;
+mod guard;
+mod interface;
+mod iter;
+#[cfg(any(test, feature = "test"))]
+pub mod mock;
+mod plan;
+mod reader;
+mod recovery;
+mod registry;
+mod retry;
+mod sampler;
+mod string;
+mod structs;
+mod subword;
+mod trace;
+mod transfer;
+mod watch;
+
+pub use super::capabilities::MemApCapabilities;
+pub use crate::architecture::arm::ap_v2::registers::{RegisterDescriptor, REGISTERS};
+pub use ap_info::{ApInfo, ApList};
+pub use cache::CacheStats;
+pub use cancel::Cancellation;
+pub use checksum::{checksum_region, ChecksumAlgo};
+pub use core_memory::CoreMemory;
+pub use describe::ApDescription;
+pub use description_cache::{DescriptionCache, TargetIdentity};
+pub use dry_run::{DryRunMemoryAp, PlannedTransfer};
+pub use dump::{ApRegisterReadResult, RegisterRead, RegisterReadResult};
+pub use guard::CswGuard;
+pub use iter::{iter_words, WordIter};
+pub use plan::{plan_transfer, read_planned, TransferPlan, TransferSegment};
+pub use reader::MemoryApReader;
+pub use recovery::{abort_ap_transfer, clear_ap_errors, recover_error_stop, FaultKind};
+pub use registry::{register_by_address, register_by_name};
+pub use retry::RetryPolicy;
+pub use sampler::{DrwSample, DrwSampler, SampleSink, SamplerStats};
+pub use string::{read_cstr, read_cstr_lossy};
+pub use structs::{read_struct, write_struct};
+pub use subword::{write_u16, write_u8};
+#[cfg(feature = "ap-tracing")]
+pub use trace::TracingApTracer;
+pub use trace::{ApAccess, ApTracer};
+pub use transfer::{
+    fill, read_block, read_block_into, read_block_into_cancellable, read_data, read_register,
+    set_transfer_address, transfer_batch, write_block, write_block_with_progress, write_data,
+    write_fifo, write_register, write_register_checked, Transfer, AUTO_INCREMENT_BLOCK_SIZE,
+};
+pub use watch::{poll_until, watch};
+
+use std::{
+    cell::Cell,
+    num::NonZeroUsize,
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    architecture::arm::{
+        ap_v2::{
+            compat::ApV1Location,
+            registers::{
+                AddressIncrement, CswValidationError, DataSize, MemApBusType, Register,
+                TransferAddress, BD0, BD1, BD2, BD3, CFG, CSW, DRW, IDR, T0TR, TAR, TAR2,
+            },
+        },
+        dp::DebugPortVersion,
+        memory::ArmMemoryInterface,
+        ApAddress, ArmError, FullyQualifiedApAddress, RegisterParseError,
+    },
+    MemoryInterface,
+};
+
+/// Errors that can occur while accessing memory through an APv2 memory access port.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum MemoryApError {
+    /// An error occurred while accessing the registers of the memory access port.
+    Arm(#[from] ArmError),
+
+    /// A register of the memory access port could not be parsed.
+    RegisterParse(#[from] RegisterParseError),
+
+    /// Writing the {register} register would change its read-only field {field}.
+    WroteReadOnly {
+        /// The name of the register.
+        register: &'static str,
+        /// The name of the read-only field.
+        field: &'static str,
+    },
+
+    /// The memory access to {addr:#x} faulted and stopped further memory accesses (CSW.ERRSTOP).
+    /// The error has been cleared, so memory accesses are enabled again.
+    ErrorStopped {
+        /// The address of the faulting access.
+        addr: u64,
+    },
+
+    /// The memory access port still reports a transfer in progress after its errors were cleared.
+    TransferInProgress,
+
+    /// The memory access port still reports a transfer in progress after {timeout:?} and
+    /// aborting the transfer.
+    TransferStuck {
+        /// How long the transfer was waited for before it was aborted.
+        timeout: Duration,
+    },
+
+    /// The memory access port still reports a transfer in progress after {timeout:?}.
+    SynchronizeTimeout {
+        /// How long the transfer was waited for.
+        timeout: Duration,
+    },
+
+    /// The write to {addr:#x} did not complete cleanly: the memory access port still reports a
+    /// transfer in progress or flagged an error.
+    WriteFailed {
+        /// The address of the write.
+        addr: u64,
+        /// The error the write failed with, or `None` if it left a transfer in progress.
+        source: Option<Box<MemoryApError>>,
+    },
+
+    /// The register offset {offset:#x} is not 4 byte aligned or outside the 4 KB register window
+    /// of the memory access port.
+    InvalidRegisterOffset {
+        /// The rejected offset.
+        offset: u16,
+    },
+
+    /// The register {name} at offset {offset:#x} has no equivalent in the register file of an
+    /// ADIv5 access port.
+    NotInApV1RegisterFile {
+        /// The name of the register.
+        name: &'static str,
+        /// The offset of the register in the ADIv6 layout.
+        offset: u16,
+    },
+
+    /// The IDR ({idr:#010x}) does not identify a MEM-AP, so no ADIv6 memory access port is
+    /// mapped at the base address.
+    NotAMemAp {
+        /// The raw value read from the address of the IDR.
+        idr: u32,
+    },
+
+    /// The banked data registers were accessed while the TAR ({tar:#010x}) is not 16 byte
+    /// aligned, so they do not access a defined window.
+    Misaligned {
+        /// The value of the TAR.
+        tar: u32,
+    },
+
+    /// The access to {addr:#x} is outside the memory window {range:#x?} of the memory access
+    /// port.
+    AddressOutOfRange {
+        /// The first address of the access.
+        addr: u64,
+        /// The addresses the memory access port maps.
+        range: Range<u64>,
+    },
+
+    /// The address {addr:#x} needs TAR2, but the memory access port does not implement large
+    /// physical addresses (CFG.LA).
+    AddressTooLarge {
+        /// The address which does not fit into the TAR.
+        addr: u64,
+    },
+
+    /// The string read from the target is not valid UTF-8.
+    InvalidUtf8(#[from] std::string::FromUtf8Error),
+
+    /// The memory access port cannot issue transfers because CSW.DeviceEn is clear. Check that
+    /// the bus behind the AP is powered and not held in reset.
+    DeviceDisabled,
+
+    /// CSW.DbgSwEnable cannot be set, so debug software cannot access memory through the memory
+    /// access port. Debug access is probably locked by the target.
+    DebugLocked,
+
+    /// The CSW configuration is not supported by the memory access port: {0}
+    InvalidCsw(#[from] CswValidationError),
+
+    /// The memory access port does not implement the large data extension (CFG.LD), so it
+    /// cannot transfer {size:?} values.
+    LargeDataUnsupported {
+        /// The size of the rejected access.
+        size: DataSize,
+    },
+
+    /// The memory access port does not support packed transfers: CSW.AddrInc did not keep the
+    /// packed increment.
+    PackedUnsupported,
+
+    /// The memory access port does not support memory tagging.
+    MteUnsupported,
+
+    /// The memory access port does not support CSW.Mode {mode:#06b}.
+    UnsupportedMode {
+        /// The requested mode.
+        mode: u8,
+    },
+
+    /// The transfer was cancelled after {words_done} words.
+    Cancelled {
+        /// The number of words transferred before the transfer was cancelled.
+        words_done: usize,
+    },
+}
+
+impl From<MemoryApError> for ArmError {
+    fn from(value: MemoryApError) -> Self {
+        match value {
+            MemoryApError::Arm(err) => err,
+            MemoryApError::RegisterParse(err) => ArmError::RegisterParse(err),
+            other => ArmError::Other(other.to_string()),
+        }
+    }
+}
+
+/// The version of the Arm Debug Interface architecture that an access port implements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AdiVersion {
+    /// ADIv5: AP registers are selected through the debug port's SELECT register, see
+    /// [`ApV1RegisterFile`](super::compat::ApV1RegisterFile).
+    V5,
+    /// ADIv6: AP registers are memory mapped in the address space of the AP's parent.
+    #[default]
+    V6,
+}
+
+impl From<DebugPortVersion> for AdiVersion {
+    /// ADIv6 requires a DPv3 debug port, earlier debug ports implement ADIv5.
+    fn from(version: DebugPortVersion) -> Self {
+        match version {
+            DebugPortVersion::DPv3 => AdiVersion::V6,
+            _ => AdiVersion::V5,
+        }
+    }
+}
+
+impl From<&FullyQualifiedApAddress> for AdiVersion {
+    /// ADIv5 access ports are addressed by their APSEL, ADIv6 ones by their base address.
+    fn from(address: &FullyQualifiedApAddress) -> Self {
+        match address.ap() {
+            ApAddress::V1(_) => AdiVersion::V5,
+            ApAddress::V2(_) => AdiVersion::V6,
+        }
+    }
+}
+
+/// The byte order of data transferred through an AP, relative to a little-endian bus.
+///
+/// Depending on the API, it selects either the order of the bytes within a word or the order of
+/// the two words of a 64 bit value:
+///
+/// - The DRW data helpers (e.g. [`read_data`]) swap the bytes of each word for
+///   [`Endianness::Big`], independent of `CFG.BE`. This is used to access peripherals whose
+///   registers are big-endian within a little-endian bus without reconfiguring the AP.
+/// - The 64 bit helpers (e.g. [`MemoryAp::read_u64`] and [`MemoryAp::set_word_order`]) transfer
+///   the upper word first for [`Endianness::Big`], and leave the bytes of each word as they are.
+///   On a little-endian memory system (`CFG.BE` clear) the lower word is at the lower address,
+///   on a big-endian one (`CFG.BE` set) the upper word is. Some APs order consecutive accesses
+///   differently, which shows up as swapped halves.
+/// - [`CoreMemory`] swaps the bytes of each word for [`Endianness::Big`], and leaves the order
+///   of the words of a 64 bit value to the memory interface it wraps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Endianness {
+    /// The least significant byte or word is at the lowest address, as on the bus.
+    #[default]
+    Little,
+    /// The most significant byte or word is at the lowest address.
+    Big,
+}
+
+impl Endianness {
+    /// The byte order of the memory system behind the access port configured by `cfg`, see
+    /// `CFG.BE`.
+    pub fn of(cfg: &CFG) -> Self {
+        if cfg.BE {
+            Endianness::Big
+        } else {
+            Endianness::Little
+        }
+    }
+
+    /// Converts `word` between the bus byte order and this byte order.
+    ///
+    /// The conversion swaps the bytes for [`Endianness::Big`], so it is its own inverse.
+    pub const fn apply(self, word: u32) -> u32 {
+        match self {
+            Endianness::Little => word,
+            Endianness::Big => word.swap_bytes(),
+        }
+    }
+
+    /// Combines the two words of a 64 bit value in transfer order.
+    pub const fn compose(self, words: [u32; 2]) -> u64 {
+        let (low, high) = match self {
+            Endianness::Little => (words[0], words[1]),
+            Endianness::Big => (words[1], words[0]),
+        };
+        ((high as u64) << 32) | low as u64
+    }
+
+    /// Splits a 64 bit value into its two words in transfer order.
+    pub const fn split(self, value: u64) -> [u32; 2] {
+        let (low, high) = (value as u32, (value >> 32) as u32);
+        match self {
+            Endianness::Little => [low, high],
+            Endianness::Big => [high, low],
+        }
+    }
+}
+
+/// How a [`MemoryAp`] handle deals with `CSW.DeviceEn` being clear before a memory transfer.
+///
+/// While `CSW.DeviceEn` is clear, the AP cannot issue transactions on its bus, so memory
+/// transfers fault.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeviceDisabledHandling {
+    /// Issue the transfer without checking `CSW.DeviceEn`.
+    #[default]
+    Ignore,
+    /// Fail with [`MemoryApError::DeviceDisabled`].
+    Fail,
+    /// Set `CSW.DbgSwEnable` and check `CSW.DeviceEn` again, failing with
+    /// [`MemoryApError::DeviceDisabled`] if it is still clear.
+    ///
+    /// On some targets, the bus behind the AP is only enabled while debug software access is.
+    EnableDebugAccess,
+}
+
+/// Returns the values of `CSW.Mode` supported by a memory AP with the bus type `bus_type`, see
+/// [`IDR::bus_type`].
+///
+/// Every memory AP supports the basic mode `0b0000`. AXI memory APs additionally support
+/// `0b0001`, which enables barrier operations. Other modes are reserved, and unknown bus types
+/// only allow the basic mode.
+fn supported_modes(bus_type: Option<MemApBusType>) -> &'static [u8] {
+    match bus_type {
+        Some(bus_type) if bus_type.is_axi() => &[0b0000, 0b0001],
+        _ => &[0b0000],
+    }
+}
+
+/// The kind of bus behind a memory access port, as far as it constrains the transfers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BusKind {
+    /// A bus supporting the transfers described by the [`MemApCapabilities`] of the AP.
+    #[default]
+    Generic,
+    /// An APB bus. APB-APs only support word transfers without packing, and each transfer must
+    /// stay within one [`BusKind::APB_WINDOW_SIZE`] window.
+    Apb,
+}
+
+impl BusKind {
+    /// The size of the aligned window each transfer through an APB-AP must stay within.
+    pub const APB_WINDOW_SIZE: u64 = 0x1000;
+
+    /// The kind of bus behind the AP identified by `idr`, see `IDR::bus_type`.
+    pub fn of(idr: &IDR) -> Self {
+        match idr.bus_type() {
+            Some(bus_type) if bus_type.is_apb() => BusKind::Apb,
+            _ => BusKind::Generic,
+        }
+    }
+}
+
+/// Clears the sticky errors of the debug port of `iface` after a failed transfer, see
+/// [`MemoryAp::enable_error_recovery`].
+type Recovery<M> = fn(&mut M) -> Result<(), MemoryApError>;
+
+/// Aborts the transfer in flight on a memory AP in `iface`, see
+/// [`MemoryAp::set_stuck_transfer_recovery`].
+type AbortTransfer<M> = fn(&mut M) -> Result<(), MemoryApError>;
+
+/// Returns whether `offset` is the offset of one of the banked data registers BD0 to BD3.
+fn is_banked(offset: u16) -> bool {
+    (BD0::ADDRESS..=BD3::ADDRESS).contains(&offset)
+}
+
+/// Checks that `offset` is the offset of a register within the 4 KB register window of an AP.
+fn check_register_offset(offset: u16) -> Result<(), MemoryApError> {
+    if offset % 4 != 0 || offset >= 0x1000 {
+        return Err(MemoryApError::InvalidRegisterOffset { offset });
+    }
+    Ok(())
+}
+
+/// The name of the register at `offset` for tracing, if it has a type.
+fn raw_register_name(offset: u16) -> &'static str {
+    register_by_address(offset).map_or("raw", |register| register.name)
+}
+
+/// Returns the recovery step for [`RetryPolicy::run`], which runs before every retry.
+///
+/// The sticky errors of the debug port are cleared with `recovery` if it is set, and the AP is
+/// checked to have no transfer in progress anymore.
+fn recover_with<M: MemoryInterface<ArmError> + ?Sized>(
+    recovery: Option<Recovery<M>>,
+    base: u64,
+) -> impl FnMut(&mut M, &MemoryApError) -> Result<(), MemoryApError> {
+    move |iface, _error| {
+        recovery
+            .map_or(Ok(()), |clear| clear(iface))
+            .and_then(|()| recovery::check_ap_after_fault(iface, base))
+    }
+}
+
+/// The configuration of a [`MemoryAp`] handle which does not change during transfers.
+///
+/// This is [`Copy`], so it can be read via [`MemoryAp::config`] while the handle is borrowed
+/// mutably elsewhere, and shared between handles for the same AP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApConfig {
+    /// The base address of the AP's register file in the address space of its parent.
+    pub base: u64,
+    /// The version of the Arm Debug Interface architecture the AP implements.
+    pub adi_version: AdiVersion,
+    /// The policy used to retry transfers that failed with a transient error.
+    pub retry_policy: RetryPolicy,
+    /// Whether block reads discard the first DRW read after every TAR write, see
+    /// [`MemoryAp::set_discard_first_read`].
+    pub discard_first_read: bool,
+    /// The largest number of DRW accesses block transfers issue in one burst, see
+    /// [`MemoryAp::set_max_burst_words`].
+    pub max_burst_words: Option<NonZeroUsize>,
+    /// How memory transfers deal with `CSW.DeviceEn` being clear.
+    pub device_disabled: DeviceDisabledHandling,
+    /// The order of the words of 64 bit values, see [`MemoryAp::set_word_order`].
+    pub word_order: Endianness,
+    /// The transfer sizes and address increments the AP supports.
+    pub capabilities: MemApCapabilities,
+    /// Whether the error-stop state of the AP is cleared after a failed memory access, see
+    /// [`MemoryAp::set_error_stop_recovery`].
+    pub error_stop_recovery: bool,
+    /// The kind of bus behind the AP, see [`MemoryAp::set_bus`].
+    pub bus: BusKind,
+}
+
+impl ApConfig {
+    /// The default configuration for the AP whose register file is at `base`.
+    pub fn new(base: u64) -> Self {
+        Self {
+            base,
+            adi_version: AdiVersion::default(),
+            retry_policy: RetryPolicy::default(),
+            discard_first_read: false,
+            max_burst_words: None,
+            device_disabled: DeviceDisabledHandling::default(),
+            word_order: Endianness::default(),
+            capabilities: MemApCapabilities::default(),
+            error_stop_recovery: false,
+            bus: BusKind::default(),
+        }
+    }
+}
+
+/// The state a [`MemoryAp`] handle tracks across transfers.
+#[derive(Debug, Default)]
+struct TransferState {
+    /// The 16 byte window this handle last programmed the TAR to for banked accesses.
+    banked_window: Option<u64>,
+    /// Whether `CSW.DbgSwEnable` has been checked since the handle was created.
+    debug_access_checked: bool,
+    /// Whether the kind of bus has been detected or set since the handle was created.
+    bus_known: bool,
+    /// `CFG.LA`, read the first time an address above 4 GB is accessed.
+    large_address: Option<bool>,
+    /// The value this handle last wrote to TAR2, so that block transfers only write TAR2 if
+    /// the upper word of their address changes.
+    tar2: Cell<Option<u32>>,
+    /// Whether the AP supports 64 bit transfers, detected the first time a 64 bit value is
+    /// accessed through [`MemoryInterface`].
+    native_64bit: Option<bool>,
+    /// The description of the AP, see [`MemoryAp::describe`].
+    description: Option<ApDescription>,
+    cache: Option<cache::ReadCache>,
+}
+
+/// A memory access port whose register file is mapped at `base` in the address space of `iface`.
+pub struct MemoryAp<'iface, M: ?Sized> {
+    iface: &'iface mut M,
+    config: ApConfig,
+    cancellation: Cancellation,
+    address_range: Option<Range<u64>>,
+    tracer: Option<&'iface dyn ApTracer>,
+    recovery: Option<Recovery<M>>,
+    stuck_recovery: Option<(AbortTransfer<M>, Duration)>,
+    state: TransferState,
+}
+
+impl<'iface, M> MemoryAp<'iface, M>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    /// Creates a handle for the memory access port whose registers are at `base` in `iface`.
+    pub fn new(iface: &'iface mut M, base: u64) -> Self {
+        Self::with_config(iface, ApConfig::new(base))
+    }
+
+    /// Creates a handle for the memory access port configured by `config` in `iface`.
+    pub fn with_config(iface: &'iface mut M, config: ApConfig) -> Self {
+        Self {
+            iface,
+            config,
+            cancellation: Cancellation::default(),
+            address_range: None,
+            tracer: None,
+            recovery: None,
+            stuck_recovery: None,
+            state: TransferState::default(),
+        }
+    }
+
+    /// The configuration of this handle.
+    pub fn config(&self) -> ApConfig {
+        self.config
+    }
+
+    /// Sets the kind of bus behind the AP, instead of detecting it from the IDR before the first
+    /// memory transfer.
+    ///
+    /// For an APB bus, this also restricts the capabilities to word transfers without packing,
+    /// so transfers with another size or a packed increment are rejected.
+    pub fn set_bus(&mut self, bus: BusKind) {
+        self.config.bus = bus;
+        if bus == BusKind::Apb {
+            self.config.capabilities = MemApCapabilities {
+                sub_word: Some(false),
+                packed: Some(false),
+                max_size: DataSize::U32,
+            };
+        }
+        self.state.bus_known = true;
+    }
+
+    /// Detects the kind of bus behind the AP from the IDR and applies its constraints, see
+    /// [`MemoryAp::set_bus`].
+    pub fn detect_bus(&mut self) -> Result<BusKind, MemoryApError> {
+        let bus = BusKind::of(&self.read_register::<IDR>()?);
+        self.set_bus(bus);
+        Ok(bus)
+    }
+
+    /// Sets the transfer sizes and address increments the AP supports, e.g. as detected with
+    /// [`MemApCapabilities::probe`].
+    pub fn set_capabilities(&mut self, capabilities: MemApCapabilities) {
+        self.config.capabilities = capabilities;
+    }
+
+    /// The version of the Arm Debug Interface architecture this AP implements, which determines
+    /// the addresses of its registers, see [`MemoryAp::register_address`].
+    pub fn adi_version(&self) -> AdiVersion {
+        self.config.adi_version
+    }
+
+    /// Sets the version of the Arm Debug Interface architecture this AP implements.
+    ///
+    /// If the version of the debug port is known, the AP version follows from it with
+    /// `AdiVersion::from`. The registers of an ADIv5 access port are not memory mapped, so a
+    /// handle for one is accessed through an [`ApV1RegisterFile`](super::compat::ApV1RegisterFile),
+    /// see [`MemoryAp::new_v1`].
+    pub fn set_adi_version(&mut self, adi_version: AdiVersion) {
+        self.config.adi_version = adi_version;
+    }
+
+    /// Detects the version of the Arm Debug Interface architecture this AP implements, and sets
+    /// it.
+    ///
+    /// A handle for an ADIv5 access port, see [`MemoryAp::new_v1`], keeps [`AdiVersion::V5`]
+    /// without a transfer. Otherwise the register file is memory mapped, which only ADIv6
+    /// access ports are: the version is set to [`AdiVersion::V6`] if the IDR at `base + 0xDFC`
+    /// identifies a MEM-AP (`IDR.CLASS` is `0x8`) with a designer (`IDR.DESIGNER` is not zero).
+    /// Any other value, e.g. of unmapped memory or a wrong base address, fails with
+    /// [`MemoryApError::NotAMemAp`], as does a failed read with its error, and leaves the version
+    /// unchanged.
+    ///
+    /// ADIv5 is never detected from the IDR. It follows from the version of the debug port with
+    /// `AdiVersion::from(DebugPortVersion)`, or from the address of the AP with
+    /// `AdiVersion::from(&FullyQualifiedApAddress)`.
+    pub fn detect_adi_version(&mut self) -> Result<AdiVersion, MemoryApError> {
+        if self.config.adi_version == AdiVersion::V5 {
+            return Ok(AdiVersion::V5);
+        }
+        let idr: IDR = self.read_register()?;
+        if idr.CLASS != IDR::MEM_AP_CLASS || idr.DESIGNER == 0 {
+            return Err(MemoryApError::NotAMemAp { idr: idr.into() });
+        }
+        self.config.adi_version = AdiVersion::V6;
+        Ok(AdiVersion::V6)
+    }
+
+    /// Returns the address at which the register `R` of this AP is accessed.
+    ///
+    /// For ADIv6, the register file of a memory AP is mapped into the address space of its
+    /// parent, and `R::ADDRESS` is an offset within that 4 KB region, so the address is
+    /// `base + R::ADDRESS`.
+    ///
+    /// For ADIv5, AP registers are not memory mapped. The AP and its register bank are selected
+    /// through the debug port's SELECT register, and the register is addressed by its
+    /// [`ApV1Location`] within the 256 byte register file of the selected AP. The handle
+    /// accesses that register file through an
+    /// [`ApV1RegisterFile`](super::compat::ApV1RegisterFile), which maps it to the
+    /// `0xD00..=0xDFF` region of the ADIv6 layout, so the address is `base + R::ADDRESS` as well.
+    /// Registers outside that region fail with [`MemoryApError::NotInApV1RegisterFile`].
+    pub fn register_address<R: Register>(&self) -> Result<u64, MemoryApError> {
+        self.offset_address(R::NAME, R::ADDRESS)
+    }
+
+    /// Returns the address at which the register `name` at `offset` in the ADIv6 layout is
+    /// accessed, see [`MemoryAp::register_address`].
+    fn offset_address(&self, name: &'static str, offset: u16) -> Result<u64, MemoryApError> {
+        match self.config.adi_version {
+            AdiVersion::V5 if ApV1Location::from_offset(offset).is_none() => {
+                Err(MemoryApError::NotInApV1RegisterFile { name, offset })
+            }
+            AdiVersion::V5 | AdiVersion::V6 => Ok(self.config.base + u64::from(offset)),
+        }
+    }
+
+    /// The base address of this AP's register file in the address space of its parent.
+    pub fn base(&self) -> u64 {
+        self.config.base
+    }
+
+    /// The policy used to retry transfers that failed with a transient error.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.config.retry_policy
+    }
+
+    /// Sets the policy used to retry transfers that failed with a transient error.
+    ///
+    /// Before a retry, the AP is checked to have no transfer in progress anymore. If the check
+    /// fails, the transfer fails with its own error. A transfer which failed with a FAULT response
+    /// faulted on the bus, see [`FaultKind::of`]; it is not retried and fails with that response,
+    /// like the transfers of the free functions.
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.config.retry_policy = retry_policy;
+    }
+
+    /// Sets the order of the words of 64 bit values accessed through the [`MemoryInterface`]
+    /// implementation of this handle, see [`Endianness`].
+    pub fn set_word_order(&mut self, word_order: Endianness) {
+        self.config.word_order = word_order;
+    }
+
+    /// Enables or disables caching of the words read through this handle.
+    ///
+    /// With the cache enabled, a block read whose words were all read before is served without a
+    /// transfer. Writes through this handle drop the cached words they overlap, and writes to DRW
+    /// or the banked data registers, for which the address is not tracked, as well as
+    /// [`MemoryAp::invalidate`] and flushing drop all of them. Only words read with
+    /// [`Endianness::Little`] are cached.
+    ///
+    /// The cache is meant to be short-lived, e.g. for one refresh of a debugger UI, as changes
+    /// of the memory by the target itself are not observed.
+    pub fn set_read_cache(&mut self, enabled: bool) {
+        self.state.cache = enabled.then(cache::ReadCache::default);
+    }
+
+    /// Drops all words from the read cache.
+    pub fn invalidate(&mut self) {
+        if let Some(cache) = &mut self.state.cache {
+            cache.clear();
+        }
+    }
+
+    /// The hits and misses of the read cache, if it is enabled.
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.state.cache.as_ref().map(|cache| cache.stats)
+    }
+
+    /// The interface and the value this handle last wrote to TAR2, for the transfers which
+    /// access the interface directly.
+    fn iface_and_tar2(&mut self) -> (&mut M, &Cell<Option<u32>>) {
+        (&mut *self.iface, &self.state.tar2)
+    }
+
+    /// Drops the cached words overlapping the `len` bytes starting at `address`.
+    fn invalidate_cached(&mut self, address: u64, len: usize) {
+        if let Some(cache) = &mut self.state.cache {
+            cache.invalidate(address, len);
+        }
+    }
+
+    /// Sets the conditions under which block transfers through this AP stop early.
+    pub fn set_cancellation(&mut self, cancellation: Cancellation) {
+        self.cancellation = cancellation;
+    }
+
+    /// Sets whether block reads discard the first DRW read after every TAR write.
+    ///
+    /// Some APs pipeline DRW reads: the first read after the TAR changed returns stale data, and
+    /// the data of each access only appears on the following read. This shows up as block reads
+    /// which are off by one word. Enabling this issues a dummy read after every TAR write, so the
+    /// subsequent reads return the requested data. Writes are not affected.
+    ///
+    /// This is off by default, as the MEM-APs defined by the architecture, such as the CoreSight
+    /// AHB-AP, APB-AP and AXI-AP, return the data of a DRW read with the read itself. Only enable
+    /// it for vendor-specific APs, e.g. bridges in FPGA soft cores, whose documentation asks for
+    /// a dummy DRW read after programming the TAR, or when block reads are off by one word.
+    pub fn set_discard_first_read(&mut self, discard_first_read: bool) {
+        self.config.discard_first_read = discard_first_read;
+    }
+
+    /// Limits the block transfers through this AP to bursts of at most `max_burst_words` DRW
+    /// accesses.
+    ///
+    /// Some debug probes can only queue a limited number of transfers and fail when a long burst
+    /// overflows their buffer. With a limit, block reads and writes re-program the TAR and flush
+    /// the queued transfers after every burst, in addition to the split at every
+    /// [`AUTO_INCREMENT_BLOCK_SIZE`] boundary. By default, bursts are unlimited.
+    pub fn set_max_burst_words(&mut self, max_burst_words: Option<NonZeroUsize>) {
+        self.config.max_burst_words = max_burst_words;
+    }
+
+    /// Restricts the memory accesses through this AP to the addresses in `range`.
+    ///
+    /// Accesses which would fall outside the window fail with [`MemoryApError::AddressOutOfRange`]
+    /// before any transfer is issued. By default, all addresses are accessible.
+    pub fn set_address_range(&mut self, range: Range<u64>) {
+        self.address_range = Some(range);
+    }
+
+    /// The addresses accessible through this AP, if restricted, see
+    /// [`MemoryAp::set_address_range`].
+    pub fn address_range(&self) -> Option<&Range<u64>> {
+        self.address_range.as_ref()
+    }
+
+    /// Checks that the `len` bytes starting at `address` are within the address range of this AP.
+    fn check_range(&self, address: u64, len: usize) -> Result<(), MemoryApError> {
+        let end = address.checked_add(len as u64);
+        if let Some(range) = &self.address_range {
+            if address < range.start || end.is_none_or(|end| end > range.end) {
+                return Err(MemoryApError::AddressOutOfRange {
+                    addr: address,
+                    range: range.clone(),
+                });
+            }
+        }
+        if self.config.bus == BusKind::Apb {
+            let start = address & !(BusKind::APB_WINDOW_SIZE - 1);
+            let window = start..start.saturating_add(BusKind::APB_WINDOW_SIZE);
+            if end.is_none_or(|end| end > window.end) {
+                return Err(MemoryApError::AddressOutOfRange {
+                    addr: address,
+                    range: window,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the `len` bytes starting at `address` are addressable by this AP.
+    ///
+    /// An AP without the large physical address extension ignores TAR2, so an address above
+    /// 4 GB would be silently truncated to its lower word. `CFG.LA` is only read for such
+    /// addresses, once per handle.
+    fn check_large_address(&mut self, address: u64, len: usize) -> Result<(), MemoryApError> {
+        let last = address.saturating_add((len as u64).saturating_sub(1));
+        if last >> 32 == 0 {
+            return Ok(());
+        }
+        let large_address = match self.state.large_address {
+            Some(large_address) => large_address,
+            None => {
+                let large_address = self.read_register::<CFG>()?.LA;
+                self.state.large_address = Some(large_address);
+                large_address
+            }
+        };
+        if large_address {
+            Ok(())
+        } else {
+            Err(MemoryApError::AddressTooLarge {
+                addr: address.max(1 << 32),
+            })
+        }
+    }
+
+    /// Returns whether `CSW.DbgSwEnable` is set, allowing debug software to access memory.
+    #[must_use = "memory accesses fail if debug access is disabled"]
+    pub fn debug_access(&mut self) -> Result<bool, MemoryApError> {
+        Ok(self.read_register::<CSW>()?.DbgSwEnable)
+    }
+
+    /// Sets or clears `CSW.DbgSwEnable`, preserving the other fields of the CSW.
+    ///
+    /// Fails with [`MemoryApError::DebugLocked`] if the AP does not accept setting the bit.
+    pub fn set_debug_access(&mut self, enabled: bool) -> Result<(), MemoryApError> {
+        let mut csw: CSW = self.read_register()?;
+        if csw.DbgSwEnable == enabled {
+            return Ok(());
+        }
+        csw.DbgSwEnable = enabled;
+        self.write_register(csw)?;
+        if enabled && !self.debug_access()? {
+            return Err(MemoryApError::DebugLocked);
+        }
+        Ok(())
+    }
+
+    /// Sets `CSW.DbgSwEnable` before the first memory transfer through this handle.
+    ///
+    /// A bootloader may have cleared the bit, in which case memory accesses fault.
+    fn ensure_debug_access(&mut self) -> Result<(), MemoryApError> {
+        if !self.state.debug_access_checked {
+            self.set_debug_access(true)?;
+            self.state.debug_access_checked = true;
+        }
+        Ok(())
+    }
+
+    /// Sets how memory transfers deal with `CSW.DeviceEn` being clear.
+    pub fn set_device_disabled_handling(&mut self, handling: DeviceDisabledHandling) {
+        self.config.device_disabled = handling;
+    }
+
+    /// Checks `CSW.DeviceEn` before a memory transfer, according to the configured
+    /// [`DeviceDisabledHandling`].
+    ///
+    /// `CSW.DeviceEn` is a status bit which can change at any time, so the CSW is read again
+    /// before every transfer.
+    fn check_device_enabled(&mut self) -> Result<(), MemoryApError> {
+        if self.config.device_disabled == DeviceDisabledHandling::Ignore {
+            return Ok(());
+        }
+
+        let mut csw: CSW = self.read_register()?;
+        if !csw.DeviceEn && self.config.device_disabled == DeviceDisabledHandling::EnableDebugAccess
+        {
+            tracing::debug!("CSW.DeviceEn is clear, enabling debug software access");
+            csw.DbgSwEnable = true;
+            self.write_register(csw)?;
+            csw = self.read_register()?;
+        }
+
+        if csw.DeviceEn {
+            Ok(())
+        } else {
+            Err(MemoryApError::DeviceDisabled)
+        }
+    }
+
+    /// Performs the checks before a memory transfer of `len` bytes at `address`.
+    ///
+    /// The kind of bus is detected first if it is not known yet. The transfer reprograms the TAR,
+    /// so the next banked access has to program it again.
+    fn begin_transfer(&mut self, address: u64, len: usize) -> Result<(), MemoryApError> {
+        if !self.state.bus_known {
+            self.detect_bus()?;
+        }
+        self.check_range(address, len)?;
+        self.check_large_address(address, len)?;
+        self.ensure_debug_access()?;
+        self.check_device_enabled()?;
+        self.state.banked_window = None;
+        Ok(())
+    }
+
+    /// Sets a tracer which observes every register access through this handle.
+    ///
+    /// Without a tracer, the accesses are not timed.
+    pub fn set_tracer(&mut self, tracer: &'iface dyn ApTracer) {
+        self.tracer = Some(tracer);
+    }
+
+    fn trace(
+        &self,
+        register_name: &'static str,
+        address: u64,
+        start: Option<Instant>,
+        is_write: bool,
+        value: u32,
+    ) {
+        if let (Some(tracer), Some(start)) = (self.tracer, start) {
+            tracer.on_transfer(&ApAccess {
+                register_name,
+                address,
+                is_write,
+                value,
+                duration: start.elapsed(),
+            });
+        }
+    }
+
+    /// Reads the register `R` of this AP.
+    ///
+    /// A value which fails to parse is logged at debug level, broken down into its fields with
+    /// [`RegisterParseError::explain`](crate::architecture::arm::RegisterParseError::explain).
+    #[must_use = "the register value is the only effect of reading it"]
+    pub fn read_register<R: Register>(&mut self) -> Result<R, MemoryApError> {
+        R::from_read(self.read_register_raw::<R>()?).map_err(|error| {
+            tracing::debug!("{}", error.explain());
+            error.into()
+        })
+    }
+
+    /// Reads the raw value of the register `R` of this AP without parsing it.
+    ///
+    /// This is cheaper than [`MemoryAp::read_register`] in poll loops which only test a single
+    /// bit, e.g. with [`CSW::tr_in_progress`](crate::architecture::arm::ap_v2::registers::CSW::tr_in_progress).
+    #[must_use = "the register value is the only effect of reading it"]
+    pub fn read_register_raw<R: Register>(&mut self) -> Result<u32, MemoryApError> {
+        self.read_offset(R::NAME, R::ADDRESS)
+    }
+
+    /// Reads the register at `offset` of this AP, for registers which have no type in
+    /// the APv2 register definitions, e.g. vendor specific ones.
+    ///
+    /// `offset` is the offset of the register in the ADIv6 register file, which has to be 4 byte
+    /// aligned and within the 4 KB register window, see [`MemoryAp::register_address`]. Fails
+    /// with [`MemoryApError::InvalidRegisterOffset`] otherwise. For ADIv5, the offset has to be
+    /// within the `0xD00..=0xDFF` region, which is all an ADIv5 access port implements, and
+    /// other offsets fail with [`MemoryApError::NotInApV1RegisterFile`]. The value is not parsed.
+    ///
+    /// Prefer [`MemoryAp::read_register`] for the registers with a type.
+    #[must_use = "the register value is the only effect of reading it"]
+    pub fn read_raw(&mut self, offset: u16) -> Result<u32, MemoryApError> {
+        check_register_offset(offset)?;
+        self.read_offset(raw_register_name(offset), offset)
+    }
+
+    /// Writes `value` to the register at `offset` of this AP, see [`MemoryAp::read_raw`].
+    ///
+    /// The value is written as is, without the checks of [`MemoryAp::write_register`]. Only a
+    /// nonzero value for TAR2 is still rejected with [`MemoryApError::AddressTooLarge`] if the AP
+    /// does not implement large physical addresses, as it would silently be ignored.
+    pub fn write_raw(&mut self, offset: u16, value: u32) -> Result<(), MemoryApError> {
+        check_register_offset(offset)?;
+        if offset == TAR2::ADDRESS && value != 0 {
+            self.check_large_address(u64::from(value) << 32, 1)?;
+        }
+        self.write_offset(raw_register_name(offset), offset, value)
+    }
+
+    fn read_offset(&mut self, name: &'static str, offset: u16) -> Result<u32, MemoryApError> {
+        if is_banked(offset) {
+            self.check_banked_window()?;
+        }
+        let address = self.offset_address(name, offset)?;
+        let start = self.tracer.map(|_| Instant::now());
+        let result = self.with_stuck_recovery(|ap| {
+            ap.config.retry_policy.run(
+                &mut *ap.iface,
+                |iface| Ok(iface.read_word_32(address)?),
+                recover_with(ap.recovery, ap.config.base),
+            )
+        });
+        let value = self.recover_error_stop_after(result)?;
+        self.trace(name, address, start, false, value);
+        Ok(value)
+    }
+
+    fn write_offset(
+        &mut self,
+        name: &'static str,
+        offset: u16,
+        value: u32,
+    ) -> Result<(), MemoryApError> {
+        if is_banked(offset) {
+            self.check_banked_window()?;
+        }
+        let address = self.offset_address(name, offset)?;
+        let start = self.tracer.map(|_| Instant::now());
+        let result = self.with_stuck_recovery(|ap| {
+            ap.config.retry_policy.run(
+                &mut *ap.iface,
+                |iface| Ok(iface.write_word_32(address, value)?),
+                recover_with(ap.recovery, ap.config.base),
+            )
+        });
+        self.recover_error_stop_after(result)?;
+        self.trace(name, address, start, true, value);
+        if offset == TAR::ADDRESS || offset == TAR2::ADDRESS {
+            self.state.banked_window = None;
+        }
+        if offset == TAR2::ADDRESS {
+            self.state.tar2.set(Some(value));
+        }
+        if offset == DRW::ADDRESS || is_banked(offset) {
+            self.invalidate();
+        }
+        Ok(())
+    }
+
+    /// Reads back the address the TAR points to, including TAR2 if the AP implements large
+    /// physical addresses.
+    ///
+    /// This is a diagnostic for comparing where the TAR auto-increment actually left the address
+    /// after a transfer with where it was expected to be, e.g. around an
+    /// [`AUTO_INCREMENT_BLOCK_SIZE`] boundary.
+    pub fn read_tar(&mut self) -> Result<u64, MemoryApError> {
+        let tar: TAR = self.read_register()?;
+        let tar2 = if self.read_register::<CFG>()?.LA {
+            Some(self.read_register::<TAR2>()?)
+        } else {
+            None
+        };
+        Ok(TransferAddress::from_parts(tar, tar2).into())
+    }
+
+    /// Writes the register `R` of this AP.
+    ///
+    /// The value is not validated against the capabilities of the AP, which may only be guessed,
+    /// so a CSW read from the AP can always be written back. The transfers of this handle
+    /// validate the CSW they configure instead, see [`CSW::validate`]. If a CSW selects packed
+    /// transfers, which the AP is known not to support, this fails with
+    /// [`MemoryApError::PackedUnsupported`]. If packed support has not been probed yet, the AP is
+    /// probed instead: the CSW is written and `CSW.AddrInc` read back. If packing did not stick,
+    /// the CSW is written with [`AddressIncrement::Single`] and
+    /// [`MemoryApError::PackedUnsupported`] returned. Either way, the result is recorded in the
+    /// capabilities.
+    pub fn write_register<R: Register>(&mut self, register: R) -> Result<(), MemoryApError> {
+        let value = register.into();
+        if R::ADDRESS == TAR2::ADDRESS && value != 0 {
+            self.check_large_address(u64::from(value) << 32, 1)?;
+        }
+        if R::ADDRESS == CSW::ADDRESS {
+            let csw = CSW::try_from(value)?;
+            if csw.AddrInc == AddressIncrement::Packed {
+                match self.config.capabilities.packed {
+                    Some(true) => {}
+                    Some(false) => return Err(MemoryApError::PackedUnsupported),
+                    None => return self.probe_packed(csw),
+                }
+            }
+        }
+        self.write_register_value::<R>(value)
+    }
+
+    /// Writes `csw`, which selects packed transfers, and checks that the AP accepted them.
+    fn probe_packed(&mut self, csw: CSW) -> Result<(), MemoryApError> {
+        self.write_register_value::<CSW>(csw.into())?;
+        if self.read_register::<CSW>()?.AddrInc == AddressIncrement::Packed {
+            self.config.capabilities.packed = Some(true);
+            return Ok(());
+        }
+
+        let single = CSW {
+            AddrInc: AddressIncrement::Single,
+            ..csw
+        };
+        self.write_register_value::<CSW>(single.into())?;
+        self.config.capabilities.packed = Some(false);
+        Err(MemoryApError::PackedUnsupported)
+    }
+
+    /// Writes the raw `value` to the register `R` of this AP, without any checks of the value.
+    fn write_register_value<R: Register>(&mut self, value: u32) -> Result<(), MemoryApError> {
+        self.write_offset(R::NAME, R::ADDRESS, value)
+    }
+
+    /// Reads the DRW register of this AP, converting the data from `endianness`.
+    pub fn read_data(&mut self, endianness: Endianness) -> Result<u32, MemoryApError> {
+        Ok(endianness.apply(self.read_register_raw::<DRW>()?))
+    }
+
+    /// Writes `data` to the DRW register of this AP, converting it to `endianness`.
+    pub fn write_data(&mut self, data: u32, endianness: Endianness) -> Result<(), MemoryApError> {
+        self.write_register(DRW {
+            data: endianness.apply(data),
+        })
+    }
+
+    /// Sets `CSW.Mode` to `mode`, preserving the other fields of the CSW.
+    ///
+    /// The mode is checked against the modes supported by the type of this AP, see `IDR.TYPE`, as
+    /// a reserved mode can hang the AP.
+    pub fn set_mode(&mut self, mode: u8) -> Result<(), MemoryApError> {
+        let idr: IDR = self.read_register()?;
+        if !supported_modes(idr.bus_type()).contains(&mode) {
+            return Err(MemoryApError::UnsupportedMode { mode });
+        }
+
+        let mut csw: CSW = self.read_register()?;
+        csw.Mode = mode;
+        self.write_register(csw)
+    }
+
+    /// Reads the word at `address` together with the allocation tag of its memory granule.
+    ///
+    /// This enables `CSW.MTE` for the access, and fails with [`MemoryApError::MteUnsupported`]
+    /// if the AP does not implement memory tagging, in which case `CSW.MTE` reads as zero. The
+    /// CSW is restored afterwards.
+    pub fn read_tagged(&mut self, address: u64) -> Result<(u32, u8), MemoryApError> {
+        if address % 4 != 0 {
+            return Err(ArmError::alignment_error(address, 4).into());
+        }
+        self.begin_transfer(address, 4)?;
+
+        let mut ap = self.csw_guard()?;
+        let mut csw = ap.saved();
+        csw.MTE = true;
+        csw.SIZE = DataSize::U32;
+        csw.AddrInc = AddressIncrement::Off;
+        ap.write_register(csw)?;
+
+        let result = ap.read_tagged_with_mte(address);
+        ap.restore()?;
+        result
+    }
+
+    fn read_tagged_with_mte(&mut self, address: u64) -> Result<(u32, u8), MemoryApError> {
+        if !self.read_register::<CSW>()?.MTE {
+            return Err(MemoryApError::MteUnsupported);
+        }
+
+        self.program_address(address)?;
+        let data = self.read_register_raw::<DRW>()?;
+        let tag = self.read_register::<T0TR>()?.tags & 0xF;
+        Ok((data, tag as u8))
+    }
+
+    /// Writes the byte `value` to `address`, see [`write_u8`].
+    pub fn write_u8(&mut self, address: u64, value: u8) -> Result<(), MemoryApError> {
+        self.begin_transfer(address, 1)?;
+        self.invalidate_cached(address, 1);
+        subword::write_sub_word(self, address, value.into(), DataSize::U8)
+    }
+
+    /// Writes the halfword `value` to `address`, see [`write_u16`].
+    pub fn write_u16(&mut self, address: u64, value: u16) -> Result<(), MemoryApError> {
+        self.begin_transfer(address, 2)?;
+        self.invalidate_cached(address, 2);
+        subword::write_sub_word(self, address, value.into(), DataSize::U16)
+    }
+
+    /// Reads `out.len()` 32 bit words starting at `address` into `out`.
+    ///
+    /// A transfer interrupted by a transient fault is resumed at the failed word after
+    /// re-programming the TAR, as the AP's address state is indeterminate after a fault.
+    ///
+    /// If the transfer is cancelled (see [`MemoryAp::set_cancellation`]),
+    /// [`MemoryApError::Cancelled`] is returned. The CSW is restored afterwards, also if the
+    /// transfer fails.
+    pub fn read_block_into(&mut self, address: u64, out: &mut [u32]) -> Result<(), MemoryApError> {
+        self.read_block_into_with_endianness(address, out, Endianness::Little)
+    }
+
+    /// Like [`MemoryAp::read_block_into`], but converts every word from `endianness`.
+    pub fn read_block_into_with_endianness(
+        &mut self,
+        address: u64,
+        out: &mut [u32],
+        endianness: Endianness,
+    ) -> Result<(), MemoryApError> {
+        if address % 4 != 0 {
+            return Err(ArmError::alignment_error(address, 4).into());
+        }
+        self.begin_transfer(address, out.len() * 4)?;
+        if out.is_empty() {
+            return Ok(());
+        }
+        let cached = endianness == Endianness::Little && self.state.cache.is_some();
+        if cached
+            && self
+                .state
+                .cache
+                .as_mut()
+                .is_some_and(|cache| cache.lookup(address, out))
+        {
+            return Ok(());
+        }
+
+        let result = self.read_words_into(address, out, endianness);
+        self.recover_error_stop_after(result)?;
+
+        if let (true, Some(cache)) = (cached, &mut self.state.cache) {
+            cache.insert(address, out);
+        }
+        Ok(())
+    }
+
+    /// Reads the words of a checked [`MemoryAp::read_block_into_with_endianness`] from the AP.
+    fn read_words_into(
+        &mut self,
+        address: u64,
+        out: &mut [u32],
+        endianness: Endianness,
+    ) -> Result<(), MemoryApError> {
+        let caps = self.config.capabilities;
+        let Some(body) = plan_transfer(address, out.len() * 4, &caps).body else {
+            return Ok(());
+        };
+        let mut guard = self.csw_guard()?;
+        guard.configure(body.size, body.increment)?;
+        let ap = &mut *guard;
+        let (iface, base) = (&mut *ap.iface, ap.config.base);
+        let reads = transfer::WordReads {
+            endianness,
+            cancellation: &ap.cancellation,
+            discard_first_read: ap.config.discard_first_read,
+            max_burst_words: ap.config.max_burst_words,
+            tar2: &ap.state.tar2,
+        };
+        let mut done = 0;
+        ap.config.retry_policy.run(
+            iface,
+            |iface| transfer::read_body(iface, base, &body, out, &reads, &mut done),
+            recover_with(ap.recovery, base),
+        )?;
+        guard.restore()
+    }
+
+    /// Reads the 32 bit peripheral register at `address` in the address space of this AP through
+    /// the banked data registers.
+    ///
+    /// BD0 to BD3 access the four words of the 16 byte window starting at the TAR, which always
+    /// starts at a 16 byte boundary: accessing them directly through [`MemoryAp::read_register`]
+    /// fails with [`MemoryApError::Misaligned`] while the TAR is not aligned. The TAR is only
+    /// programmed if the register is outside the window of the previous banked access through
+    /// this handle, so reading several registers within one window costs a single transfer
+    /// each. As the handle tracks the TAR itself, it must not be reprogrammed through the
+    /// parent interface in between.
+    ///
+    /// The access uses the transfer size configured in the CSW, which is a word by default.
+    pub fn read_banked(&mut self, address: u64) -> Result<u32, MemoryApError> {
+        self.select_banked(address)?;
+        let value = match (address >> 2) & 0b11 {
+            0 => self.read_register_raw::<BD0>()?,
+            1 => self.read_register_raw::<BD1>()?,
+            2 => self.read_register_raw::<BD2>()?,
+            _ => self.read_register_raw::<BD3>()?,
+        };
+        Ok(value)
+    }
+
+    /// Writes `data` to the 32 bit peripheral register at `address` in the address space of this
+    /// AP through the banked data registers, see [`MemoryAp::read_banked`].
+    pub fn write_banked(&mut self, address: u64, data: u32) -> Result<(), MemoryApError> {
+        self.select_banked(address)?;
+        match (address >> 2) & 0b11 {
+            0 => self.write_register(BD0 { data }),
+            1 => self.write_register(BD1 { data }),
+            2 => self.write_register(BD2 { data }),
+            _ => self.write_register(BD3 { data }),
+        }
+    }
+
+    /// Checks that the TAR is 16 byte aligned before an access to the banked data registers,
+    /// failing with [`MemoryApError::Misaligned`] otherwise.
+    ///
+    /// The banked data registers access the 16 byte window at the TAR, so their accesses are
+    /// undefined if the TAR has been pointed somewhere within a window, e.g. by a DRW transfer.
+    /// The TAR is only read if this handle has not programmed it for a banked access itself.
+    fn check_banked_window(&mut self) -> Result<(), MemoryApError> {
+        if self.state.banked_window.is_some() {
+            return Ok(());
+        }
+        let tar = self.read_register_raw::<TAR>()?;
+        if tar & 0xF != 0 {
+            return Err(MemoryApError::Misaligned { tar });
+        }
+        Ok(())
+    }
+
+    /// Points the TAR at the 16 byte window containing the peripheral register at `address`,
+    /// unless the previous banked access through this handle already did.
+    fn select_banked(&mut self, address: u64) -> Result<(), MemoryApError> {
+        if address % 4 != 0 {
+            return Err(ArmError::alignment_error(address, 4).into());
+        }
+        self.check_range(address, 4)?;
+        self.check_large_address(address, 4)?;
+        self.ensure_debug_access()?;
+        self.check_device_enabled()?;
+
+        let window = address & !0xF;
+        if self.state.banked_window != Some(window) {
+            self.program_address(window)?;
+            self.state.banked_window = Some(window);
+        }
+        Ok(())
+    }
+
+    /// Reads `len` 32 bit words starting at `address`.
+    pub fn read_block(&mut self, address: u64, len: usize) -> Result<Vec<u32>, MemoryApError> {
+        let mut data = vec![0; len];
+        self.read_block_into(address, &mut data)?;
+        Ok(data)
+    }
+
+    /// Reads the 64 bit value at `address` as two words in `order`.
+    pub fn read_u64(&mut self, address: u64, order: Endianness) -> Result<u64, MemoryApError> {
+        if address % 8 != 0 {
+            return Err(ArmError::alignment_error(address, 8).into());
+        }
+        let mut words = [0; 2];
+        self.read_block_into(address, &mut words)?;
+        Ok(order.compose(words))
+    }
+
+    /// Writes the 64 bit value `value` to `address` as two words in `order`.
+    pub fn write_u64(
+        &mut self,
+        address: u64,
+        value: u64,
+        order: Endianness,
+    ) -> Result<(), MemoryApError> {
+        if address % 8 != 0 {
+            return Err(ArmError::alignment_error(address, 8).into());
+        }
+        self.write_block(address, &order.split(value))
+    }
+
+    /// Reads the 128 bit value at `address` with a single 128 bit access, see
+    /// [`MemoryAp::read_u256`].
+    pub fn read_u128(&mut self, address: u64) -> Result<u128, MemoryApError> {
+        let mut words = [0; 4];
+        self.read_large(address, DataSize::U128, &mut words)?;
+        Ok(words
+            .iter()
+            .rev()
+            .fold(0, |value, word| (value << 32) | u128::from(*word)))
+    }
+
+    /// Writes the 128 bit value `value` to `address` with a single 128 bit access, see
+    /// [`MemoryAp::write_u256`].
+    pub fn write_u128(&mut self, address: u64, value: u128) -> Result<(), MemoryApError> {
+        let words = std::array::from_fn::<u32, 4, _>(|i| (value >> (32 * i)) as u32);
+        self.write_large(address, DataSize::U128, &words)
+    }
+
+    /// Reads the 256 bit value at `address` with a single 256 bit access, returning its words
+    /// least significant first.
+    ///
+    /// Accesses larger than a word need the large data extension, and fail with
+    /// [`MemoryApError::LargeDataUnsupported`] if `CFG.LD` is clear. The size must also be in the
+    /// capabilities of the AP, see [`MemApCapabilities::probe`]. The AP transfers the value as
+    /// one DRW access per word, least significant first. The CSW is restored afterwards.
+    pub fn read_u256(&mut self, address: u64) -> Result<[u32; 8], MemoryApError> {
+        let mut words = [0; 8];
+        self.read_large(address, DataSize::U256, &mut words)?;
+        Ok(words)
+    }
+
+    /// Writes the 256 bit value with the words `words`, least significant first, to `address`
+    /// with a single 256 bit access, see [`MemoryAp::read_u256`].
+    pub fn write_u256(&mut self, address: u64, words: [u32; 8]) -> Result<(), MemoryApError> {
+        self.write_large(address, DataSize::U256, &words)
+    }
+
+    fn read_large(
+        &mut self,
+        address: u64,
+        size: DataSize,
+        words: &mut [u32],
+    ) -> Result<(), MemoryApError> {
+        let mut ap = self.begin_large_transfer(address, size)?;
+        for word in words {
+            *word = ap.read_register_raw::<DRW>()?;
+        }
+        ap.restore()
+    }
+
+    fn write_large(
+        &mut self,
+        address: u64,
+        size: DataSize,
+        words: &[u32],
+    ) -> Result<(), MemoryApError> {
+        self.invalidate_cached(address, size.to_byte_count());
+        let mut ap = self.begin_large_transfer(address, size)?;
+        for word in words {
+            ap.write_register(DRW { data: *word })?;
+        }
+        ap.restore()
+    }
+
+    /// Returns whether the AP supports 64 bit transfers.
+    ///
+    /// Unless the capabilities of the handle already include them, this is detected the first
+    /// time from `CFG.LD` and by probing `CSW.SIZE`, and the capabilities are raised accordingly.
+    fn native_64bit(&mut self) -> Result<bool, MemoryApError> {
+        if let Some(native) = self.state.native_64bit {
+            return Ok(native);
+        }
+        let native = self.config.capabilities.max_size >= DataSize::U64
+            || (self.read_register::<CFG>()?.LD
+                && DataSize::U64.probe_supported(self.iface, self.config.base)?);
+        if native {
+            let caps = &mut self.config.capabilities;
+            caps.max_size = caps.max_size.max(DataSize::U64);
+        }
+        self.state.native_64bit = Some(native);
+        Ok(native)
+    }
+
+    /// Configures a single access of `size` to `address`, returning a guard which restores the
+    /// CSW.
+    fn begin_large_transfer(
+        &mut self,
+        address: u64,
+        size: DataSize,
+    ) -> Result<CswGuard<'_, 'iface, M>, MemoryApError> {
+        let bytes = size.to_byte_count();
+        if address % bytes as u64 != 0 {
+            return Err(ArmError::alignment_error(address, bytes).into());
+        }
+        if !self.read_register::<CFG>()?.LD {
+            return Err(MemoryApError::LargeDataUnsupported { size });
+        }
+        self.begin_single_access(address, size, |_| {})
+    }
+
+    /// Reads the word at `address` with `CSW.Prot` set to `prot` for this access only, e.g. to
+    /// perform a privileged access with [`AhbProt`](super::prot::AhbProt).
+    ///
+    /// `prot` is 7 bits wide, higher bits are ignored. The CSW is restored afterwards, so the
+    /// protection does not leak into other accesses.
+    pub fn read_word_with_prot(&mut self, address: u64, prot: u8) -> Result<u32, MemoryApError> {
+        if address % 4 != 0 {
+            return Err(ArmError::alignment_error(address, 4).into());
+        }
+        let mut ap =
+            self.begin_single_access(address, DataSize::U32, |csw| csw.Prot = prot & 0x7F)?;
+        let value = ap.read_register_raw::<DRW>()?;
+        ap.restore()?;
+        Ok(value)
+    }
+
+    /// Writes `value` to the word at `address` with `CSW.Prot` set to `prot` for this access
+    /// only, see [`MemoryAp::read_word_with_prot`].
+    pub fn write_word_with_prot(
+        &mut self,
+        address: u64,
+        value: u32,
+        prot: u8,
+    ) -> Result<(), MemoryApError> {
+        if address % 4 != 0 {
+            return Err(ArmError::alignment_error(address, 4).into());
+        }
+        self.invalidate_cached(address, 4);
+        let mut ap =
+            self.begin_single_access(address, DataSize::U32, |csw| csw.Prot = prot & 0x7F)?;
+        ap.write_register(DRW { data: value })?;
+        ap.restore()
+    }
+
+    /// Configures a single access of `size` to `address`, with the CSW further adjusted by
+    /// `configure`, and returns a guard which restores the CSW.
+    fn begin_single_access(
+        &mut self,
+        address: u64,
+        size: DataSize,
+        configure: impl FnOnce(&mut CSW),
+    ) -> Result<CswGuard<'_, 'iface, M>, MemoryApError> {
+        self.begin_transfer(address, size.to_byte_count())?;
+
+        let mut ap = self.csw_guard()?;
+        let mut csw = ap.saved();
+        csw.SIZE = size;
+        csw.AddrInc = AddressIncrement::Off;
+        csw.DbgSwEnable = true;
+        configure(&mut csw);
+        ap.write_register(csw)?;
+        ap.program_address(address)?;
+        Ok(ap)
+    }
+
+    /// Programs the TAR with `address`, and TAR2 unless this handle knows it already holds the
+    /// upper word of `address`, see [`set_transfer_address`].
+    fn program_address(&mut self, address: u64) -> Result<(), MemoryApError> {
+        let base = self.config.base;
+        let (iface, tar2) = self.iface_and_tar2();
+        transfer::program_transfer_address(iface, base, address, tar2)
+    }
+
+    /// Writes the 32 bit words of `data` starting at `address`, see [`write_block`].
+    pub fn write_block(&mut self, address: u64, data: &[u32]) -> Result<(), MemoryApError> {
+        self.begin_transfer(address, data.len() * 4)?;
+        self.invalidate_cached(address, data.len() * 4);
+        let result = transfer::write_bursts(self, address, data, None);
+        self.recover_error_stop_after(result)
+    }
+
+    /// Writes the 32 bit words of `data` starting at `address`, calling `progress` after every
+    /// chunk, see [`write_block_with_progress`].
+    pub fn write_block_with_progress(
+        &mut self,
+        address: u64,
+        data: &[u32],
+        progress: Option<&mut dyn FnMut(usize)>,
+    ) -> Result<(), MemoryApError> {
+        self.begin_transfer(address, data.len() * 4)?;
+        self.invalidate_cached(address, data.len() * 4);
+        let result = transfer::write_bursts(self, address, data, progress);
+        self.recover_error_stop_after(result)
+    }
+
+    /// Fills the `words` 32 bit words starting at `address` with `pattern`, see [`fill`].
+    pub fn fill(&mut self, address: u64, pattern: u32, words: usize) -> Result<(), MemoryApError> {
+        self.begin_transfer(address, words * 4)?;
+        self.invalidate_cached(address, words * 4);
+        let result = transfer::fill_bursts(self, address, pattern, words);
+        self.recover_error_stop_after(result)
+    }
+
+    /// Returns an iterator over the `count` 32 bit words starting at `address`, see
+    /// [`iter_words`]. The chunks are read in bursts of at most
+    /// [`MemoryAp::set_max_burst_words`] DRW reads.
+    ///
+    /// The region is checked like for the other memory transfers of this handle before the
+    /// iterator is created. The iterator keeps the TAR2 value tracked by this handle up to date,
+    /// and the next banked access programs the TAR again.
+    pub fn iter_words(
+        &mut self,
+        address: u64,
+        count: usize,
+    ) -> Result<WordIter<'_, M>, MemoryApError> {
+        self.begin_transfer(address, count * 4)?;
+        let config = self.config;
+        Ok(WordIter::new(
+            &mut *self.iface,
+            config.base,
+            address,
+            count,
+            config.discard_first_read,
+            config.max_burst_words,
+            Some(&self.state.tar2),
+        ))
+    }
+
+    /// Reads the bytes at `address..address + out.len()` with the transfers [`plan_transfer`]
+    /// plans for the capabilities of this AP, see [`read_planned`].
+    pub fn read_planned(&mut self, address: u64, out: &mut [u8]) -> Result<(), MemoryApError> {
+        self.begin_transfer(address, out.len())?;
+        let caps = self.config.capabilities;
+        let result = plan::read_planned_bytes(self, address, out, &caps);
+        self.recover_error_stop_after(result)
+    }
+
+    /// Writes the 32 bit words of `data` to the single address `address`, see [`write_fifo`].
+    pub fn write_fifo(&mut self, address: u64, data: &[u32]) -> Result<(), MemoryApError> {
+        self.begin_transfer(address, 4)?;
+        self.invalidate_cached(address, 4);
+        let result = transfer::write_fifo_words(self, address, data);
+        self.recover_error_stop_after(result)
+    }
+
+    /// Enables clearing the error-stop state of the AP when a memory access fails.
+    ///
+    /// If the AP implements error control (`CFG.ERR`) and `CSW.ERRSTOP` is set, a faulting
+    /// memory access stops all following ones until the error is cleared, so every access after
+    /// a single bad one fails. With this enabled, a failed transfer clears the error-stop state
+    /// with [`recover_error_stop`] after the sticky errors of the debug port, if error recovery
+    /// is enabled too (see [`MemoryAp::enable_error_recovery`]). It then fails with
+    /// [`MemoryApError::ErrorStopped`] and the address of the faulting access instead of being
+    /// retried.
+    pub fn set_error_stop_recovery(&mut self, enabled: bool) {
+        self.config.error_stop_recovery = enabled;
+    }
+
+    /// Clears the error-stop state of the AP, and returns the address of the memory access which
+    /// faulted, see [`recover_error_stop`].
+    ///
+    /// With `CSW.ERRNPASS` set, faulting accesses complete without an error response, so this
+    /// checks whether a sequence of accesses took effect.
+    pub fn clear_error_stop(&mut self) -> Result<Option<u64>, MemoryApError> {
+        recover_error_stop(self.iface, self.config.base)
+    }
+
+    /// Clears the error-stop state if the memory access `result` failed, see
+    /// [`MemoryAp::set_error_stop_recovery`].
+    fn recover_error_stop_after<T>(
+        &mut self,
+        result: Result<T, MemoryApError>,
+    ) -> Result<T, MemoryApError> {
+        let error = match result {
+            Err(error) if self.config.error_stop_recovery && error.is_retryable() => error,
+            result => return result,
+        };
+        let recovered = self
+            .recovery
+            .map_or(Ok(()), |clear| clear(self.iface))
+            .and_then(|()| recover_error_stop(self.iface, self.config.base));
+        match recovered {
+            Ok(Some(addr)) => Err(MemoryApError::ErrorStopped { addr }),
+            Ok(None) => Err(error),
+            Err(recovery_error) => {
+                tracing::warn!("Failed to clear the error-stop state: {recovery_error}");
+                Err(error)
+            }
+        }
+    }
+
+    /// Enables recovering from a transfer which never completes.
+    ///
+    /// When a register access fails, and `CSW.TrInProg` is still set after waiting for
+    /// `timeout`, the transfer in flight is cancelled with `abort`, e.g. by writing
+    /// `ABORT.DAPABORT` of the debug port, see [`abort_ap_transfer`]. If `CSW.TrInProg` has
+    /// cleared then, the access is retried once, otherwise it fails with
+    /// [`MemoryApError::TransferStuck`].
+    pub fn set_stuck_transfer_recovery(&mut self, abort: AbortTransfer<M>, timeout: Duration) {
+        self.stuck_recovery = Some((abort, timeout));
+    }
+
+    /// Runs the register access `op`, retrying it once after recovering from a stuck transfer
+    /// if it fails, see [`MemoryAp::set_stuck_transfer_recovery`].
+    fn with_stuck_recovery<T>(
+        &mut self,
+        mut op: impl FnMut(&mut Self) -> Result<T, MemoryApError>,
+    ) -> Result<T, MemoryApError> {
+        match op(self) {
+            Err(error)
+                if self.stuck_recovery.is_some()
+                    && (error.is_retryable()
+                        || matches!(error, MemoryApError::TransferInProgress)) =>
+            {
+                self.recover_stuck_transfer()?;
+                op(self)
+            }
+            result => result,
+        }
+    }
+
+    fn recover_stuck_transfer(&mut self) -> Result<(), MemoryApError> {
+        let Some((abort, timeout)) = self.stuck_recovery else {
+            return Ok(());
+        };
+        let csw = self.register_address::<CSW>()?;
+        let start = Instant::now();
+        while CSW::tr_in_progress(self.iface.read_word_32(csw)?) {
+            if start.elapsed() >= timeout {
+                tracing::debug!("CSW.TrInProg is stuck after {timeout:?}, aborting the transfer");
+                abort(self.iface)?;
+                if CSW::tr_in_progress(self.iface.read_word_32(csw)?) {
+                    return Err(MemoryApError::TransferStuck { timeout });
+                }
+                break;
+            }
+            std::thread::yield_now();
+        }
+        Ok(())
+    }
+
+    /// Waits until the transfers issued through this AP have completed.
+    ///
+    /// Writes queued by the probe are pushed out first, then `CSW.TrInProg` is polled until it
+    /// clears, failing with [`MemoryApError::SynchronizeTimeout`] if it is still set after
+    /// `timeout`. This is a synchronization point before e.g. triggering a reset.
+    ///
+    /// This only guarantees that the AP has completed the transfers on its bus. Whether the
+    /// written data has reached its destination, e.g. through caches or write buffers of the
+    /// target, is up to the target.
+    pub fn synchronize(&mut self, timeout: Duration) -> Result<(), MemoryApError> {
+        self.iface.flush()?;
+        let start = Instant::now();
+        while CSW::tr_in_progress(self.read_register_raw::<CSW>()?) {
+            if start.elapsed() >= timeout {
+                return Err(MemoryApError::SynchronizeTimeout { timeout });
+            }
+            std::thread::yield_now();
+        }
+        Ok(())
+    }
+
+    /// Writes the word `data` to `address` and checks that the write completed cleanly, for
+    /// critical configuration writes.
+    ///
+    /// After the write, the queued transfers are flushed, so that an error response to the
+    /// write is reported, and `CSW.TrInProg` must be clear. Fails with
+    /// [`MemoryApError::WriteFailed`] if the write or the flush failed on the bus or the wire,
+    /// or a transfer is still in progress.
+    pub fn write_word_checked(&mut self, address: u64, data: u32) -> Result<(), MemoryApError> {
+        let written = self
+            .write_block(address, &[data])
+            .and_then(|()| Ok(self.iface.flush()?));
+        match written {
+            Err(error)
+                if error.is_retryable() || matches!(error, MemoryApError::ErrorStopped { .. }) =>
+            {
+                return Err(MemoryApError::WriteFailed {
+                    addr: address,
+                    source: Some(Box::new(error)),
+                });
+            }
+            written => written?,
+        }
+        if CSW::tr_in_progress(self.read_register_raw::<CSW>()?) {
+            return Err(MemoryApError::WriteFailed {
+                addr: address,
+                source: None,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<M> MemoryAp<'_, M>
+where
+    M: ArmMemoryInterface + ?Sized,
+{
+    /// Clears the sticky errors of the debug port after a failed transfer, see
+    /// [`clear_ap_errors`], and checks that the AP has no transfer in progress anymore.
+    pub fn clear_errors(&mut self) -> Result<(), MemoryApError> {
+        Self::clear_sticky_errors(self.iface)?;
+        recovery::check_ap_after_fault(self.iface, self.config.base)
+    }
+
+    /// Enables clearing the sticky errors of the debug port, see [`clear_ap_errors`], whenever
+    /// a transfer fails with a retryable error.
+    ///
+    /// Without it, a failed transfer is still classified and the AP checked before a retry, see
+    /// [`MemoryAp::set_retry_policy`], relying on the probe to clear the sticky errors when it
+    /// sees a FAULT response. Enable this for probes which leave them set.
+    pub fn enable_error_recovery(&mut self) {
+        self.recovery = Some(Self::clear_sticky_errors);
+    }
+
+    /// Returns the AP to its reset configuration, e.g. before handing the probe to another tool.
+    ///
+    /// The sticky errors of the debug port are cleared first, see [`clear_ap_errors`]. Then the
+    /// architectural reset value of the CSW is written and TAR and TAR2 are zeroed. The read
+    /// cache of this handle is emptied.
+    pub fn reset_ap_state(&mut self) -> Result<(), MemoryApError> {
+        Self::clear_sticky_errors(self.iface)?;
+        self.invalidate();
+        self.write_register(CSW::default())?;
+        self.write_register(TAR { address: 0 })?;
+        self.write_register(TAR2 { address: 0 })?;
+        self.iface.flush()?;
+        Ok(())
+    }
+
+    /// Enables aborting transfers which never complete through the debug port, see
+    /// [`MemoryAp::set_stuck_transfer_recovery`].
+    pub fn enable_stuck_transfer_recovery(&mut self, timeout: Duration) {
+        self.set_stuck_transfer_recovery(Self::abort, timeout);
+    }
+
+    fn abort(iface: &mut M) -> Result<(), MemoryApError> {
+        let dp = iface.fully_qualified_address().dp();
+        abort_ap_transfer(iface.get_dap_access().map_err(ArmError::from)?, dp)?;
+        Ok(())
+    }
+
+    fn clear_sticky_errors(iface: &mut M) -> Result<(), MemoryApError> {
+        let dp = iface.fully_qualified_address().dp();
+        clear_ap_errors(iface.get_dap_access().map_err(ArmError::from)?, dp)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        num::NonZeroUsize,
+        sync::{atomic::AtomicBool, Arc},
+        time::Duration,
+    };
+
+    use super::{
+        mock::MockMemoryAp, AdiVersion, ApAccess, ApTracer, BusKind, CacheStats, Cancellation,
+        DeviceDisabledHandling, Endianness, MemApCapabilities, MemoryAp, MemoryApError,
+        RetryPolicy, AUTO_INCREMENT_BLOCK_SIZE,
+    };
+    use crate::architecture::arm::{
+        ap_v2::compat::ApV1RegisterFile,
+        ap_v2::prot::{AhbProt, ProtDecode},
+        ap_v2::registers::{
+            AddressIncrement, CswValidationError, DataSize, Register, BD0, CFG, CSW, DRW, IDR, TAR,
+            TAR2,
+        },
+        dp::{DebugPortVersion, DpAddress},
+        ApV2Address, ArmError, DapError, FullyQualifiedApAddress,
+    };
+
+    const BASE: u64 = 0x2000;
+
+    #[test]
+    fn block_read_resumes_after_transient_faults() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.drw_faults = 2;
+        let expected = mock.words(0x40, 8);
+
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_retry_policy(RetryPolicy::new(3, Duration::ZERO));
+
+        assert_eq!(ap.read_block(0x40, 8).unwrap(), expected);
+        // One TAR write for the initial transfer, and one for each retry.
+        assert_eq!(mock.tar_writes, 3);
+    }
+
+    #[test]
+    fn block_read_fails_when_attempts_run_out() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.drw_faults = 3;
+
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_retry_policy(RetryPolicy::new(3, Duration::ZERO));
+
+        assert!(ap.read_block(0x40, 8).is_err());
+    }
+
+    #[test]
+    fn register_address_depends_on_adi_version() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+
+        assert_eq!(ap.register_address::<CSW>().unwrap(), BASE + 0xD00);
+        assert_eq!(ap.register_address::<IDR>().unwrap(), BASE + 0xDFC);
+
+        // The ADIv5 register file is mapped to the same offsets by `ApV1RegisterFile`.
+        ap.set_adi_version(AdiVersion::V5);
+        assert_eq!(ap.register_address::<CSW>().unwrap(), BASE + 0xD00);
+        assert_eq!(ap.register_address::<IDR>().unwrap(), BASE + 0xDFC);
+        assert!(matches!(
+            ap.read_raw(0xF00),
+            Err(MemoryApError::NotInApV1RegisterFile { offset: 0xF00, .. })
+        ));
+    }
+
+    #[test]
+    fn adi_version_is_detected_from_the_idr() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        assert_eq!(ap.detect_adi_version().unwrap(), AdiVersion::V6);
+        assert_eq!(ap.adi_version(), AdiVersion::V6);
+
+        // Unmapped memory reads as zero, and a MEM-AP class without a designer is not an IDR
+        // either. Neither changes the version.
+        for idr in [0, 0x0001_0000] {
+            mock.set_register(IDR::ADDRESS, idr);
+            let mut ap = MemoryAp::new(&mut mock, BASE);
+            assert!(matches!(
+                ap.detect_adi_version(),
+                Err(MemoryApError::NotAMemAp { idr: value }) if value == idr
+            ));
+            assert_eq!(ap.adi_version(), AdiVersion::V6);
+        }
+
+        // A failed read keeps the previous version.
+        mock.faulting_registers.insert(IDR::ADDRESS);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        assert!(ap.detect_adi_version().is_err());
+        assert_eq!(ap.adi_version(), AdiVersion::V6);
+
+        // The version of an ADIv5 handle is known without a transfer.
+        mock.accesses = 0;
+        let mut registers = ApV1RegisterFile::new(&mut mock, DpAddress::Default, 0);
+        let mut ap = MemoryAp::new_v1(&mut registers);
+        assert_eq!(ap.detect_adi_version().unwrap(), AdiVersion::V5);
+        assert_eq!(mock.accesses, 0);
+    }
+
+    #[test]
+    fn adi_version_follows_the_debug_port_version() {
+        assert_eq!(AdiVersion::from(DebugPortVersion::DPv1), AdiVersion::V5);
+        assert_eq!(AdiVersion::from(DebugPortVersion::DPv2), AdiVersion::V5);
+        assert_eq!(AdiVersion::from(DebugPortVersion::DPv3), AdiVersion::V6);
+    }
+
+    #[test]
+    fn adi_version_follows_the_ap_address() {
+        let v1 = FullyQualifiedApAddress::v1_with_default_dp(1);
+        let v2 = FullyQualifiedApAddress::v2_with_default_dp(ApV2Address::new_with_tip(BASE));
+        assert_eq!(AdiVersion::from(&v1), AdiVersion::V5);
+        assert_eq!(AdiVersion::from(&v2), AdiVersion::V6);
+    }
+
+    #[test]
+    fn raw_register_read_skips_parsing() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+
+        let raw = ap.read_register_raw::<CSW>().unwrap();
+        assert_eq!(raw, u32::from(ap.read_register::<CSW>().unwrap()));
+        assert!(!CSW::tr_in_progress(raw));
+    }
+
+    #[test]
+    fn block_read_swaps_only_when_requested() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let native = mock.words(0x80, 4);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+
+        let mut out = [0u32; 4];
+        ap.read_block_into_with_endianness(0x80, &mut out, Endianness::Little)
+            .unwrap();
+        assert_eq!(out.as_slice(), native.as_slice());
+
+        ap.read_block_into_with_endianness(0x80, &mut out, Endianness::Big)
+            .unwrap();
+        let swapped: Vec<u32> = native.iter().map(|word| word.swap_bytes()).collect();
+        assert_eq!(out.as_slice(), swapped.as_slice());
+    }
+
+    #[test]
+    fn cancelled_block_read_is_not_retried() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_retry_policy(RetryPolicy::new(3, Duration::ZERO));
+        ap.set_cancellation(Cancellation::none().with_flag(Arc::new(AtomicBool::new(true))));
+
+        assert!(matches!(
+            ap.read_block(0x40, 8),
+            Err(MemoryApError::Cancelled { words_done: 0 })
+        ));
+        assert_eq!(mock.tar_writes, 0);
+    }
+
+    #[test]
+    fn discarding_first_read_hides_drw_pipeline() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.pipelined_drw = true;
+        let expected = mock.words(0x3F8, 4);
+
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        assert_ne!(ap.read_block(0x3F8, 4).unwrap(), expected);
+
+        ap.set_discard_first_read(true);
+        assert_eq!(ap.read_block(0x3F8, 4).unwrap(), expected);
+    }
+
+    #[test]
+    fn set_mode_checks_the_ap_type() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+
+        // AHB5 only supports the basic mode.
+        assert!(matches!(
+            ap.set_mode(0b0001),
+            Err(MemoryApError::UnsupportedMode { mode: 0b0001 })
+        ));
+        ap.set_mode(0b0000).unwrap();
+
+        // AXI5 also supports barriers.
+        mock.set_register(IDR::ADDRESS, 0x0477_0007);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_mode(0b0001).unwrap();
+
+        let csw = ap.read_register::<CSW>().unwrap();
+        assert_eq!(csw.Mode, 0b0001);
+        assert_eq!(u32::from(csw), 0x8000_0152);
+    }
+
+    #[test]
+    fn tagged_read_requires_mte() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        assert!(matches!(
+            ap.read_tagged(0x40),
+            Err(MemoryApError::MteUnsupported)
+        ));
+
+        mock.mte = true;
+        let expected = mock.words(0x48, 1)[0];
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        assert_eq!(ap.read_tagged(0x48).unwrap(), (expected, 0x4));
+        // The CSW is restored, with MTE disabled again.
+        assert_eq!(ap.read_register_raw::<CSW>().unwrap(), 0x8000_0052);
+    }
+
+    #[derive(Default)]
+    struct RecordingTracer(RefCell<Vec<ApAccess>>);
+
+    impl ApTracer for RecordingTracer {
+        fn on_transfer(&self, access: &ApAccess) {
+            self.0.borrow_mut().push(access.clone());
+        }
+    }
+
+    #[test]
+    fn tracer_observes_register_accesses() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let tracer = RecordingTracer::default();
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_tracer(&tracer);
+
+        let csw: CSW = ap.read_register().unwrap();
+        ap.write_register(csw).unwrap();
+
+        let accesses = tracer.0.borrow();
+        assert_eq!(accesses.len(), 2);
+        assert_eq!(accesses[0].register_name, "CSW");
+        assert_eq!(accesses[0].address, BASE + 0xD00);
+        assert!(!accesses[0].is_write);
+        assert_eq!(accesses[0].value, 0x8000_0052);
+        assert!(accesses[1].is_write);
+        assert_eq!(accesses[1].value, 0x8000_0052);
+    }
+
+    #[test]
+    fn bus_faults_are_not_retried() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.faulting_addresses.insert(0x48);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_retry_policy(RetryPolicy::new(3, Duration::ZERO));
+
+        assert!(matches!(
+            ap.read_block(0x40, 4),
+            Err(MemoryApError::Arm(ArmError::Dap(DapError::FaultResponse)))
+        ));
+        drop(ap);
+        assert_eq!(mock.tar_writes, 1);
+    }
+
+    #[test]
+    fn failed_recovery_keeps_the_transfer_error() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.drw_faults = 1;
+        // The AP never finishes the failed transfer, so the check before the retry fails.
+        mock.busy_polls = usize::MAX;
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_retry_policy(RetryPolicy::new(3, Duration::ZERO));
+        ap.write_register(TAR { address: 0x40 }).unwrap();
+
+        assert!(matches!(
+            ap.read_register_raw::<DRW>(),
+            Err(MemoryApError::Arm(ArmError::Dap(DapError::WaitResponse)))
+        ));
+    }
+
+    #[test]
+    fn recovery_only_runs_before_a_retry() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.drw_faults = 2;
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.enable_error_recovery();
+        ap.set_debug_access(false).unwrap();
+        ap.write_register(TAR { address: 0x40 }).unwrap();
+
+        // Without retries, the failed transfer is not followed by a recovery.
+        assert!(matches!(
+            ap.read_register_raw::<DRW>(),
+            Err(MemoryApError::Arm(ArmError::Dap(DapError::WaitResponse)))
+        ));
+        assert!(ap.iface.aborts.is_empty());
+
+        ap.set_retry_policy(RetryPolicy::new(3, Duration::ZERO));
+        ap.read_register_raw::<DRW>().unwrap();
+        assert_eq!(ap.iface.aborts.len(), 1);
+        // The recovery leaves the debug access as it was configured.
+        assert!(!ap.debug_access().unwrap());
+    }
+
+    #[test]
+    fn error_recovery_clears_the_sticky_errors() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.drw_faults = 1;
+        let expected = mock.words(0x40, 2);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_retry_policy(RetryPolicy::new(3, Duration::ZERO));
+        ap.enable_error_recovery();
+
+        assert_eq!(ap.read_block(0x40, 2).unwrap(), expected);
+        drop(ap);
+        // ORUNERRCLR, WDERRCLR, STKERRCLR and STKCMPCLR.
+        assert_eq!(mock.aborts, [0x1E]);
+    }
+
+    #[test]
+    fn faulting_address_fails_block_read() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.faulting_addresses.insert(0x48);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+
+        assert!(ap.read_block(0x40, 2).is_ok());
+        assert!(matches!(
+            ap.read_block(0x40, 4),
+            Err(MemoryApError::Arm(ArmError::Dap(DapError::FaultResponse)))
+        ));
+    }
+
+    #[test]
+    fn error_stop_is_cleared_and_reports_the_faulting_address() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.set_register(CFG::ADDRESS, 1 << 12);
+        mock.faulting_addresses.insert(0x48);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        let mut csw: CSW = ap.read_register().unwrap();
+        csw.ERRSTOP = true;
+        ap.write_register(csw).unwrap();
+
+        // Without recovery, the AP refuses all accesses after the fault.
+        assert!(ap.read_block(0x40, 4).is_err());
+        assert_eq!(ap.clear_error_stop().unwrap(), Some(0x48));
+        assert_eq!(ap.clear_error_stop().unwrap(), None);
+        assert!(ap.read_block(0x40, 2).is_ok());
+        assert!(ap.read_block(0x40, 4).is_err());
+        assert!(ap.read_block(0x40, 2).is_err());
+        assert!(ap.clear_error_stop().unwrap().is_some());
+
+        ap.set_error_stop_recovery(true);
+        assert!(matches!(
+            ap.read_block(0x40, 4),
+            Err(MemoryApError::ErrorStopped { addr: 0x48 })
+        ));
+        assert!(ap.read_block(0x40, 2).is_ok());
+        assert!(matches!(
+            ap.write_block(0x44, &[0; 2]),
+            Err(MemoryApError::ErrorStopped { addr: 0x48 })
+        ));
+        assert!(ap.write_block(0x40, &[0; 2]).is_ok());
+    }
+
+    #[test]
+    fn error_stop_needs_error_control() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.faulting_addresses.insert(0x48);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_error_stop_recovery(true);
+        let mut csw: CSW = ap.read_register().unwrap();
+        csw.ERRSTOP = true;
+        ap.write_register(csw).unwrap();
+
+        assert!(!ap.read_register::<CSW>().unwrap().ERRSTOP);
+        assert!(matches!(
+            ap.read_block(0x40, 4),
+            Err(MemoryApError::Arm(ArmError::Dap(DapError::FaultResponse)))
+        ));
+        assert!(ap.read_block(0x40, 2).is_ok());
+    }
+
+    #[test]
+    fn accesses_outside_the_address_range_are_rejected() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_address_range(0x100..0x200);
+
+        assert!(ap.read_block(0x1F8, 2).is_ok());
+        assert!(matches!(
+            ap.read_block(0x1FC, 2),
+            Err(MemoryApError::AddressOutOfRange { addr: 0x1FC, .. })
+        ));
+        assert!(matches!(
+            ap.write_u8(0xFF, 0),
+            Err(MemoryApError::AddressOutOfRange { addr: 0xFF, .. })
+        ));
+        assert_eq!(mock.tar_writes, 1);
+    }
+
+    #[test]
+    fn disabled_device_is_reported_before_the_transfer() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        // DbgSwEnable and DeviceEn clear.
+        mock.set_register(CSW::ADDRESS, 0x0000_0012);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        assert!(ap.read_block(0x40, 1).is_ok());
+
+        ap.set_device_disabled_handling(DeviceDisabledHandling::Fail);
+        assert!(matches!(
+            ap.read_block(0x40, 1),
+            Err(MemoryApError::DeviceDisabled)
+        ));
+
+        // The mock does not model DbgSwEnable driving DeviceEn, so enabling debug access is not
+        // enough.
+        ap.set_debug_access(false).unwrap();
+        ap.set_device_disabled_handling(DeviceDisabledHandling::EnableDebugAccess);
+        assert!(matches!(
+            ap.read_block(0x40, 1),
+            Err(MemoryApError::DeviceDisabled)
+        ));
+        assert!(ap.debug_access().unwrap());
+
+        mock.set_register(CSW::ADDRESS, 0x8000_0052);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_device_disabled_handling(DeviceDisabledHandling::Fail);
+        assert!(ap.read_block(0x40, 1).is_ok());
+    }
+
+    #[test]
+    fn raw_registers_are_accessed_by_offset() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        // An implementation defined register.
+        mock.set_register(0xE00, 0x1234_5678);
+        let tracer = RecordingTracer::default();
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_tracer(&tracer);
+
+        assert_eq!(ap.read_raw(0xE00).unwrap(), 0x1234_5678);
+        ap.write_raw(0xE00, 0xCAFE_F00D).unwrap();
+        assert_eq!(ap.read_raw(0xDFC).unwrap(), 0x0477_0005);
+        let names: Vec<_> = tracer
+            .0
+            .borrow()
+            .iter()
+            .map(|access| (access.register_name, access.address))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("raw", BASE + 0xE00),
+                ("raw", BASE + 0xE00),
+                ("IDR", BASE + 0xDFC)
+            ]
+        );
+
+        for offset in [0xE02, 0x1000] {
+            assert!(matches!(
+                ap.read_raw(offset),
+                Err(MemoryApError::InvalidRegisterOffset { .. })
+            ));
+        }
+        assert_eq!(ap.read_raw(0xE00).unwrap(), 0xCAFE_F00D);
+
+        // An ADIv5 access port only has the registers in 0xD00..=0xDFF.
+        ap.set_adi_version(AdiVersion::V5);
+        assert!(matches!(
+            ap.read_raw(0xE00),
+            Err(MemoryApError::NotInApV1RegisterFile {
+                name: "raw",
+                offset: 0xE00
+            })
+        ));
+        assert!(matches!(
+            ap.write_raw(0x0FC, 0),
+            Err(MemoryApError::NotInApV1RegisterFile { .. })
+        ));
+    }
+
+    #[test]
+    fn banked_accesses_need_an_aligned_tar() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let expected = mock.words(0x40, 1);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+
+        ap.write_register(TAR { address: 0x44 }).unwrap();
+        assert!(matches!(
+            ap.read_register::<BD0>(),
+            Err(MemoryApError::Misaligned { tar: 0x44 })
+        ));
+        assert!(matches!(
+            ap.write_register(BD0 { data: 0 }),
+            Err(MemoryApError::Misaligned { tar: 0x44 })
+        ));
+
+        ap.write_register(TAR { address: 0x40 }).unwrap();
+        assert_eq!(ap.read_register::<BD0>().unwrap().data, expected[0]);
+    }
+
+    #[test]
+    fn banked_accesses_reuse_the_tar_window() {
+        // CPUID, ICSR and AIRCR of the System Control Block share one 16 byte window.
+        const CPUID: u64 = 0xE000_ED00;
+        const ICSR: u64 = 0xE000_ED04;
+        const AIRCR: u64 = 0xE000_ED0C;
+        let mut mock = MockMemoryAp::new(BASE);
+        mock.map(CPUID, &[0u8; 0x20]);
+        mock.map(CPUID, &0x410F_C241u32.to_le_bytes());
+        mock.map(ICSR, &0x0000_0803u32.to_le_bytes());
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+
+        assert_eq!(ap.read_banked(CPUID).unwrap(), 0x410F_C241);
+        assert_eq!(ap.read_banked(ICSR).unwrap(), 0x0000_0803);
+        ap.write_banked(AIRCR, 0x05FA_0004).unwrap();
+
+        // Leaving the window, or any other transfer, moves the TAR.
+        ap.read_banked(CPUID + 0x10).unwrap();
+        ap.read_block(CPUID, 1).unwrap();
+        ap.read_banked(CPUID + 0x10).unwrap();
+
+        assert_eq!(mock.words(AIRCR, 1), [0x05FA_0004]);
+        assert_eq!(mock.tar_writes, 4);
+    }
+
+    #[test]
+    fn tar_readback_shows_the_incremented_address() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+
+        ap.read_block(0x3F0, 8).unwrap();
+        // The TAR was re-programmed at the 1 KB boundary and incremented past the last word.
+        assert_eq!(ap.read_tar().unwrap(), 0x410);
+
+        // Without CFG.LA, TAR2 is not part of the address.
+        mock.set_register(TAR2::ADDRESS, 1);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        assert_eq!(ap.read_tar().unwrap(), 0x410);
+        // CFG.LA makes TAR2 part of the address.
+        mock.set_register(CFG::ADDRESS, 0b10);
+        assert_eq!(
+            MemoryAp::new(&mut mock, BASE).read_tar().unwrap(),
+            0x1_0000_0410
+        );
+    }
+
+    #[test]
+    fn high_addresses_need_large_physical_addresses() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+
+        assert!(matches!(
+            ap.read_block(0x1_0000_0000, 1),
+            Err(MemoryApError::AddressTooLarge {
+                addr: 0x1_0000_0000
+            })
+        ));
+        // A transfer running past 4 GB is rejected as well.
+        assert!(matches!(
+            ap.write_block(0xFFFF_FFFC, &[0, 0]),
+            Err(MemoryApError::AddressTooLarge {
+                addr: 0x1_0000_0000
+            })
+        ));
+        assert!(matches!(
+            ap.write_register(TAR2 { address: 1 }),
+            Err(MemoryApError::AddressTooLarge {
+                addr: 0x1_0000_0000
+            })
+        ));
+        // Raw writes cannot bypass the check either.
+        assert!(matches!(
+            ap.write_raw(TAR2::ADDRESS, 1),
+            Err(MemoryApError::AddressTooLarge {
+                addr: 0x1_0000_0000
+            })
+        ));
+        ap.write_raw(TAR2::ADDRESS, 0).unwrap();
+        assert_eq!(ap.read_raw(TAR2::ADDRESS).unwrap(), 0);
+        assert_eq!(mock.tar_writes, 0);
+
+        mock.set_register(CFG::ADDRESS, 0b10);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.write_register(TAR2 { address: 1 }).unwrap();
+        assert_eq!(ap.read_tar().unwrap(), 0x1_0000_0000);
+        ap.write_raw(TAR2::ADDRESS, 2).unwrap();
+        assert_eq!(ap.read_tar().unwrap(), 0x2_0000_0000);
+    }
+
+    #[test]
+    fn debug_access_is_enabled_before_the_first_transfer() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        // DbgSwEnable clear, as left behind by a bootloader.
+        mock.set_register(CSW::ADDRESS, 0x0000_0052);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        assert!(!ap.debug_access().unwrap());
+
+        ap.read_block(0x40, 1).unwrap();
+        assert!(ap.debug_access().unwrap());
+
+        mock.set_register(CSW::ADDRESS, 0x0000_0052);
+        mock.debug_locked = true;
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        assert!(matches!(
+            ap.read_block(0x40, 1),
+            Err(MemoryApError::DebugLocked)
+        ));
+    }
+
+    #[test]
+    fn word_order_selects_the_low_half() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let [first, second] = mock.words(0x80, 2)[..] else {
+            unreachable!()
+        };
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+
+        let low_first = ap.read_u64(0x80, Endianness::Little).unwrap();
+        assert_eq!(low_first, (u64::from(second) << 32) | u64::from(first));
+        let high_first = ap.read_u64(0x80, Endianness::Big).unwrap();
+        assert_eq!(high_first, (u64::from(first) << 32) | u64::from(second));
+
+        ap.write_u64(0x80, 0x1111_2222_3333_4444, Endianness::Big)
+            .unwrap();
+        assert_eq!(mock.words(0x80, 2), [0x1111_2222, 0x3333_4444]);
+    }
+
+    #[test]
+    fn configured_csw_is_validated_against_the_capabilities() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_capabilities(MemApCapabilities {
+            sub_word: Some(false),
+            packed: Some(true),
+            ..MemApCapabilities::default()
+        });
+
+        // Writing the register directly is not checked, as the capabilities may be guessed.
+        let bytes = CSW::builder().size(DataSize::U8).build();
+        ap.write_register(bytes).unwrap();
+
+        let mut guard = ap.csw_guard().unwrap();
+        assert!(matches!(
+            guard.configure(DataSize::U8, AddressIncrement::Packed),
+            Err(MemoryApError::InvalidCsw(
+                CswValidationError::SubWordUnsupported { size: DataSize::U8 }
+            ))
+        ));
+        guard.set_capabilities(MemApCapabilities {
+            packed: Some(true),
+            ..MemApCapabilities::default()
+        });
+        guard
+            .configure(DataSize::U8, AddressIncrement::Packed)
+            .unwrap();
+        guard.restore().unwrap();
+        assert_eq!(ap.read_register::<CSW>().unwrap(), bytes);
+    }
+
+    #[test]
+    fn packed_transfers_are_probed_on_the_first_csw_write() {
+        let packed = CSW::new_packed_bytes();
+
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.write_register(packed).unwrap();
+        assert_eq!(ap.config().capabilities.packed, Some(true));
+        assert_eq!(
+            ap.read_register::<CSW>().unwrap().AddrInc,
+            AddressIncrement::Packed
+        );
+
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.packed_unsupported = true;
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        assert!(matches!(
+            ap.write_register(packed),
+            Err(MemoryApError::PackedUnsupported)
+        ));
+        assert_eq!(ap.config().capabilities.packed, Some(false));
+        let csw: CSW = ap.read_register().unwrap();
+        assert_eq!(
+            (csw.SIZE, csw.AddrInc),
+            (DataSize::U8, AddressIncrement::Single)
+        );
+
+        // The AP is not probed again once packing is known to be unsupported, so the write fails
+        // even though this AP would accept it.
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_capabilities(MemApCapabilities {
+            packed: Some(false),
+            ..MemApCapabilities::default()
+        });
+        assert!(matches!(
+            ap.write_register(packed),
+            Err(MemoryApError::PackedUnsupported)
+        ));
+    }
+
+    #[test]
+    fn apb_aps_are_restricted_to_word_transfers_within_4_kb() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        // AMBA APB4 or APB5
+        mock.set_register(IDR::ADDRESS, 0x0477_0006);
+        let expected = mock.words(0xFF8, 2);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+
+        assert_eq!(ap.read_block(0xFF8, 2).unwrap(), expected);
+        assert_eq!(ap.config().bus, BusKind::Apb);
+        assert_eq!(ap.config().capabilities.sub_word, Some(false));
+        assert!(matches!(
+            ap.read_block(0xFFC, 2),
+            Err(MemoryApError::AddressOutOfRange { addr: 0xFFC, range }) if range == (0..0x1000)
+        ));
+
+        // Sub-word writes are performed on the containing word.
+        ap.write_u8(0x41, 0xA5).unwrap();
+        let mut expected = mock.bytes(0x40, 4);
+        expected[1] = 0xA5;
+        assert_eq!(mock.bytes(0x40, 4), expected);
+    }
+
+    #[test]
+    fn generic_aps_are_not_restricted() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+
+        ap.read_block(0xFFC, 2).unwrap();
+        assert_eq!(ap.config().bus, BusKind::Generic);
+        assert_eq!(ap.config().capabilities, MemApCapabilities::default());
+    }
+
+    #[test]
+    fn config_is_shared_between_handles() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_word_order(Endianness::Big);
+        ap.set_discard_first_read(true);
+        let config = ap.config();
+        assert_eq!(config.base, BASE);
+        assert_eq!(config.word_order, Endianness::Big);
+        drop(ap);
+
+        let ap = MemoryAp::with_config(&mut mock, config);
+        assert_eq!(ap.config(), config);
+    }
+
+    #[test]
+    fn reset_restores_the_default_transfer_state() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_read_cache(true);
+        ap.write_register(CSW {
+            AddrInc: AddressIncrement::Off,
+            ..CSW::builder().size(DataSize::U8).build()
+        })
+        .unwrap();
+        ap.read_block(0x1234, 1).unwrap();
+
+        ap.reset_ap_state().unwrap();
+        let csw: CSW = ap.read_register().unwrap();
+        assert_eq!(
+            CSW {
+                DeviceEn: false,
+                ..csw
+            },
+            CSW::default()
+        );
+        assert_eq!(ap.read_tar().unwrap(), 0);
+        // The cached word is read again.
+        ap.read_block(0x1234, 1).unwrap();
+        assert_eq!(ap.cache_stats().unwrap().hits, 0);
+        drop(ap);
+        // The sticky errors were cleared, although error recovery is not enabled.
+        assert_eq!(mock.aborts, [0x1E]);
+    }
+
+    #[test]
+    fn prot_is_only_applied_to_the_scoped_access() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let expected = mock.words(0x40, 1)[0];
+        let tracer = RecordingTracer::default();
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_tracer(&tracer);
+        let before: CSW = ap.read_register().unwrap();
+        let privileged = AhbProt::privileged_data().to_prot();
+
+        assert_eq!(ap.read_word_with_prot(0x40, privileged).unwrap(), expected);
+        let written_prot: Vec<_> = tracer
+            .0
+            .borrow()
+            .iter()
+            .filter(|access| access.is_write && access.register_name == "CSW")
+            .map(|access| CSW::try_from(access.value).unwrap().Prot)
+            .collect();
+        assert_eq!(written_prot, [privileged, before.Prot]);
+        assert_eq!(ap.read_register::<CSW>().unwrap(), before);
+        ap.write_word_with_prot(0x44, 0x1234_5678, privileged)
+            .unwrap();
+        assert_eq!(ap.read_register::<CSW>().unwrap(), before);
+        assert_eq!(mock.words(0x44, 1), [0x1234_5678]);
+    }
+
+    #[test]
+    fn description_is_read_once() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let tracer = RecordingTracer::default();
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_tracer(&tracer);
+
+        let description = ap.describe().unwrap();
+        assert_eq!(ap.config().capabilities, description.capabilities);
+        let accesses = tracer.0.borrow().len();
+        assert_eq!(ap.describe().unwrap(), description);
+        assert_eq!(tracer.0.borrow().len(), accesses);
+    }
+
+    /// Returns a mock AP implementing the large data extension.
+    fn large_data_mock() -> MockMemoryAp {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let cfg = CFG {
+            LD: true,
+            ..CFG::try_from(0).unwrap()
+        };
+        mock.set_register(CFG::ADDRESS, cfg.into());
+        mock
+    }
+
+    #[test]
+    fn wide_values_are_composed_least_significant_word_first() {
+        let mut mock = large_data_mock();
+        let expected_128 = u128::from_le_bytes(mock.bytes(0x40, 16).try_into().unwrap());
+        let expected_256 = mock.words(0x60, 8);
+        let caps = MemApCapabilities::probe(&mut mock, BASE).unwrap();
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_capabilities(caps);
+        let csw = ap.read_register_raw::<CSW>().unwrap();
+
+        assert_eq!(ap.read_u128(0x40).unwrap(), expected_128);
+        assert_eq!(ap.read_u256(0x60).unwrap()[..], expected_256);
+
+        let value = 0x0001_0203_0405_0607_0809_0A0B_0C0D_0E0F;
+        ap.write_u128(0x80, value).unwrap();
+        ap.write_u256(0xA0, [1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+        assert_eq!(ap.read_register_raw::<CSW>().unwrap(), csw);
+        assert_eq!(mock.bytes(0x80, 16), value.to_le_bytes());
+        assert_eq!(mock.words(0xA0, 8), [1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn wide_accesses_need_large_data() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+
+        assert!(matches!(
+            ap.read_u128(0x40),
+            Err(MemoryApError::LargeDataUnsupported {
+                size: DataSize::U128
+            })
+        ));
+        assert!(matches!(
+            ap.write_u256(0x40, [0; 8]),
+            Err(MemoryApError::LargeDataUnsupported {
+                size: DataSize::U256
+            })
+        ));
+
+        // The capabilities are not probed for a single wide access, `CFG.LD` is enough.
+        let mut mock = large_data_mock();
+        let expected = u128::from_le_bytes(mock.bytes(0x40, 16).try_into().unwrap());
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        assert_eq!(ap.read_u128(0x40).unwrap(), expected);
+        assert!(ap.read_u128(0x48).is_err());
+    }
+
+    #[test]
+    fn cached_reads_skip_the_transfer_until_written() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_read_cache(true);
+
+        let first = ap.read_block(0x40, 8).unwrap();
+        // Contained in the first read.
+        assert_eq!(ap.read_block(0x48, 2).unwrap(), first[2..4]);
+        ap.write_u8(0x49, 0xAA).unwrap();
+        let after_write = ap.read_block(0x48, 2).unwrap();
+        assert_eq!(after_write[0], (first[2] & !0xFF00) | 0xAA00);
+        assert_eq!(
+            ap.cache_stats(),
+            Some(CacheStats {
+                hits: 2,
+                misses: 10
+            })
+        );
+
+        ap.invalidate();
+        ap.read_block(0x40, 1).unwrap();
+        drop(ap);
+        // The initial read, the write, the read after the write and the one after invalidating.
+        assert_eq!(mock.tar_writes, 4);
+    }
+
+    #[test]
+    fn checked_writes_fail_while_the_transfer_is_in_progress() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.write_word_checked(0x40, 0x1234_5678).unwrap();
+        assert_eq!(mock.words(0x40, 1), [0x1234_5678]);
+
+        mock.busy_polls = usize::MAX;
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        assert!(matches!(
+            ap.write_word_checked(0x44, 0x1234_5678),
+            Err(MemoryApError::WriteFailed {
+                addr: 0x44,
+                source: None
+            })
+        ));
+    }
+
+    #[test]
+    fn checked_writes_keep_the_fault() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.faulting_addresses.insert(0x44);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+
+        let Err(MemoryApError::WriteFailed {
+            addr: 0x44,
+            source: Some(source),
+        }) = ap.write_word_checked(0x44, 0x1234_5678)
+        else {
+            panic!("the faulting write was not reported");
+        };
+        assert!(matches!(
+            *source,
+            MemoryApError::Arm(ArmError::Dap(DapError::FaultResponse))
+        ));
+    }
+
+    #[test]
+    fn bursts_are_limited_to_max_burst_words() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let expected = mock.words(0x100, 10);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_max_burst_words(NonZeroUsize::new(4));
+        assert_eq!(ap.read_block(0x100, 10).unwrap(), expected);
+        drop(ap);
+        // 4 + 4 + 2 words.
+        assert_eq!(mock.tar_writes, 3);
+
+        // The burst also ends at the wrap boundary: 2 + 4 + 4 words.
+        let address = AUTO_INCREMENT_BLOCK_SIZE - 8;
+        let data = [0x1234_5678; 10];
+        mock.tar_writes = 0;
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_max_burst_words(NonZeroUsize::new(4));
+        ap.write_block(address, &data).unwrap();
+        drop(ap);
+        assert_eq!(mock.tar_writes, 3);
+        assert_eq!(mock.words(address, 10), data);
+    }
+
+    #[test]
+    fn narrow_bus_block_reads_follow_the_plan() {
+        for (max_size, packed) in [
+            (DataSize::U8, Some(false)),
+            (DataSize::U16, Some(false)),
+            (DataSize::U8, Some(true)),
+        ] {
+            let mut mock = MockMemoryAp::with_pattern(BASE);
+            let expected = mock.words(AUTO_INCREMENT_BLOCK_SIZE - 8, 4);
+            let mut ap = MemoryAp::new(&mut mock, BASE);
+            let before: CSW = ap.read_register().unwrap();
+            ap.set_capabilities(MemApCapabilities {
+                sub_word: Some(true),
+                packed,
+                max_size,
+            });
+            let words = ap.read_block(AUTO_INCREMENT_BLOCK_SIZE - 8, 4).unwrap();
+            assert_eq!(words, expected, "{max_size:?}, packed {packed:?}");
+            assert_eq!(ap.read_register::<CSW>().unwrap(), before);
+            drop(ap);
+            assert_eq!(mock.tar_writes, 2);
+        }
+    }
+
+    #[test]
+    fn narrow_bus_block_writes_follow_the_plan() {
+        let data = [0x1122_3344, 0x5566_7788, 0x99AA_BBCC];
+        for (max_size, packed) in [
+            (DataSize::U8, Some(false)),
+            (DataSize::U16, Some(false)),
+            (DataSize::U8, Some(true)),
+        ] {
+            let mut mock = MockMemoryAp::with_pattern(BASE);
+            let mut ap = MemoryAp::new(&mut mock, BASE);
+            let before: CSW = ap.read_register().unwrap();
+            ap.set_capabilities(MemApCapabilities {
+                sub_word: Some(true),
+                packed,
+                max_size,
+            });
+            let mut reported = vec![];
+            ap.write_block_with_progress(0x100, &data, Some(&mut |words| reported.push(words)))
+                .unwrap();
+            assert_eq!(ap.read_register::<CSW>().unwrap(), before);
+            drop(ap);
+
+            assert_eq!(
+                mock.words(0x100, 3),
+                data,
+                "{max_size:?}, packed {packed:?}"
+            );
+            assert_eq!(reported, [3]);
+        }
+    }
+
+    #[test]
+    fn stuck_transfers_are_aborted_and_retried_once() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let expected = mock.words(0x40, 1)[0];
+        mock.drw_faults = 1;
+        mock.busy_polls = usize::MAX;
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_stuck_transfer_recovery(
+            |mock: &mut MockMemoryAp| {
+                mock.busy_polls = 0;
+                Ok(())
+            },
+            Duration::from_millis(1),
+        );
+        ap.write_register(TAR { address: 0x40 }).unwrap();
+        assert_eq!(ap.read_data(Endianness::Little).unwrap(), expected);
+
+        mock.drw_faults = 1;
+        mock.busy_polls = usize::MAX;
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        // The abort does not free the AP.
+        ap.set_stuck_transfer_recovery(|_| Ok(()), Duration::from_millis(1));
+        assert!(matches!(
+            ap.read_data(Endianness::Little),
+            Err(MemoryApError::TransferStuck { .. })
+        ));
+    }
+
+    #[test]
+    fn synchronize_waits_for_the_transfer_to_complete() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.busy_polls = 3;
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.synchronize(Duration::from_secs(1)).unwrap();
+        assert_eq!(mock.busy_polls, 0);
+
+        mock.busy_polls = usize::MAX;
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        assert!(matches!(
+            ap.synchronize(Duration::from_millis(10)),
+            Err(MemoryApError::SynchronizeTimeout { .. })
+        ));
+    }
+}
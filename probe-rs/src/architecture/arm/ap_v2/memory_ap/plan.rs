@@ -0,0 +1,526 @@
+//! Planning how a transfer of a byte range is decomposed into AP accesses.
+
+use crate::{
+    architecture::arm::{
+        ap_v2::{
+            capabilities::MemApCapabilities,
+            registers::{AddressIncrement, DataSize, Register, CFG, CSW, DRW},
+        },
+        ArmError,
+    },
+    MemoryInterface,
+};
+
+use super::{
+    transfer::{
+        program_transfer_address, read_register, read_words, write_register, WordReads,
+        AUTO_INCREMENT_BLOCK_SIZE,
+    },
+    Cancellation, CswGuard, Endianness, MemoryAp, MemoryApError,
+};
+
+impl MemApCapabilities {
+    /// The order in which [`MemApCapabilities::probe`] tries the sizes above a word: from the
+    /// widest.
+    pub const DEFAULT_SIZE_ORDER: &'static [DataSize] =
+        &[DataSize::U256, DataSize::U128, DataSize::U64];
+
+    /// Detects the capabilities of the memory access port at `base`.
+    ///
+    /// The supported sizes and increments are found by writing them to the CSW and reading them
+    /// back, see [`DataSize::probe_supported`]. The CSW is restored afterwards. Sizes above a
+    /// word are only probed if the AP implements the large data extension (`CFG.LD`), in the
+    /// order of [`MemApCapabilities::DEFAULT_SIZE_ORDER`].
+    #[must_use = "the capabilities are the only effect of probing them"]
+    pub fn probe<M>(iface: &mut M, base: u64) -> Result<Self, MemoryApError>
+    where
+        M: MemoryInterface<ArmError> + ?Sized,
+    {
+        Self::probe_with_order(iface, base, Self::DEFAULT_SIZE_ORDER)
+    }
+
+    /// Like [`MemApCapabilities::probe`], but tries the sizes above a word in `order`.
+    ///
+    /// The first supported size in `order` becomes [`MemApCapabilities::max_size`], and the
+    /// following sizes are not probed. This lets targets which misbehave when a size is probed
+    /// skip it, e.g. by dropping from `U64` straight to `U32` without trying `U128`. Word
+    /// transfers are always supported, so sizes up to a word in `order` are ignored, and the
+    /// largest size is `U32` if none of the sizes is supported.
+    #[must_use = "the capabilities are the only effect of probing them"]
+    pub fn probe_with_order<M>(
+        iface: &mut M,
+        base: u64,
+        order: &[DataSize],
+    ) -> Result<Self, MemoryApError>
+    where
+        M: MemoryInterface<ArmError> + ?Sized,
+    {
+        let sub_word = Some(
+            DataSize::U8.probe_supported(iface, base)?
+                && DataSize::U16.probe_supported(iface, base)?,
+        );
+
+        let previous: CSW = read_register(iface, base)?;
+        let mut csw = previous;
+        csw.AddrInc = AddressIncrement::Packed;
+        write_register(iface, base, csw)?;
+        let packed = read_register::<CSW, _>(iface, base)?.AddrInc == AddressIncrement::Packed;
+        let packed = Some(packed);
+        write_register(iface, base, previous)?;
+
+        let mut max_size = DataSize::U32;
+        if read_register::<CFG, _>(iface, base)?.LD {
+            for size in order.iter().filter(|size| **size > DataSize::U32) {
+                if size.probe_supported(iface, base)? {
+                    max_size = *size;
+                    break;
+                }
+            }
+        }
+
+        Ok(Self {
+            sub_word,
+            packed,
+            max_size,
+        })
+    }
+}
+
+/// A run of DRW accesses of a single size and address increment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferSegment {
+    /// The address of the first access.
+    pub address: u64,
+    /// The number of bytes accessed on the bus. If the AP only supports word transfers, this
+    /// includes the bytes of the containing word outside the requested range.
+    pub len: usize,
+    /// The size of each transfer.
+    pub size: DataSize,
+    /// The address increment after each DRW access.
+    pub increment: AddressIncrement,
+}
+
+impl TransferSegment {
+    /// The number of DRW accesses. A packed access transfers a whole word.
+    pub fn drw_accesses(&self) -> usize {
+        match self.increment {
+            AddressIncrement::Packed => self.len.div_ceil(4),
+            _ => self.len / self.size.to_byte_count(),
+        }
+    }
+
+    /// The number of TAR writes, one for every [`AUTO_INCREMENT_BLOCK_SIZE`] block the segment
+    /// touches.
+    pub fn tar_writes(&self) -> usize {
+        if self.len == 0 {
+            return 0;
+        }
+        let first = self.address / AUTO_INCREMENT_BLOCK_SIZE;
+        let last = (self.address + self.len as u64 - 1) / AUTO_INCREMENT_BLOCK_SIZE;
+        (last - first + 1) as usize
+    }
+}
+
+/// How a transfer of a byte range is decomposed, see [`plan_transfer`].
+///
+/// The head holds the bytes before the first word boundary and the tail the bytes after the last
+/// one, the body the whole words in between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TransferPlan {
+    /// The bytes before the first word boundary.
+    pub head: Option<TransferSegment>,
+    /// The whole words.
+    pub body: Option<TransferSegment>,
+    /// The bytes after the last word boundary.
+    pub tail: Option<TransferSegment>,
+}
+
+impl TransferPlan {
+    /// The segments of the plan in address order.
+    pub fn segments(&self) -> impl Iterator<Item = &TransferSegment> {
+        [&self.head, &self.body, &self.tail]
+            .into_iter()
+            .filter_map(Option::as_ref)
+    }
+
+    /// The total number of TAR writes. Every segment programs the TAR for itself.
+    pub fn tar_writes(&self) -> usize {
+        self.segments().map(TransferSegment::tar_writes).sum()
+    }
+
+    /// The total number of DRW accesses.
+    pub fn drw_accesses(&self) -> usize {
+        self.segments().map(TransferSegment::drw_accesses).sum()
+    }
+}
+
+/// Plans the transfer of the `len` bytes starting at `address` through an AP with the
+/// capabilities `caps`.
+///
+/// The body uses word transfers with the address auto-increment. On a bus narrower than a word,
+/// it uses the widest size of the bus instead, packed if the AP supports it, so each DRW access
+/// still transfers a whole word. The unaligned head and tail use the widest sub-word size their
+/// alignment allows, or the containing word if the AP only supports word transfers.
+pub fn plan_transfer(address: u64, len: usize, caps: &MemApCapabilities) -> TransferPlan {
+    let end = address + len as u64;
+    let body_start = address.next_multiple_of(4);
+    let body_end = end & !0b11;
+    let head_end = body_start.min(end);
+    let tail_start = body_end.max(head_end);
+
+    let body = (body_start < body_end).then(|| {
+        if caps.max_size >= DataSize::U32 {
+            TransferSegment {
+                address: body_start,
+                len: (body_end - body_start) as usize,
+                size: DataSize::U32,
+                increment: AddressIncrement::Single,
+            }
+        } else {
+            TransferSegment {
+                address: body_start,
+                len: (body_end - body_start) as usize,
+                size: caps.max_size,
+                increment: if caps.packed == Some(true) {
+                    AddressIncrement::Packed
+                } else {
+                    AddressIncrement::Single
+                },
+            }
+        }
+    });
+
+    TransferPlan {
+        head: (address < head_end).then(|| partial_word(address, head_end, caps)),
+        body,
+        tail: (tail_start < end).then(|| partial_word(tail_start, end, caps)),
+    }
+}
+
+/// Plans the transfer of the bytes in `start..end`, which lie within one word.
+fn partial_word(start: u64, end: u64, caps: &MemApCapabilities) -> TransferSegment {
+    if caps.sub_word == Some(false) {
+        return TransferSegment {
+            address: start & !0b11,
+            len: 4,
+            size: DataSize::U32,
+            increment: AddressIncrement::Single,
+        };
+    }
+
+    let len = (end - start) as usize;
+    let halfwords = start % 2 == 0 && len % 2 == 0 && caps.max_size >= DataSize::U16;
+    TransferSegment {
+        address: start,
+        len,
+        size: if halfwords {
+            DataSize::U16
+        } else {
+            DataSize::U8
+        },
+        increment: AddressIncrement::Single,
+    }
+}
+
+/// Reads the bytes at `address..address + out.len()` through the memory access port at `base`,
+/// executing the plan of [`plan_transfer`] for `caps`.
+///
+/// The CSW is restored afterwards, also if a read fails.
+pub fn read_planned<M>(
+    iface: &mut M,
+    base: u64,
+    address: u64,
+    out: &mut [u8],
+    caps: &MemApCapabilities,
+) -> Result<(), MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    read_planned_bytes(&mut MemoryAp::new(iface, base), address, out, caps)
+}
+
+/// Like [`read_planned`] through the handle `ap`, whose burst limit the word reads follow.
+pub(super) fn read_planned_bytes<M>(
+    ap: &mut MemoryAp<'_, M>,
+    address: u64,
+    out: &mut [u8],
+    caps: &MemApCapabilities,
+) -> Result<(), MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    let plan = plan_transfer(address, out.len(), caps);
+    if plan.segments().next().is_none() {
+        return Ok(());
+    }
+    let mut ap = ap.csw_guard()?;
+    for segment in plan.segments() {
+        let bytes = read_segment(&mut ap, segment, caps)?;
+        // Only copy the requested bytes of a containing word.
+        for (i, byte) in bytes.iter().enumerate() {
+            let offset = (segment.address + i as u64).wrapping_sub(address) as usize;
+            if let Some(out) = out.get_mut(offset) {
+                *out = *byte;
+            }
+        }
+    }
+    ap.restore()
+}
+
+/// Reads the bytes of `segment` from the bus, configuring the CSW saved by `ap` for it.
+fn read_segment<M>(
+    ap: &mut CswGuard<'_, '_, M>,
+    segment: &TransferSegment,
+    caps: &MemApCapabilities,
+) -> Result<Vec<u8>, MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    CSW::builder()
+        .size(segment.size)
+        .addr_inc(segment.increment)
+        .build()
+        .validate(caps)?;
+    ap.configure(segment.size, segment.increment)?;
+    let config = ap.config;
+    let (iface, tar2) = ap.iface_and_tar2();
+    if segment.size == DataSize::U32 {
+        let mut words = vec![0; segment.len / 4];
+        let reads = WordReads {
+            endianness: Endianness::Little,
+            cancellation: &Cancellation::none(),
+            discard_first_read: config.discard_first_read,
+            max_burst_words: config.max_burst_words,
+            tar2,
+        };
+        read_words(
+            iface,
+            config.base,
+            segment.address,
+            &mut words,
+            &reads,
+            &mut 0,
+        )?;
+        return Ok(words.iter().flat_map(|word| word.to_le_bytes()).collect());
+    }
+
+    let size = segment.size.to_byte_count();
+    let step = if segment.increment == AddressIncrement::Packed {
+        4
+    } else {
+        size
+    };
+
+    let mut bytes = Vec::with_capacity(segment.len);
+    let mut address = segment.address;
+    let drw = config.base + u64::from(DRW::ADDRESS);
+    while bytes.len() < segment.len {
+        if address == segment.address || address % AUTO_INCREMENT_BLOCK_SIZE == 0 {
+            program_transfer_address(iface, config.base, address, tar2)?;
+            if config.discard_first_read {
+                iface.read_word_32(drw)?;
+            }
+        }
+        let word = iface.read_word_32(drw)?;
+        if step == 4 {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        } else {
+            // Sub-word data is transferred on the byte lanes of its address.
+            let lane = (address % 4) as u32 * 8;
+            bytes.extend_from_slice(&(word >> lane).to_le_bytes()[..size]);
+        }
+        address += step as u64;
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use super::{plan_transfer, read_planned, MemApCapabilities, TransferSegment};
+    use crate::architecture::arm::ap_v2::{
+        memory_ap::{mock::MockMemoryAp, MemoryAp},
+        registers::{AddressIncrement, DataSize, Register, CFG},
+    };
+
+    const BASE: u64 = 0x2000;
+
+    fn segment(address: u64, len: usize, size: DataSize) -> TransferSegment {
+        TransferSegment {
+            address,
+            len,
+            size,
+            increment: AddressIncrement::Single,
+        }
+    }
+
+    #[test]
+    fn aligned_words_are_a_single_body() {
+        let plan = plan_transfer(0x100, 64, &MemApCapabilities::default());
+        assert_eq!(plan.head, None);
+        assert_eq!(plan.body, Some(segment(0x100, 64, DataSize::U32)));
+        assert_eq!(plan.tail, None);
+        assert_eq!((plan.tar_writes(), plan.drw_accesses()), (1, 16));
+    }
+
+    #[test]
+    fn unaligned_ends_become_head_and_tail() {
+        let plan = plan_transfer(0x101, 10, &MemApCapabilities::default());
+        assert_eq!(plan.head, Some(segment(0x101, 3, DataSize::U8)));
+        assert_eq!(plan.body, Some(segment(0x104, 4, DataSize::U32)));
+        assert_eq!(plan.tail, Some(segment(0x108, 3, DataSize::U8)));
+        assert_eq!((plan.tar_writes(), plan.drw_accesses()), (3, 7));
+
+        // A halfword within a single word.
+        let plan = plan_transfer(0x102, 2, &MemApCapabilities::default());
+        assert_eq!(plan.head, Some(segment(0x102, 2, DataSize::U16)));
+        assert_eq!((plan.body, plan.tail), (None, None));
+    }
+
+    #[test]
+    fn word_only_ap_reads_containing_words() {
+        let caps = MemApCapabilities {
+            sub_word: Some(false),
+            ..Default::default()
+        };
+        let plan = plan_transfer(0x102, 4, &caps);
+        assert_eq!(plan.head, Some(segment(0x100, 4, DataSize::U32)));
+        assert_eq!(plan.tail, Some(segment(0x104, 4, DataSize::U32)));
+    }
+
+    #[test]
+    fn narrow_bus_body_is_packed_if_supported() {
+        let mut caps = MemApCapabilities {
+            max_size: DataSize::U8,
+            ..Default::default()
+        };
+        assert_eq!(plan_transfer(0x100, 16, &caps).drw_accesses(), 16);
+
+        caps.packed = Some(true);
+        let plan = plan_transfer(0x100, 16, &caps);
+        assert_eq!(plan.body.unwrap().increment, AddressIncrement::Packed);
+        assert_eq!(plan.drw_accesses(), 4);
+    }
+
+    #[test]
+    fn body_crossing_a_block_programs_the_tar_twice() {
+        let plan = plan_transfer(0x3F0, 32, &MemApCapabilities::default());
+        assert_eq!(plan.tar_writes(), 2);
+    }
+
+    #[test]
+    fn executed_plans_read_the_memory() {
+        let caps = [
+            MemApCapabilities::default(),
+            MemApCapabilities {
+                sub_word: Some(false),
+                ..Default::default()
+            },
+            MemApCapabilities {
+                max_size: DataSize::U16,
+                ..Default::default()
+            },
+            MemApCapabilities {
+                max_size: DataSize::U8,
+                packed: Some(true),
+                ..Default::default()
+            },
+        ];
+        for caps in caps {
+            for (address, len) in [(0x100, 16), (0x101, 10), (0x102, 2), (0x3FD, 8)] {
+                let mut mock = MockMemoryAp::with_pattern(BASE);
+                mock.word_only = caps.sub_word == Some(false);
+                let expected = mock.bytes(address, len);
+
+                let mut out = vec![0; len];
+                read_planned(&mut mock, BASE, address, &mut out, &caps).unwrap();
+                assert_eq!(out, expected, "{caps:?} at {address:#x}");
+            }
+        }
+    }
+
+    #[test]
+    fn handle_reads_follow_the_burst_limit() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let expected = mock.bytes(0x102, 36);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_max_burst_words(NonZeroUsize::new(4));
+
+        let mut out = vec![0; 36];
+        ap.read_planned(0x102, &mut out).unwrap();
+        drop(ap);
+        assert_eq!(out, expected);
+        // The halfword head and tail, and the 8 word body in bursts of 4 + 4 words.
+        assert_eq!(mock.tar_writes, 4);
+    }
+
+    #[test]
+    fn handle_reads_discard_the_first_read() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.pipelined_drw = true;
+        let expected = mock.bytes(0x3FD, 10);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_discard_first_read(true);
+
+        let mut out = vec![0; 10];
+        ap.read_planned(0x3FD, &mut out).unwrap();
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn probe_detects_word_only_ap() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        assert_eq!(
+            MemApCapabilities::probe(&mut mock, BASE).unwrap().sub_word,
+            Some(true)
+        );
+        mock.word_only = true;
+        assert_eq!(
+            MemApCapabilities::probe(&mut mock, BASE).unwrap().sub_word,
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn probe_detects_large_data_sizes() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let large_data = CFG {
+            LD: true,
+            ..CFG::try_from(0).unwrap()
+        };
+        mock.set_register(CFG::ADDRESS, large_data.into());
+
+        let caps = MemApCapabilities::probe(&mut mock, BASE).unwrap();
+        assert_eq!(caps.max_size, DataSize::U256);
+    }
+
+    #[test]
+    fn probe_tries_the_sizes_in_the_given_order() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let large_data = CFG {
+            LD: true,
+            ..CFG::try_from(0).unwrap()
+        };
+        mock.set_register(CFG::ADDRESS, large_data.into());
+        mock.unsupported_sizes = vec![DataSize::U128];
+
+        let probe = |mock: &mut MockMemoryAp, order: &[DataSize]| {
+            MemApCapabilities::probe_with_order(mock, BASE, order)
+                .unwrap()
+                .max_size
+        };
+        assert_eq!(
+            probe(&mut mock, MemApCapabilities::DEFAULT_SIZE_ORDER),
+            DataSize::U256
+        );
+        assert_eq!(
+            probe(&mut mock, &[DataSize::U128, DataSize::U64]),
+            DataSize::U64
+        );
+        assert_eq!(probe(&mut mock, &[DataSize::U128]), DataSize::U32);
+        // Word transfers are never excluded.
+        assert_eq!(probe(&mut mock, &[DataSize::U8]), DataSize::U32);
+    }
+}
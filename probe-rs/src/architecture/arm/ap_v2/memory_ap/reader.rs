@@ -0,0 +1,252 @@
+//! Reading target memory through the [`std::io`] traits.
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::{architecture::arm::ArmError, MemoryInterface};
+
+use super::MemoryAp;
+
+/// The number of bytes read into the buffer at once.
+const CHUNK_BYTES: usize = 256;
+
+/// Reads the memory behind a [`MemoryAp`] as a byte stream, for parsers of firmware formats which
+/// take [`Read`] + [`Seek`].
+///
+/// The cursor is a target address. Seeking only moves the cursor, the memory is read in buffered
+/// chunks when it is read from, with the transfers [`plan_transfer`](super::plan_transfer) plans
+/// for the chunk. The chunks start at the word containing the cursor and are read as word blocks
+/// following the settings of the handle, such as [`MemoryAp::set_max_burst_words`]. If the AP
+/// has an address range (see [`MemoryAp::set_address_range`]), the chunks are clipped to it, so
+/// the bytes at an unaligned start or end of the range are read with sub-word transfers, or
+/// sliced out of their containing words on an AP without them. The stream ends at the end of the
+/// range, and seeking outside of it fails.
+pub struct MemoryApReader<'ap, 'iface, M: MemoryInterface<ArmError> + ?Sized> {
+    ap: &'ap mut MemoryAp<'iface, M>,
+    position: u64,
+    bytes: [u8; CHUNK_BYTES],
+    /// The address of the first buffered byte.
+    buffer_start: u64,
+    /// The number of buffered bytes.
+    buffer_len: usize,
+}
+
+impl<'ap, 'iface, M> MemoryApReader<'ap, 'iface, M>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    /// Creates a reader over the memory of `ap`, starting at `address`.
+    pub fn new(ap: &'ap mut MemoryAp<'iface, M>, address: u64) -> Self {
+        Self {
+            ap,
+            position: address,
+            bytes: [0; CHUNK_BYTES],
+            buffer_start: 0,
+            buffer_len: 0,
+        }
+    }
+
+    /// The address the next read starts at.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// The address the stream ends at, if the AP has an address range.
+    fn end(&self) -> Option<u64> {
+        self.ap.address_range().map(|range| range.end)
+    }
+
+    fn is_buffered(&self, address: u64) -> bool {
+        (self.buffer_start..self.buffer_start + self.buffer_len as u64).contains(&address)
+    }
+
+    /// Reads the chunk starting at the word containing the cursor, clipped to the address range
+    /// of the AP, into the buffer.
+    fn fill_buffer(&mut self) -> io::Result<()> {
+        let mut start = self.position & !0b11;
+        let mut end = start + CHUNK_BYTES as u64;
+        if let Some(range) = self.ap.address_range() {
+            start = start.max(range.start);
+            end = end.min(range.end);
+        }
+        let len = end.saturating_sub(start) as usize;
+
+        self.ap
+            .read_planned(start, &mut self.bytes[..len])
+            .map_err(io::Error::other)?;
+        self.buffer_start = start;
+        self.buffer_len = len;
+        Ok(())
+    }
+}
+
+impl<M> Read for MemoryApReader<'_, '_, M>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.end().is_some_and(|end| self.position >= end) {
+            return Ok(0);
+        }
+        if !self.is_buffered(self.position) {
+            self.fill_buffer()?;
+            if !self.is_buffered(self.position) {
+                return Ok(0);
+            }
+        }
+
+        let offset = (self.position - self.buffer_start) as usize;
+        let buffered = &self.bytes[offset..self.buffer_len];
+        let count = buf.len().min(buffered.len());
+        buf[..count].copy_from_slice(&buffered[..count]);
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl<M> Seek for MemoryApReader<'_, '_, M>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(address) => Some(address),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+            SeekFrom::End(delta) => {
+                let end = self.end().ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "the memory access port has no address range to seek from the end of",
+                    )
+                })?;
+                end.checked_add_signed(delta)
+            }
+        };
+        let target = target.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek to a negative or overflowing address",
+            )
+        })?;
+
+        if let Some(range) = self.ap.address_range() {
+            if target < range.start || target > range.end {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("address {target:#x} is outside the memory window {range:#x?}"),
+                ));
+            }
+        }
+
+        self.position = target;
+        Ok(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Seek, SeekFrom},
+        num::NonZeroUsize,
+    };
+
+    use super::MemoryApReader;
+    use crate::architecture::arm::ap_v2::memory_ap::{
+        mock::MockMemoryAp, MemApCapabilities, MemoryAp,
+    };
+
+    const BASE: u64 = 0x2000;
+
+    #[test]
+    fn reads_unaligned_bytes_across_chunks() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let expected = mock.bytes(0x103, 600);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+
+        let mut reader = MemoryApReader::new(&mut ap, 0x103);
+        let mut read = vec![0; 600];
+        reader.read_exact(&mut read).unwrap();
+        assert_eq!(read, expected);
+        assert_eq!(reader.position(), 0x103 + 600);
+    }
+
+    #[test]
+    fn reads_follow_the_burst_limit() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let expected = mock.bytes(0x100, 40);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_address_range(0x100..0x100 + 40);
+        ap.set_max_burst_words(NonZeroUsize::new(4));
+
+        let mut read = Vec::new();
+        MemoryApReader::new(&mut ap, 0x100)
+            .read_to_end(&mut read)
+            .unwrap();
+        drop(ap);
+        assert_eq!(read, expected);
+        // The 10 buffered words are read in bursts of 4 + 4 + 2 words.
+        assert_eq!(mock.tar_writes, 3);
+    }
+
+    #[test]
+    fn reads_an_unaligned_address_range() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let expected = mock.bytes(0x101, 0x10A);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_address_range(0x101..0x20B);
+
+        let mut read = Vec::new();
+        MemoryApReader::new(&mut ap, 0x101)
+            .read_to_end(&mut read)
+            .unwrap();
+        assert_eq!(read, expected);
+
+        // A word-only AP slices the bytes out of the words containing them.
+        mock.word_only = true;
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_address_range(0x101..0x20B);
+        ap.set_capabilities(MemApCapabilities {
+            sub_word: Some(false),
+            ..Default::default()
+        });
+
+        let mut read = Vec::new();
+        MemoryApReader::new(&mut ap, 0x101)
+            .read_to_end(&mut read)
+            .unwrap();
+        assert_eq!(read, expected);
+    }
+
+    #[test]
+    fn seeking_does_not_transfer() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let expected = mock.bytes(0x1FE, 2);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_address_range(0x100..0x200);
+
+        let mut reader = MemoryApReader::new(&mut ap, 0x100);
+        assert_eq!(reader.seek(SeekFrom::End(-2)).unwrap(), 0x1FE);
+        assert_eq!(reader.seek(SeekFrom::Current(-0x10)).unwrap(), 0x1EE);
+        assert_eq!(reader.seek(SeekFrom::Start(0x1FE)).unwrap(), 0x1FE);
+        assert_eq!(mock.tar_writes, 0);
+
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_address_range(0x100..0x200);
+        let mut reader = MemoryApReader::new(&mut ap, 0x1FE);
+        let mut read = Vec::new();
+        // The stream ends at the end of the address range.
+        reader.read_to_end(&mut read).unwrap();
+        assert_eq!(read, expected);
+    }
+
+    #[test]
+    fn seeking_outside_the_address_range_fails() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        ap.set_address_range(0x100..0x200);
+
+        let mut reader = MemoryApReader::new(&mut ap, 0x100);
+        assert!(reader.seek(SeekFrom::Start(0x201)).is_err());
+        assert!(reader.seek(SeekFrom::Current(-1)).is_err());
+        assert_eq!(reader.position(), 0x100);
+    }
+}
@@ -0,0 +1,131 @@
+//! Recovery from faulted transfers.
+
+use crate::{
+    architecture::arm::{
+        ap_v2::registers::{CFG, CSW, TAR, TAR2, TRR},
+        dp::{Abort, DpAddress, DpRegister},
+        ArmError, DapAccess, DapError,
+    },
+    MemoryInterface,
+};
+
+use super::{
+    transfer::{read_register, write_register},
+    MemoryApError,
+};
+
+/// The kind of fault a failed transfer was caused by, see [`FaultKind::of`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultKind {
+    /// The transfer failed transiently, e.g. with a WAIT response or a protocol error.
+    Transient,
+    /// The transfer failed with a FAULT response: the memory access faulted on the bus, e.g.
+    /// because the address is not mapped. Retrying the transfer fails again.
+    Bus,
+}
+
+impl FaultKind {
+    /// The kind of fault the transfer which failed with `error` was caused by.
+    ///
+    /// This is decided from the response of the failed transfer, not from
+    /// `CTRL/STAT.STICKYERR`: the probe drivers clear the sticky errors as soon as they see a
+    /// FAULT response, so the flag is usually clear again by the time the AP layer sees the
+    /// error.
+    pub fn of(error: &MemoryApError) -> Self {
+        match error {
+            MemoryApError::Arm(ArmError::Dap(DapError::FaultResponse)) => FaultKind::Bus,
+            _ => FaultKind::Transient,
+        }
+    }
+}
+
+/// Clears the sticky error flags of the debug port `dp` after a failed AP transfer.
+///
+/// While `CTRL/STAT.STICKYERR` is set, the debug port does not perform any further AP
+/// transfers, so the AP stays unusable until the flags are cleared through the ABORT
+/// register.
+pub fn clear_ap_errors<D>(iface: &mut D, dp: DpAddress) -> Result<(), ArmError>
+where
+    D: DapAccess + ?Sized,
+{
+    let mut abort = Abort(0);
+    abort.set_orunerrclr(true);
+    abort.set_wderrclr(true);
+    abort.set_stkerrclr(true);
+    abort.set_stkcmpclr(true);
+    iface.write_raw_dp_register(dp, Abort::ADDRESS, abort.into())?;
+    Ok(())
+}
+
+/// Checks the memory access port at `base` after a failed transfer.
+///
+/// Fails with [`MemoryApError::TransferInProgress`] if `CSW.TrInProg` is still set. The rest of
+/// the CSW, including `CSW.DbgSwEnable`, is left as it is.
+pub(super) fn check_ap_after_fault<M>(iface: &mut M, base: u64) -> Result<(), MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    let csw: CSW = read_register(iface, base)?;
+    if csw.TrInProg {
+        return Err(MemoryApError::TransferInProgress);
+    }
+    Ok(())
+}
+
+/// Aborts the AP transaction in flight on the debug port `dp` by setting `ABORT.DAPABORT`.
+///
+/// This frees an AP whose transfer never completes, e.g. because the bus behind it hangs. The
+/// aborted transfer is lost.
+pub fn abort_ap_transfer<D>(iface: &mut D, dp: DpAddress) -> Result<(), ArmError>
+where
+    D: DapAccess + ?Sized,
+{
+    let mut abort = Abort(0);
+    abort.set_dapabort(true);
+    iface.write_raw_dp_register(dp, Abort::ADDRESS, abort.into())?;
+    Ok(())
+}
+
+/// Clears the error-stop state of the memory access port at `base` after a failed memory
+/// access, and returns the address of the access which faulted.
+///
+/// If `CFG.ERR` reports error control and `CSW.ERRSTOP` is set, the AP stops performing memory
+/// accesses after one of them faults, until the error is cleared. The recovery:
+///
+/// 1. reads `CFG.ERR` and `CSW.ERRSTOP`, and returns `None` if the AP does not stop on errors,
+/// 2. reads `TRR.ERR`, and returns `None` if no memory access faulted,
+/// 3. reads the TAR, and TAR2 if `CFG.LA` is set, which hold the address of the faulting access
+///    until they are written again, as the TAR is not incremented after a failed access,
+/// 4. writes `1` to `TRR.ERR`, which re-enables memory accesses.
+///
+/// If `CSW.ERRNPASS` is clear, the fault was also reported to the debug port, whose sticky errors
+/// have to be cleared first, see [`clear_ap_errors`]. If it is set, the faulting access and the
+/// accesses blocked after it complete without an error response, so this has to be called after
+/// a sequence of accesses to check whether they took effect.
+pub fn recover_error_stop<M>(iface: &mut M, base: u64) -> Result<Option<u64>, MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    let cfg: CFG = read_register(iface, base)?;
+    if !cfg.supports_error_control() {
+        return Ok(None);
+    }
+    let csw: CSW = read_register(iface, base)?;
+    if !csw.ERRSTOP {
+        return Ok(None);
+    }
+    let trr: TRR = read_register(iface, base)?;
+    if !trr.ERR {
+        return Ok(None);
+    }
+
+    let tar: TAR = read_register(iface, base)?;
+    let mut address = u64::from(tar.address);
+    if cfg.LA {
+        let tar2: TAR2 = read_register(iface, base)?;
+        address |= u64::from(tar2.address) << 32;
+    }
+    tracing::debug!("Memory access to {address:#x} faulted with CSW.ERRSTOP set, clearing TRR.ERR");
+    write_register(iface, base, TRR { ERR: true })?;
+    Ok(Some(address))
+}
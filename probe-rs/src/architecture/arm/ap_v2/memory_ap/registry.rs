@@ -0,0 +1,90 @@
+//! Lookup of the memory AP registers by name or address at runtime.
+
+use crate::{
+    architecture::arm::{
+        ap_v2::registers::{RegisterDescriptor, REGISTERS},
+        ArmError,
+    },
+    MemoryInterface,
+};
+
+use super::{MemoryAp, MemoryApError};
+
+impl RegisterDescriptor {
+    /// Reads this register of the memory access port `ap`, at the address its ADI version
+    /// puts the register at, see [`MemoryAp::register_address`].
+    ///
+    /// Returns the raw value together with the formatted parsed value.
+    pub fn read<M>(&self, ap: &mut MemoryAp<'_, M>) -> Result<(u32, String), MemoryApError>
+    where
+        M: MemoryInterface<ArmError> + ?Sized,
+    {
+        let raw = ap.read_raw(self.address)?;
+        Ok((raw, self.format(raw)?))
+    }
+}
+
+/// Returns the register called `name`, ignoring ASCII case.
+pub fn register_by_name(name: &str) -> Option<&'static RegisterDescriptor> {
+    REGISTERS
+        .iter()
+        .find(|register| register.name.eq_ignore_ascii_case(name))
+}
+
+/// Returns the register at offset `address` within the AP's register file.
+pub fn register_by_address(address: u16) -> Option<&'static RegisterDescriptor> {
+    REGISTERS
+        .iter()
+        .find(|register| register.address == address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{register_by_address, register_by_name, REGISTERS};
+    use crate::architecture::arm::{
+        ap_v2::{
+            compat::ApV1RegisterFile,
+            memory_ap::{mock::MockMemoryAp, MemoryAp},
+            registers::{Register, TAR},
+        },
+        dp::DpAddress,
+    };
+
+    const BASE: u64 = 0x2000;
+
+    #[test]
+    fn lookup_by_name_and_address() {
+        assert_eq!(register_by_name("csw").unwrap().address, 0xD00);
+        assert_eq!(register_by_address(0xDFC).unwrap().name, "IDR");
+        assert!(register_by_name("NOPE").is_none());
+        assert!(register_by_address(0xD02).is_none());
+
+        // Every register defined with `define_apv2_register!` is collected.
+        assert_eq!(REGISTERS.len(), 15);
+        for register in REGISTERS {
+            assert_eq!(
+                register_by_address(register.address).unwrap().name,
+                register.name
+            );
+        }
+    }
+
+    #[test]
+    fn read_returns_raw_and_parsed_value() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut ap = MemoryAp::new(&mut mock, BASE);
+        let (raw, parsed) = register_by_name("CSW").unwrap().read(&mut ap).unwrap();
+
+        assert_eq!(raw, 0x8000_0052);
+        assert!(parsed.starts_with("CSW {"));
+
+        // An ADIv5 register file is accessed through the debug port.
+        mock.set_register(TAR::ADDRESS, 0x2000_0040);
+        let mut registers = ApV1RegisterFile::new(&mut mock, DpAddress::Default, 1);
+        let mut ap = MemoryAp::new_v1(&mut registers);
+        let (raw, parsed) = register_by_name("TAR").unwrap().read(&mut ap).unwrap();
+        assert_eq!(raw, 0x2000_0040);
+        assert!(parsed.starts_with("TAR {"));
+        assert_eq!(mock.selects, [0x0100_0000]);
+    }
+}
@@ -0,0 +1,124 @@
+//! Retrying of transient transfer faults.
+
+use std::time::Duration;
+
+use crate::architecture::arm::{ArmError, DapError};
+
+use super::{FaultKind, MemoryApError};
+
+/// How often and how fast a failed memory access port transfer is retried.
+///
+/// Only transient transport errors, such as a WAIT or FAULT acknowledge, are retried. Parse and
+/// capability errors fail immediately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// The delay before the first retry. The delay doubles with every further retry.
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// A policy which never retries.
+    pub const fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff: Duration::ZERO,
+        }
+    }
+
+    /// Creates a policy with up to `max_attempts` attempts and the given initial `backoff`.
+    pub const fn new(max_attempts: u32, backoff: Duration) -> Self {
+        Self {
+            max_attempts,
+            backoff,
+        }
+    }
+
+    /// Runs `op` until it succeeds, fails with a non-retryable error or the attempts run out.
+    ///
+    /// A transfer which failed with a FAULT response faulted on the bus, see [`FaultKind::of`],
+    /// and is not retried. Before every retry, `recover` is called with the error. If it fails,
+    /// the transfer is not retried either and fails with its own error.
+    pub(super) fn run<S: ?Sized, T>(
+        &self,
+        state: &mut S,
+        mut op: impl FnMut(&mut S) -> Result<T, MemoryApError>,
+        mut recover: impl FnMut(&mut S, &MemoryApError) -> Result<(), MemoryApError>,
+    ) -> Result<T, MemoryApError> {
+        let mut delay = self.backoff;
+        let mut attempt = 1;
+        loop {
+            match op(state) {
+                Err(error) if error.is_retryable() => {
+                    if attempt >= self.max_attempts || FaultKind::of(&error) == FaultKind::Bus {
+                        return Err(error);
+                    }
+                    if let Err(recovery_error) = recover(state, &error) {
+                        tracing::debug!(
+                            "Recovery after a failed transfer failed: {recovery_error}"
+                        );
+                        return Err(error);
+                    }
+                    tracing::debug!("Retrying memory AP transfer after error: {error}");
+                    std::thread::sleep(delay);
+                    delay *= 2;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl MemoryApError {
+    /// Returns whether the error is a transient transport error that may succeed when retried.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            MemoryApError::Arm(
+                ArmError::Timeout
+                    | ArmError::Dap(
+                        DapError::WaitResponse
+                            | DapError::FaultResponse
+                            | DapError::NoAcknowledge
+                            | DapError::SwdProtocol
+                            | DapError::IncorrectParity
+                    )
+            )
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::RetryPolicy;
+    use crate::architecture::arm::ap_v2::memory_ap::MemoryApError;
+
+    #[test]
+    fn does_not_retry_permanent_errors() {
+        let mut attempts = 0;
+        let result: Result<(), _> = RetryPolicy::new(3, Duration::ZERO).run(
+            &mut attempts,
+            |attempts| {
+                *attempts += 1;
+                Err(MemoryApError::WroteReadOnly {
+                    register: "CSW",
+                    field: "TrInProg",
+                })
+            },
+            |_, _| Ok(()),
+        );
+
+        assert!(matches!(result, Err(MemoryApError::WroteReadOnly { .. })));
+        assert_eq!(attempts, 1);
+    }
+}
@@ -0,0 +1,246 @@
+//! Continuous sampling of a single memory location.
+
+use std::{
+    sync::mpsc::{SyncSender, TrySendError},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    architecture::arm::{
+        ap_v2::registers::{AddressIncrement, DataSize, Register, CSW, DRW},
+        ArmError,
+    },
+    MemoryInterface,
+};
+
+use super::{transfer, write_register, Cancellation, MemoryApError};
+
+/// A word read by a [`DrwSampler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrwSample {
+    /// The time since the sampler was started.
+    pub timestamp: Duration,
+    /// The word read.
+    pub value: u32,
+}
+
+/// A buffer which a [`DrwSampler`] pushes its samples into, usually a bounded single producer,
+/// single consumer ring buffer drained by another thread.
+pub trait SampleSink {
+    /// Adds `sample` to the buffer without blocking. Returns `false` if the buffer is full and
+    /// the sample was dropped.
+    fn try_push(&mut self, sample: DrwSample) -> bool;
+}
+
+/// A bounded channel, created with [`std::sync::mpsc::sync_channel`], is a ring buffer whose
+/// receiver can be moved to the consumer thread.
+impl SampleSink for SyncSender<DrwSample> {
+    fn try_push(&mut self, sample: DrwSample) -> bool {
+        match self.try_send(sample) {
+            Ok(()) => true,
+            // A disconnected consumer drops the samples like a full buffer.
+            Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) => false,
+        }
+    }
+}
+
+/// The number of samples a [`DrwSampler`] captured and lost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SamplerStats {
+    /// The number of samples pushed into the buffer.
+    pub samples: u64,
+    /// The number of samples dropped because the buffer was full.
+    pub overflows: u64,
+}
+
+/// Reads the word at a fixed address continuously, for capturing a value which changes at a high
+/// rate, like a trace or status word.
+///
+/// Starting the sampler turns the address increment off and programs the TAR once, so every
+/// sample is a single DRW read. The samples are timestamped and pushed into a [`SampleSink`];
+/// samples which do not fit are counted as overflows instead of blocking the capture.
+/// [`DrwSampler::stop`] restores the CSW, as does dropping the sampler.
+pub struct DrwSampler<'iface, M: MemoryInterface<ArmError> + ?Sized> {
+    iface: &'iface mut M,
+    base: u64,
+    previous: Option<CSW>,
+    start: Instant,
+    stats: SamplerStats,
+}
+
+impl<'iface, M> DrwSampler<'iface, M>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    /// Configures the memory access port at `base` for sampling the word at `address`.
+    pub fn start(iface: &'iface mut M, base: u64, address: u64) -> Result<Self, MemoryApError> {
+        if address % 4 != 0 {
+            return Err(ArmError::alignment_error(address, 4).into());
+        }
+
+        let previous =
+            transfer::configure_transfer(iface, base, DataSize::U32, AddressIncrement::Off)?;
+        let sampler = Self {
+            iface,
+            base,
+            previous: Some(previous),
+            start: Instant::now(),
+            stats: SamplerStats::default(),
+        };
+        transfer::set_transfer_address(sampler.iface, base, address)?;
+        Ok(sampler)
+    }
+
+    /// Reads one sample and pushes it into `sink`. Returns whether it fit into the buffer.
+    ///
+    /// The result can be ignored, dropped samples are also counted in [`SamplerStats::overflows`].
+    pub fn sample(&mut self, sink: &mut impl SampleSink) -> Result<bool, MemoryApError> {
+        let value = self
+            .iface
+            .read_word_32(self.base + u64::from(DRW::ADDRESS))?;
+        let sample = DrwSample {
+            timestamp: self.start.elapsed(),
+            value,
+        };
+        let pushed = sink.try_push(sample);
+        if pushed {
+            self.stats.samples += 1;
+        } else {
+            self.stats.overflows += 1;
+        }
+        Ok(pushed)
+    }
+
+    /// Samples every `interval` until `cancellation` is triggered, e.g. by the consumer thread
+    /// setting a flag.
+    ///
+    /// With a zero interval, the word is sampled as fast as the probe allows. A sample which
+    /// takes longer than the interval delays the next one, the missed samples are not made up.
+    pub fn run(
+        &mut self,
+        sink: &mut impl SampleSink,
+        interval: Duration,
+        cancellation: &Cancellation,
+    ) -> Result<SamplerStats, MemoryApError> {
+        let mut next = Instant::now();
+        while !cancellation.is_cancelled() {
+            self.sample(sink)?;
+            next += interval;
+            if let Some(wait) = next.checked_duration_since(Instant::now()) {
+                std::thread::sleep(wait);
+            } else {
+                next = Instant::now();
+            }
+        }
+        Ok(self.stats)
+    }
+
+    /// The number of samples captured and lost so far.
+    pub fn stats(&self) -> SamplerStats {
+        self.stats
+    }
+
+    /// Stops sampling and restores the CSW, returning the final statistics.
+    pub fn stop(mut self) -> Result<SamplerStats, MemoryApError> {
+        if let Some(previous) = self.previous.take() {
+            write_register(self.iface, self.base, previous)?;
+        }
+        Ok(self.stats)
+    }
+}
+
+impl<M> Drop for DrwSampler<'_, M>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            if let Err(err) = write_register(self.iface, self.base, previous) {
+                tracing::warn!(
+                    "Failed to restore the CSW of the AP at {:#x}: {err}",
+                    self.base
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::mpsc::sync_channel,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use super::{DrwSample, DrwSampler, SampleSink, SamplerStats};
+    use crate::architecture::arm::ap_v2::{
+        memory_ap::{mock::MockMemoryAp, read_register, Cancellation},
+        registers::{AddressIncrement, CSW},
+    };
+
+    const BASE: u64 = 0x2000;
+
+    impl SampleSink for Vec<DrwSample> {
+        fn try_push(&mut self, sample: DrwSample) -> bool {
+            self.push(sample);
+            true
+        }
+    }
+
+    #[test]
+    fn samples_are_read_from_a_fixed_address() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let expected = mock.words(0x20, 1)[0];
+
+        let mut sampler = DrwSampler::start(&mut mock, BASE, 0x20).unwrap();
+        let mut samples = vec![];
+        for _ in 0..3 {
+            assert!(sampler.sample(&mut samples).unwrap());
+        }
+        let stats = sampler.stop().unwrap();
+
+        assert_eq!(
+            stats,
+            SamplerStats {
+                samples: 3,
+                overflows: 0
+            }
+        );
+        assert!(samples.iter().all(|sample| sample.value == expected));
+        assert!(samples.is_sorted_by_key(|sample| sample.timestamp));
+        assert_eq!(mock.tar_writes, 1);
+        let csw: CSW = read_register(&mut mock, BASE).unwrap();
+        assert_eq!(csw.AddrInc, AddressIncrement::Single);
+    }
+
+    #[test]
+    fn full_buffers_count_overflows() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let (mut sender, receiver) = sync_channel(2);
+
+        let mut sampler = DrwSampler::start(&mut mock, BASE, 0x20).unwrap();
+        assert!(sampler.sample(&mut sender).unwrap());
+        assert!(sampler.sample(&mut sender).unwrap());
+        assert!(!sampler.sample(&mut sender).unwrap());
+        // The consumer drains the buffer from another thread.
+        let consumer = thread::spawn(move || receiver.iter().count());
+        let cancellation = Cancellation::none().with_deadline(Instant::now());
+        sampler
+            .run(&mut sender, Duration::ZERO, &cancellation)
+            .unwrap();
+        assert_eq!(
+            sampler.stats(),
+            SamplerStats {
+                samples: 2,
+                overflows: 1
+            }
+        );
+        drop(sampler);
+        drop(sender);
+
+        assert_eq!(consumer.join().unwrap(), 2);
+        let csw: CSW = read_register(&mut mock, BASE).unwrap();
+        assert_eq!(csw.AddrInc, AddressIncrement::Single);
+    }
+}
@@ -0,0 +1,139 @@
+//! Reading of null-terminated strings.
+
+use crate::{architecture::arm::ArmError, MemoryInterface};
+
+use super::{transfer::read_block_into, MemoryApError};
+
+/// The number of words read at once while scanning for the terminator.
+const CHUNK_WORDS: usize = 16;
+
+/// Reads the null-terminated string at `address` through the memory access port at `base`.
+///
+/// At most `max_len` bytes are read, so a string which is not terminated within `max_len` bytes
+/// is truncated. Fails with [`MemoryApError::InvalidUtf8`] if the string is not valid UTF-8.
+pub fn read_cstr<M>(
+    iface: &mut M,
+    base: u64,
+    address: u64,
+    max_len: usize,
+) -> Result<String, MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    let bytes = read_cstr_bytes(iface, base, address, max_len)?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+/// Like [`read_cstr`], but replaces invalid UTF-8 sequences with `U+FFFD`.
+pub fn read_cstr_lossy<M>(
+    iface: &mut M,
+    base: u64,
+    address: u64,
+    max_len: usize,
+) -> Result<String, MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    let bytes = read_cstr_bytes(iface, base, address, max_len)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Reads the bytes of the string at `address` up to, but excluding, the terminator.
+fn read_cstr_bytes<M>(
+    iface: &mut M,
+    base: u64,
+    address: u64,
+    max_len: usize,
+) -> Result<Vec<u8>, MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    let mut bytes = Vec::new();
+    let mut words = [0u32; CHUNK_WORDS];
+    // The string may start in the middle of a word.
+    let mut skip = (address % 4) as usize;
+    let mut word_address = address - skip as u64;
+
+    while bytes.len() < max_len {
+        let remaining = max_len - bytes.len() + skip;
+        let count = remaining.div_ceil(4).min(CHUNK_WORDS);
+        read_block_into(iface, base, word_address, &mut words[..count])?;
+
+        let chunk = words[..count].iter().flat_map(|word| word.to_le_bytes());
+        for byte in chunk.skip(skip).take(max_len - bytes.len()) {
+            if byte == 0 {
+                return Ok(bytes);
+            }
+            bytes.push(byte);
+        }
+
+        skip = 0;
+        word_address += count as u64 * 4;
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_cstr, read_cstr_lossy};
+    use crate::architecture::arm::ap_v2::memory_ap::{mock::MockMemoryAp, MemoryApError};
+
+    const BASE: u64 = 0x2000;
+
+    fn with_string(address: u64, string: &[u8]) -> MockMemoryAp {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.map(address, string);
+        mock
+    }
+
+    #[test]
+    fn terminator_at_every_byte_offset() {
+        for start in 0x100..0x104 {
+            for len in 0..8 {
+                let string = &b"probe-rs\0"[8 - len..];
+                let mut mock = with_string(start, string);
+
+                let read = read_cstr(&mut mock, BASE, start, 64).unwrap();
+                assert_eq!(
+                    read.as_bytes(),
+                    &string[..len],
+                    "start {start:#x}, len {len}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn unterminated_string_is_truncated() {
+        let mut mock = with_string(0x101, b"abcdefgh");
+
+        // The pattern memory contains no zero bytes.
+        assert_eq!(read_cstr(&mut mock, BASE, 0x101, 5).unwrap(), "abcde");
+        assert_eq!(read_cstr(&mut mock, BASE, 0x101, 0).unwrap(), "");
+    }
+
+    #[test]
+    fn long_string_spans_chunks() {
+        let string = [b'x'; 100];
+        let mut mock = with_string(0x102, &string);
+        mock.map(0x102 + 100, &[0]);
+
+        let read = read_cstr(&mut mock, BASE, 0x102, 200).unwrap();
+        assert_eq!(read.len(), 100);
+    }
+
+    #[test]
+    fn invalid_utf8_is_rejected_unless_lossy() {
+        let mut mock = with_string(0x100, b"a\xFFb\0");
+
+        assert!(matches!(
+            read_cstr(&mut mock, BASE, 0x100, 16),
+            Err(MemoryApError::InvalidUtf8(_))
+        ));
+        assert_eq!(
+            read_cstr_lossy(&mut mock, BASE, 0x100, 16).unwrap(),
+            "a\u{FFFD}b"
+        );
+    }
+}
@@ -0,0 +1,137 @@
+//! Reading and writing of plain structs from and to target memory.
+
+use zerocopy::{FromBytes, Immutable, IntoBytes};
+
+use crate::{architecture::arm::ArmError, MemoryInterface};
+
+use super::{
+    transfer::{read_block_into, write_block},
+    MemoryApError,
+};
+
+/// Reads the `T` at `address` through the memory access port at `base`.
+///
+/// The `size_of::<T>()` bytes are read with a block transfer, so `address` has to be 4 byte
+/// aligned. `T` itself needs no alignment, as the bytes are copied into the returned value.
+///
+/// The bytes are taken in the order they are stored in target memory, and the fields of `T` are
+/// interpreted in the byte order of the host. For a target whose byte order differs, or to not
+/// depend on the host, use the types of [`zerocopy::byteorder`] for the fields.
+pub fn read_struct<T, M>(iface: &mut M, base: u64, address: u64) -> Result<T, MemoryApError>
+where
+    T: FromBytes,
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    let len = size_of::<T>();
+    let mut words = vec![0; len.div_ceil(4)];
+    read_block_into(iface, base, address, &mut words)?;
+
+    let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+    Ok(T::read_from_bytes(&bytes[..len]).expect("the buffer holds exactly the bytes of T"))
+}
+
+/// Writes `value` to `address` through the memory access port at `base`, see [`read_struct`].
+///
+/// `address` has to be 4 byte aligned. If the size of `T` is not a multiple of 4 bytes, the last
+/// word is read first, so the bytes following the struct are written back unchanged.
+pub fn write_struct<T, M>(
+    iface: &mut M,
+    base: u64,
+    address: u64,
+    value: &T,
+) -> Result<(), MemoryApError>
+where
+    T: IntoBytes + Immutable,
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    if address % 4 != 0 {
+        return Err(ArmError::alignment_error(address, 4).into());
+    }
+    let bytes = value.as_bytes();
+    let mut words: Vec<u32> = bytes
+        .chunks(4)
+        .map(|chunk| {
+            let mut word = [0; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            u32::from_le_bytes(word)
+        })
+        .collect();
+
+    let tail = bytes.len() % 4;
+    if tail != 0 {
+        let last = words.len() - 1;
+        let mut existing = [0];
+        read_block_into(iface, base, address + last as u64 * 4, &mut existing)?;
+        let mask = u32::MAX << (tail * 8);
+        words[last] |= existing[0] & mask;
+    }
+
+    write_block(iface, base, address, &words)
+}
+
+#[cfg(test)]
+mod tests {
+    use zerocopy::{byteorder::big_endian, FromBytes, Immutable, IntoBytes, KnownLayout};
+
+    use super::{read_struct, write_struct};
+    use crate::architecture::arm::ap_v2::memory_ap::{mock::MockMemoryAp, MemoryApError};
+
+    const BASE: u64 = 0x2000;
+
+    #[derive(Debug, PartialEq, Eq, FromBytes, IntoBytes, Immutable, KnownLayout)]
+    #[repr(C)]
+    struct Header {
+        magic: u32,
+        version: u16,
+        flags: u16,
+        length: big_endian::U32,
+    }
+
+    #[test]
+    fn read_struct_matches_memory() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.map(
+            0x100,
+            &[
+                0x78, 0x56, 0x34, 0x12, 0x02, 0x00, 0x01, 0x80, 0x00, 0x00, 0x01, 0x00,
+            ],
+        );
+
+        let header: Header = read_struct(&mut mock, BASE, 0x100).unwrap();
+        assert_eq!(
+            header,
+            Header {
+                magic: 0x1234_5678,
+                version: 2,
+                flags: 0x8001,
+                length: big_endian::U32::new(0x100),
+            }
+        );
+    }
+
+    #[test]
+    fn write_struct_keeps_the_bytes_after_a_partial_word() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let following = mock.bytes(0x106, 2);
+
+        write_struct(&mut mock, BASE, 0x100, &[1u8, 2, 3, 4, 5, 6]).unwrap();
+        assert_eq!(mock.bytes(0x100, 6), [1, 2, 3, 4, 5, 6]);
+        assert_eq!(mock.bytes(0x106, 2), following);
+
+        let read: [u8; 6] = read_struct(&mut mock, BASE, 0x100).unwrap();
+        assert_eq!(read, [1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn structs_need_an_aligned_address() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        assert!(matches!(
+            read_struct::<u32, _>(&mut mock, BASE, 0x102),
+            Err(MemoryApError::Arm(_))
+        ));
+        assert!(matches!(
+            write_struct(&mut mock, BASE, 0x102, &0u32),
+            Err(MemoryApError::Arm(_))
+        ));
+    }
+}
@@ -0,0 +1,229 @@
+//! Byte and halfword writes, also through memory access ports which only support word accesses.
+
+use crate::{
+    architecture::arm::{
+        ap_v2::registers::{AddressIncrement, DataSize, Register, CSW, DRW},
+        ArmError,
+    },
+    MemoryInterface,
+};
+
+use super::{
+    transfer::{program_transfer_address, read_register, write_register},
+    MemoryAp, MemoryApError,
+};
+
+impl DataSize {
+    /// Checks whether the memory access port at `base` supports transfers of this size.
+    ///
+    /// `CSW.SIZE` only holds the sizes the AP implements, so this writes the size to the CSW and
+    /// reads it back. The CSW is restored afterwards.
+    #[must_use = "transfers of an unsupported size fail or are performed with another size"]
+    pub fn probe_supported<M>(self, iface: &mut M, base: u64) -> Result<bool, MemoryApError>
+    where
+        M: MemoryInterface<ArmError> + ?Sized,
+    {
+        let address = base + u64::from(CSW::ADDRESS);
+        let previous = iface.read_word_32(address)?;
+        iface.write_word_32(address, (previous & !DataSize::MASK) | self.bits())?;
+        let readback = iface.read_word_32(address)?;
+        iface.write_word_32(address, previous)?;
+        Ok(readback & DataSize::MASK == self.bits())
+    }
+}
+
+/// Writes the byte `value` to `address` through the memory access port at `base`.
+///
+/// If the AP does not support byte transfers, the containing word is read, modified and written
+/// back, preserving its other bytes. The CSW is restored afterwards, also if the write fails.
+pub fn write_u8<M>(iface: &mut M, base: u64, address: u64, value: u8) -> Result<(), MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    let mut ap = MemoryAp::new(iface, base);
+    write_sub_word(&mut ap, address, u32::from(value), DataSize::U8)
+}
+
+/// Writes the halfword `value` to `address` through the memory access port at `base`.
+///
+/// If the AP does not support halfword transfers, the containing word is read, modified and
+/// written back, preserving its other halfword. The CSW is restored afterwards, also if the write
+/// fails.
+pub fn write_u16<M>(iface: &mut M, base: u64, address: u64, value: u16) -> Result<(), MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    let mut ap = MemoryAp::new(iface, base);
+    write_sub_word(&mut ap, address, u32::from(value), DataSize::U16)
+}
+
+/// Writes `value` of `size` to `address` through the handle `ap`.
+///
+/// The value is written with a transfer of `size` if the capabilities of `ap` include sub-word
+/// transfers, otherwise with a read-modify-write of the containing word. If sub-word support has
+/// not been probed yet, the AP is probed for byte and halfword transfers once, and the result is
+/// recorded in the capabilities. Capabilities without sub-word transfers skip probing, for APs
+/// which must not see a sub-word `CSW.SIZE`.
+pub(super) fn write_sub_word<M>(
+    ap: &mut MemoryAp<'_, M>,
+    address: u64,
+    value: u32,
+    size: DataSize,
+) -> Result<(), MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    let bytes = size.to_byte_count();
+    if address % bytes as u64 != 0 {
+        return Err(ArmError::alignment_error(address, bytes).into());
+    }
+    // Sub-word data is transferred on the byte lanes of its address.
+    let lane = (address % 4) as u32 * 8;
+    let mask = (u32::MAX >> (32 - 8 * size.to_byte_count() as u32)) << lane;
+
+    let base = ap.config.base;
+    let sub_word = match ap.config.capabilities.sub_word {
+        Some(sub_word) => sub_word,
+        None => {
+            let sub_word = DataSize::U8.probe_supported(ap.iface, base)?
+                && DataSize::U16.probe_supported(ap.iface, base)?;
+            ap.config.capabilities.sub_word = Some(sub_word);
+            sub_word
+        }
+    };
+    let transfer_size = if sub_word { size } else { DataSize::U32 };
+    let mut ap = ap.csw_guard()?;
+    ap.configure(transfer_size, AddressIncrement::Off)?;
+    let (iface, tar2) = ap.iface_and_tar2();
+
+    if transfer_size == size {
+        program_transfer_address(iface, base, address, tar2)?;
+        write_register(
+            iface,
+            base,
+            DRW {
+                data: value << lane,
+            },
+        )?;
+    } else {
+        program_transfer_address(iface, base, address & !0b11, tar2)?;
+        let word: DRW = read_register(iface, base)?;
+        let data = (word.data & !mask) | ((value << lane) & mask);
+        write_register(iface, base, DRW { data })?;
+    }
+
+    ap.restore()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_u16, write_u8};
+    use crate::architecture::arm::ap_v2::{
+        memory_ap::{mock::MockMemoryAp, read_register, MemoryAp},
+        registers::{DataSize, CSW},
+    };
+
+    const BASE: u64 = 0x2000;
+
+    /// The bytes around the sub-word accesses of the tests.
+    const WINDOW: (u64, usize) = (0x30, 0x20);
+
+    /// Returns the mock memory in [`WINDOW`] after writing the bytes of `value` to `address`.
+    fn expected(mock: &MockMemoryAp, address: u64, value: &[u8]) -> Vec<u8> {
+        let mut memory = mock.bytes(WINDOW.0, WINDOW.1);
+        let offset = (address - WINDOW.0) as usize;
+        memory[offset..offset + value.len()].copy_from_slice(value);
+        memory
+    }
+
+    #[test]
+    fn probe_detects_word_only_ap() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        assert!(DataSize::U8.probe_supported(&mut mock, BASE).unwrap());
+
+        mock.word_only = true;
+        assert!(!DataSize::U8.probe_supported(&mut mock, BASE).unwrap());
+        assert!(!DataSize::U16.probe_supported(&mut mock, BASE).unwrap());
+        assert!(DataSize::U32.probe_supported(&mut mock, BASE).unwrap());
+    }
+
+    #[test]
+    fn write_u8_at_every_offset() {
+        for word_only in [false, true] {
+            for offset in 0..4 {
+                let mut mock = MockMemoryAp::with_pattern(BASE);
+                mock.word_only = word_only;
+                let expected = expected(&mock, 0x40 + offset, &[0xA5]);
+
+                write_u8(&mut mock, BASE, 0x40 + offset, 0xA5).unwrap();
+                assert_eq!(
+                    mock.bytes(WINDOW.0, WINDOW.1),
+                    expected,
+                    "offset {offset}, word only {word_only}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn write_u16_at_every_offset() {
+        for word_only in [false, true] {
+            for offset in [0, 2] {
+                let mut mock = MockMemoryAp::with_pattern(BASE);
+                mock.word_only = word_only;
+                let expected = expected(&mock, 0x40 + offset, &[0x34, 0x12]);
+
+                write_u16(&mut mock, BASE, 0x40 + offset, 0x1234).unwrap();
+                assert_eq!(
+                    mock.bytes(WINDOW.0, WINDOW.1),
+                    expected,
+                    "offset {offset}, word only {word_only}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn handles_probe_sub_word_support_once() {
+        for word_only in [false, true] {
+            let mut mock = MockMemoryAp::with_pattern(BASE);
+            mock.word_only = word_only;
+            let mut ap = MemoryAp::new(&mut mock, BASE);
+
+            // Skip the checks before the first transfer, so only the writes are counted.
+            ap.write_u8(0x40, 0xA5).unwrap();
+            let start = ap.iface.accesses;
+            ap.config.capabilities.sub_word = None;
+            ap.write_u8(0x41, 0xA5).unwrap();
+            let probed = ap.iface.accesses - start;
+            assert_eq!(ap.config().capabilities.sub_word, Some(!word_only));
+
+            let start = ap.iface.accesses;
+            ap.write_u8(0x42, 0xA5).unwrap();
+            let cached = ap.iface.accesses - start;
+            // Two CSW reads and writes for every probed size.
+            let probe = if word_only { 4 } else { 8 };
+            assert_eq!(probed, cached + probe, "word only {word_only}");
+        }
+    }
+
+    #[test]
+    fn csw_is_restored_after_a_fault() {
+        for word_only in [false, true] {
+            let mut mock = MockMemoryAp::with_pattern(BASE);
+            mock.word_only = word_only;
+            let before: CSW = read_register(&mut mock, BASE).unwrap();
+            mock.faulting_addresses.extend([0x40, 0x41]);
+
+            assert!(write_u8(&mut mock, BASE, 0x41, 0xA5).is_err());
+            let after: CSW = read_register(&mut mock, BASE).unwrap();
+            assert_eq!(after, before, "word only {word_only}");
+        }
+    }
+
+    #[test]
+    fn write_u16_rejects_unaligned_address() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        assert!(write_u16(&mut mock, BASE, 0x41, 0x1234).is_err());
+    }
+}
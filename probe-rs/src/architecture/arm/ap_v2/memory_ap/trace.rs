@@ -0,0 +1,48 @@
+//! Observation of the register accesses of a memory access port.
+
+use std::time::Duration;
+
+/// A register access performed through a [`MemoryAp`](super::MemoryAp) handle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApAccess {
+    /// The name of the accessed register.
+    pub register_name: &'static str,
+    /// The address the register was accessed at.
+    pub address: u64,
+    /// Whether the access was a write.
+    pub is_write: bool,
+    /// The value that was read or written.
+    pub value: u32,
+    /// The time the access took, including retries.
+    pub duration: Duration,
+}
+
+/// Observes the register accesses of a [`MemoryAp`](super::MemoryAp) handle.
+///
+/// Only successful accesses of single registers are reported. The words of block transfers are
+/// not, as tracing them would slow down the transfer.
+pub trait ApTracer {
+    /// Called after each register access.
+    fn on_transfer(&self, access: &ApAccess);
+}
+
+/// An [`ApTracer`] which emits every access as a `tracing` event at the trace level.
+///
+/// Only available with the `ap-tracing` feature.
+#[cfg(feature = "ap-tracing")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingApTracer;
+
+#[cfg(feature = "ap-tracing")]
+impl ApTracer for TracingApTracer {
+    fn on_transfer(&self, access: &ApAccess) {
+        tracing::trace!(
+            register = access.register_name,
+            address = format_args!("{:#x}", access.address),
+            write = access.is_write,
+            value = format_args!("{:#010x}", access.value),
+            duration = ?access.duration,
+            "AP register access"
+        );
+    }
+}
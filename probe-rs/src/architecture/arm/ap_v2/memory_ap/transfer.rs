@@ -0,0 +1,1009 @@
+//! Register and block transfers through the register file of a memory access port.
+
+use std::{cell::Cell, num::NonZeroUsize};
+
+use crate::{
+    architecture::arm::{
+        ap_v2::registers::{
+            AddressIncrement, DataSize, Register, TransferAddress, CFG, CSW, DRW, TAR2,
+        },
+        ArmError,
+    },
+    MemoryInterface,
+};
+
+use super::{
+    plan::{plan_transfer, TransferSegment},
+    Cancellation, CswGuard, Endianness, MemoryAp, MemoryApError,
+};
+
+/// The size of the address block within which the TAR auto-increment is guaranteed to work.
+///
+/// Incrementing the TAR across this boundary is implementation defined (see C2.2.6), so block
+/// transfers re-program the TAR whenever they cross it.
+pub const AUTO_INCREMENT_BLOCK_SIZE: u64 = 1 << 10;
+
+/// Reads the register `R` of the memory access port at `base`.
+#[must_use = "the register value is the only effect of reading it"]
+pub fn read_register<R, M>(iface: &mut M, base: u64) -> Result<R, MemoryApError>
+where
+    R: Register,
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    let value = iface.read_word_32(base + u64::from(R::ADDRESS))?;
+    Ok(R::from_read(value)?)
+}
+
+/// Writes the register `R` of the memory access port at `base`.
+///
+/// Before a nonzero value is written to TAR2, `CFG.LA` is read, failing with
+/// [`MemoryApError::AddressTooLarge`] if the AP does not implement large physical addresses.
+pub fn write_register<R, M>(iface: &mut M, base: u64, register: R) -> Result<(), MemoryApError>
+where
+    R: Register,
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    let value = register.into();
+    if R::ADDRESS == TAR2::ADDRESS && value != 0 {
+        let cfg: CFG = read_register(iface, base)?;
+        TransferAddress::new(u64::from(value) << 32, &cfg).map_err(|error| {
+            MemoryApError::AddressTooLarge {
+                addr: error.address,
+            }
+        })?;
+    }
+    iface.write_word_32(base + u64::from(R::ADDRESS), value)?;
+    Ok(())
+}
+
+/// Writes the register `R` of the memory access port at `base`, checking that none of its
+/// read-only fields differ from `last_read`, the value most recently read from the register.
+pub fn write_register_checked<R, M>(
+    iface: &mut M,
+    base: u64,
+    register: R,
+    last_read: &R,
+) -> Result<(), MemoryApError>
+where
+    R: Register,
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    if let Some(field) = register.changed_read_only_field(last_read) {
+        return Err(MemoryApError::WroteReadOnly {
+            register: R::NAME,
+            field,
+        });
+    }
+    write_register(iface, base, register)
+}
+
+/// Reads the DRW register of the memory access port at `base`, converting the data from
+/// `endianness`.
+///
+/// The CSW is left untouched, so the access uses the currently configured size and address
+/// increment.
+pub fn read_data<M>(iface: &mut M, base: u64, endianness: Endianness) -> Result<u32, MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    let drw: DRW = read_register(iface, base)?;
+    Ok(endianness.apply(drw.data))
+}
+
+/// Writes `data` to the DRW register of the memory access port at `base`, converting it to
+/// `endianness`.
+///
+/// The CSW is left untouched, so the access uses the currently configured size and address
+/// increment.
+pub fn write_data<M>(
+    iface: &mut M,
+    base: u64,
+    data: u32,
+    endianness: Endianness,
+) -> Result<(), MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    write_register(
+        iface,
+        base,
+        DRW {
+            data: endianness.apply(data),
+        },
+    )
+}
+
+/// Configures the transfer size and address increment of the next DRW accesses.
+///
+/// Returns the previous value of the CSW.
+pub(super) fn configure_transfer<M>(
+    iface: &mut M,
+    base: u64,
+    size: DataSize,
+    increment: AddressIncrement,
+) -> Result<CSW, MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    let current: CSW = read_register(iface, base)?;
+    let mut csw = current;
+    csw.SIZE = size;
+    csw.AddrInc = increment;
+    csw.DbgSwEnable = true;
+    write_register_checked(iface, base, csw, &current)?;
+    Ok(current)
+}
+
+/// Programs the TAR and TAR2 with the address the next DRW access targets.
+///
+/// TAR2 is always written, as it may still hold the upper word of a previous address, e.g. one
+/// programmed through a [`MemoryAp`] handle or by another tool. For an address above 4 GB,
+/// `CFG.LA` is read first, failing with [`MemoryApError::AddressTooLarge`] if the AP would
+/// truncate the address. The writes are not flushed, so their errors surface with the next DRW
+/// access or flush.
+pub fn set_transfer_address<M>(iface: &mut M, base: u64, address: u64) -> Result<(), MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    program_transfer_address(iface, base, address, &Cell::new(None))
+}
+
+/// Like [`set_transfer_address`], but only writes TAR2 if the upper word of `address` differs
+/// from `tar2`, the value last written to TAR2, which is updated.
+///
+/// `None` means the value of TAR2 is unknown, so TAR2 is written.
+pub(super) fn program_transfer_address<M>(
+    iface: &mut M,
+    base: u64,
+    address: u64,
+    tar2: &Cell<Option<u32>>,
+) -> Result<(), MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    let address = match u32::try_from(address) {
+        Ok(address) => TransferAddress::from(address),
+        Err(_) => {
+            let cfg: CFG = read_register(iface, base)?;
+            TransferAddress::new(address, &cfg).map_err(|error| MemoryApError::AddressTooLarge {
+                addr: error.address,
+            })?
+        }
+    };
+    let (tar, upper) = address.split();
+    write_register(iface, base, tar)?;
+    let upper = upper.unwrap_or_default().address;
+    if tar2.get() != Some(upper) {
+        // `CFG.LA` has been checked above, so TAR2 is written without `write_register`.
+        iface.write_word_32(base + u64::from(TAR2::ADDRESS), upper)?;
+        tar2.set(Some(upper));
+    }
+    Ok(())
+}
+
+/// Reads `out.len()` 32 bit words starting at `address` into `out`.
+///
+/// The words are read with the TAR auto-increment enabled, re-programming the TAR only when the
+/// transfer crosses an [`AUTO_INCREMENT_BLOCK_SIZE`] boundary. No memory is allocated, which makes
+/// this suitable for reading the same buffer repeatedly. The CSW is restored afterwards, also if
+/// a read fails.
+pub fn read_block_into<M>(
+    iface: &mut M,
+    base: u64,
+    address: u64,
+    out: &mut [u32],
+) -> Result<(), MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    read_block_into_cancellable(iface, base, address, out, &Cancellation::none())
+}
+
+/// Like [`read_block_into`], but stops early when `cancellation` is triggered.
+///
+/// A cancelled transfer returns [`MemoryApError::Cancelled`] with the number of words already
+/// read into `out`. The words are read with the transfers
+/// [`plan_transfer`](super::plan_transfer) plans for the body of the block, with the default
+/// [`ApConfig`](super::ApConfig) of the AP at `base`. Reads which need another configuration,
+/// e.g. with [`MemoryAp::set_discard_first_read`](super::MemoryAp::set_discard_first_read), go
+/// through [`MemoryAp::read_block_into`](super::MemoryAp::read_block_into).
+pub fn read_block_into_cancellable<M>(
+    iface: &mut M,
+    base: u64,
+    address: u64,
+    out: &mut [u32],
+    cancellation: &Cancellation,
+) -> Result<(), MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    if address % 4 != 0 {
+        return Err(ArmError::alignment_error(address, 4).into());
+    }
+    if out.is_empty() {
+        return Ok(());
+    }
+
+    let mut ap = MemoryAp::new(iface, base);
+    let config = ap.config;
+    let Some(body) = plan_transfer(address, out.len() * 4, &config.capabilities).body else {
+        return Ok(());
+    };
+    let mut ap = ap.csw_guard()?;
+    ap.configure(body.size, body.increment)?;
+    let (iface, tar2) = ap.iface_and_tar2();
+    let reads = WordReads {
+        endianness: Endianness::Little,
+        cancellation,
+        discard_first_read: config.discard_first_read,
+        max_burst_words: config.max_burst_words,
+        tar2,
+    };
+    read_body(iface, base, &body, out, &reads, &mut 0)?;
+    ap.restore()
+}
+
+/// How [`read_words`] performs its DRW reads.
+pub(super) struct WordReads<'c> {
+    /// The byte order every word is converted from.
+    pub endianness: Endianness,
+    /// Checked before every chunk.
+    pub cancellation: &'c Cancellation,
+    /// Whether to discard the first DRW read after every TAR write, see
+    /// [`MemoryAp::set_discard_first_read`](super::MemoryAp::set_discard_first_read).
+    pub discard_first_read: bool,
+    /// The largest number of DRW reads issued before flushing, see
+    /// [`MemoryAp::set_max_burst_words`](super::MemoryAp::set_max_burst_words).
+    pub max_burst_words: Option<NonZeroUsize>,
+    /// The value last written to TAR2, see [`program_transfer_address`].
+    pub tar2: &'c Cell<Option<u32>>,
+}
+
+/// The number of words a burst starting at `address` transfers: up to the next
+/// [`AUTO_INCREMENT_BLOCK_SIZE`] boundary, and at most `max_burst_words`.
+fn burst_words(address: u64, max_burst_words: Option<NonZeroUsize>) -> usize {
+    let words_to_boundary =
+        ((AUTO_INCREMENT_BLOCK_SIZE - address % AUTO_INCREMENT_BLOCK_SIZE) / 4) as usize;
+    max_burst_words.map_or(words_to_boundary, |max| words_to_boundary.min(max.get()))
+}
+
+/// Reads the words of `out` from index `done` onward, advancing `done` after every word.
+///
+/// The TAR is programmed before the first word and whenever the transfer crosses an
+/// [`AUTO_INCREMENT_BLOCK_SIZE`] boundary or a burst reaches [`WordReads::max_burst_words`], in
+/// which case the reads are flushed as well. Because the TAR is always re-programmed on entry, an
+/// interrupted transfer can be resumed by calling this function again with the same `done`.
+pub(super) fn read_words<M>(
+    iface: &mut M,
+    base: u64,
+    address: u64,
+    out: &mut [u32],
+    reads: &WordReads<'_>,
+    done: &mut usize,
+) -> Result<(), MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    let drw = base + u64::from(DRW::ADDRESS);
+    while *done < out.len() {
+        if reads.cancellation.is_cancelled() {
+            return Err(MemoryApError::Cancelled { words_done: *done });
+        }
+
+        let address = address + *done as u64 * 4;
+        let end = out
+            .len()
+            .min(*done + burst_words(address, reads.max_burst_words));
+
+        program_transfer_address(iface, base, address, reads.tar2)?;
+        if reads.discard_first_read {
+            iface.read_word_32(drw)?;
+        }
+        for word in &mut out[*done..end] {
+            *word = reads.endianness.apply(iface.read_word_32(drw)?);
+            *done += 1;
+        }
+        if reads.max_burst_words.is_some() {
+            iface.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Like [`read_words`], but for the `body` of a [`plan_transfer`], which the CSW is configured
+/// for.
+///
+/// On a bus narrower than a word, every word is composed from the DRW reads of its transfers.
+/// [`WordReads::max_burst_words`] then limits the DRW reads of a burst rather than its words.
+pub(super) fn read_body<M>(
+    iface: &mut M,
+    base: u64,
+    body: &TransferSegment,
+    out: &mut [u32],
+    reads: &WordReads<'_>,
+    done: &mut usize,
+) -> Result<(), MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    if body.size == DataSize::U32 || out.is_empty() {
+        return read_words(iface, base, body.address, out, reads, done);
+    }
+
+    // A bus narrower than a word takes several DRW accesses per word, unless they are packed.
+    let per_word = body.drw_accesses() / out.len();
+    let lane_bits = 32 / per_word as u32;
+    let max_burst_words = reads
+        .max_burst_words
+        .map(|max| NonZeroUsize::new(max.get() / per_word).unwrap_or(NonZeroUsize::MIN));
+    let drw = base + u64::from(DRW::ADDRESS);
+    while *done < out.len() {
+        if reads.cancellation.is_cancelled() {
+            return Err(MemoryApError::Cancelled { words_done: *done });
+        }
+
+        let address = body.address + *done as u64 * 4;
+        let end = out.len().min(*done + burst_words(address, max_burst_words));
+
+        program_transfer_address(iface, base, address, reads.tar2)?;
+        if reads.discard_first_read {
+            iface.read_word_32(drw)?;
+        }
+        for word in &mut out[*done..end] {
+            let mut value = 0;
+            for access in 0..per_word as u32 {
+                // A sub-word transfer returns its data on the byte lanes of its address.
+                let lanes = (u32::MAX >> (32 - lane_bits)) << (access * lane_bits);
+                value |= iface.read_word_32(drw)? & lanes;
+            }
+            *word = reads.endianness.apply(value);
+            *done += 1;
+        }
+        if reads.max_burst_words.is_some() {
+            iface.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// A single 32 bit access in a [`transfer_batch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transfer {
+    /// Reads the word at `addr`.
+    Read {
+        /// The address of the word.
+        addr: u64,
+    },
+    /// Writes `data` to the word at `addr`.
+    Write {
+        /// The address of the word.
+        addr: u64,
+        /// The data to write.
+        data: u32,
+    },
+}
+
+impl Transfer {
+    fn address(&self) -> u64 {
+        match self {
+            Transfer::Read { addr } | Transfer::Write { addr, .. } => *addr,
+        }
+    }
+}
+
+/// Performs the 32 bit accesses `ops`, minimizing the number of TAR and CSW writes.
+///
+/// The accesses are performed in order of their address, so that consecutive words reuse the
+/// TAR auto-increment within an [`AUTO_INCREMENT_BLOCK_SIZE`] block. Accesses to the same address
+/// keep their relative order. The results are returned in the order of `ops`; the result of a
+/// write holds the written data. The CSW is restored after the batch.
+pub fn transfer_batch<M>(
+    iface: &mut M,
+    base: u64,
+    ops: &[Transfer],
+) -> Vec<Result<u32, MemoryApError>>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    let mut order: Vec<usize> = (0..ops.len()).collect();
+    order.sort_by_key(|&index| ops[index].address());
+
+    let mut results: Vec<Option<Result<u32, MemoryApError>>> = ops.iter().map(|_| None).collect();
+    let mut ap = MemoryAp::new(iface, base);
+    let mut remaining = order.as_slice();
+    // The CSW is saved before the first aligned access. If it cannot be read, that access fails
+    // and the next one tries again.
+    while let [index, rest @ ..] = remaining {
+        let address = ops[*index].address();
+        if address % 4 != 0 {
+            results[*index] = Some(Err(ArmError::alignment_error(address, 4).into()));
+            remaining = rest;
+            continue;
+        }
+        match ap.csw_guard() {
+            Ok(mut guard) => {
+                batch_accesses(&mut guard, ops, remaining, &mut results);
+                break;
+            }
+            Err(error) => {
+                results[*index] = Some(Err(error));
+                remaining = rest;
+            }
+        }
+    }
+
+    results.into_iter().flatten().collect()
+}
+
+/// Performs the accesses of a [`transfer_batch`] at the indices `order` within `ops`.
+fn batch_accesses<M>(
+    ap: &mut CswGuard<'_, '_, M>,
+    ops: &[Transfer],
+    order: &[usize],
+    results: &mut [Option<Result<u32, MemoryApError>>],
+) where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    let mut configured = false;
+    // The address the TAR currently holds, if known.
+    let mut tar = None;
+
+    for &index in order {
+        let result = batch_access(ap, ops[index], &mut configured, &mut tar);
+        if result.is_err() {
+            tar = None;
+        }
+        results[index] = Some(result);
+    }
+}
+
+/// Performs a single access of a [`transfer_batch`], writing the TAR only if it does not already
+/// hold the address of the access.
+fn batch_access<M>(
+    ap: &mut CswGuard<'_, '_, M>,
+    op: Transfer,
+    configured: &mut bool,
+    tar: &mut Option<u64>,
+) -> Result<u32, MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    let address = op.address();
+    if address % 4 != 0 {
+        return Err(ArmError::alignment_error(address, 4).into());
+    }
+    if !*configured {
+        ap.configure(DataSize::U32, AddressIncrement::Single)?;
+        *configured = true;
+    }
+    let base = ap.config.base;
+    if *tar != Some(address) {
+        *tar = None;
+        let (iface, tar2) = ap.iface_and_tar2();
+        program_transfer_address(iface, base, address, tar2)?;
+    }
+
+    let drw = base + u64::from(DRW::ADDRESS);
+    let value = match op {
+        Transfer::Read { .. } => ap.iface.read_word_32(drw)?,
+        Transfer::Write { data, .. } => {
+            ap.iface.write_word_32(drw, data)?;
+            data
+        }
+    };
+
+    let next = address + 4;
+    *tar = (next % AUTO_INCREMENT_BLOCK_SIZE != 0).then_some(next);
+    Ok(value)
+}
+
+/// Reads `len` 32 bit words starting at `address`.
+///
+/// See [`read_block_into`] for a variant that reads into a caller-supplied buffer.
+pub fn read_block<M>(
+    iface: &mut M,
+    base: u64,
+    address: u64,
+    len: usize,
+) -> Result<Vec<u32>, MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    let mut data = vec![0; len];
+    read_block_into(iface, base, address, &mut data)?;
+    Ok(data)
+}
+
+/// Writes the 32 bit words of `data` starting at `address`.
+///
+/// Like [`read_block_into`], the words are written with the TAR auto-increment enabled,
+/// re-programming the TAR only when the transfer crosses an [`AUTO_INCREMENT_BLOCK_SIZE`]
+/// boundary. The CSW is restored afterwards, also if a write fails.
+pub fn write_block<M>(
+    iface: &mut M,
+    base: u64,
+    address: u64,
+    data: &[u32],
+) -> Result<(), MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    write_block_with_progress(iface, base, address, data, None)
+}
+
+/// Like [`write_block`], but calls `progress` with the number of words written so far after
+/// every chunk, e.g. to show the progress of a long download.
+///
+/// A chunk ends at the next [`AUTO_INCREMENT_BLOCK_SIZE`] boundary. The words are written with
+/// the transfers [`plan_transfer`](super::plan_transfer) plans for the body of the block: word
+/// transfers, or the widest size of a bus narrower than a word.
+pub fn write_block_with_progress<M>(
+    iface: &mut M,
+    base: u64,
+    address: u64,
+    data: &[u32],
+    progress: Option<&mut dyn FnMut(usize)>,
+) -> Result<(), MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    write_bursts(&mut MemoryAp::new(iface, base), address, data, progress)
+}
+
+/// Like [`write_block_with_progress`] through the handle `ap`, with the transfers planned for its
+/// capabilities, and flushing after at most [`MemoryAp::set_max_burst_words`] DRW writes. A
+/// chunk ends at the end of every burst.
+pub(super) fn write_bursts<M>(
+    ap: &mut MemoryAp<'_, M>,
+    address: u64,
+    data: &[u32],
+    mut progress: Option<&mut dyn FnMut(usize)>,
+) -> Result<(), MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    if address % 4 != 0 {
+        return Err(ArmError::alignment_error(address, 4).into());
+    }
+    if data.is_empty() {
+        return Ok(());
+    }
+
+    let caps = ap.config.capabilities;
+    let Some(body) = plan_transfer(address, data.len() * 4, &caps).body else {
+        return Ok(());
+    };
+    // A bus narrower than a word takes several DRW accesses per word, unless they are packed.
+    let per_word = body.drw_accesses() / data.len();
+    let max_burst_words = ap
+        .config
+        .max_burst_words
+        .map(|max| NonZeroUsize::new(max.get() / per_word).unwrap_or(NonZeroUsize::MIN));
+
+    let base = ap.config.base;
+    let mut ap = ap.csw_guard()?;
+    ap.configure(body.size, body.increment)?;
+    let (iface, tar2) = ap.iface_and_tar2();
+    let drw = base + u64::from(DRW::ADDRESS);
+    let mut done = 0;
+    while done < data.len() {
+        let address = address + done as u64 * 4;
+        let end = data.len().min(done + burst_words(address, max_burst_words));
+
+        program_transfer_address(iface, base, address, tar2)?;
+        for word in &data[done..end] {
+            // A sub-word transfer takes its data from the byte lanes of its address, which are
+            // the lanes the bytes of the word are on.
+            for _ in 0..per_word {
+                iface.write_word_32(drw, *word)?;
+            }
+        }
+        if max_burst_words.is_some() {
+            iface.flush()?;
+        }
+        done = end;
+        if let Some(progress) = progress.as_mut() {
+            progress(done);
+        }
+    }
+
+    ap.restore()
+}
+
+/// Fills the `words` 32 bit words starting at `address` with `pattern`, e.g. to clear RAM.
+///
+/// The words are written with the TAR auto-increment enabled, re-programming the TAR only when
+/// the fill crosses an [`AUTO_INCREMENT_BLOCK_SIZE`] boundary. Unlike [`write_block`], no buffer
+/// of the region is built. The CSW is restored afterwards, also if a write fails.
+pub fn fill<M>(
+    iface: &mut M,
+    base: u64,
+    address: u64,
+    pattern: u32,
+    words: usize,
+) -> Result<(), MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    fill_bursts(&mut MemoryAp::new(iface, base), address, pattern, words)
+}
+
+/// Like [`fill`] through the handle `ap`, flushing after at most
+/// [`MemoryAp::set_max_burst_words`] DRW writes.
+pub(super) fn fill_bursts<M>(
+    ap: &mut MemoryAp<'_, M>,
+    address: u64,
+    pattern: u32,
+    words: usize,
+) -> Result<(), MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    if address % 4 != 0 {
+        return Err(ArmError::alignment_error(address, 4).into());
+    }
+    if words == 0 {
+        return Ok(());
+    }
+
+    let (base, max_burst_words) = (ap.config.base, ap.config.max_burst_words);
+    let mut ap = ap.csw_guard()?;
+    ap.configure(DataSize::U32, AddressIncrement::Single)?;
+    let (iface, tar2) = ap.iface_and_tar2();
+    let drw = base + u64::from(DRW::ADDRESS);
+    let mut done = 0;
+    while done < words {
+        let address = address + done as u64 * 4;
+        let end = words.min(done + burst_words(address, max_burst_words));
+
+        program_transfer_address(iface, base, address, tar2)?;
+        for _ in done..end {
+            iface.write_word_32(drw, pattern)?;
+        }
+        if max_burst_words.is_some() {
+            iface.flush()?;
+        }
+        done = end;
+    }
+
+    ap.restore()
+}
+
+/// Writes the 32 bit words of `data` to the single address `address`, e.g. a FIFO or the data
+/// register of a UART.
+///
+/// The TAR is programmed once and the auto-increment is disabled, so every word is written to
+/// `address` with a single DRW access. With a burst limit (see
+/// [`MemoryAp::set_max_burst_words`]), the writes are flushed after every burst. The CSW is
+/// restored afterwards, also if a write fails.
+pub fn write_fifo<M>(
+    iface: &mut M,
+    base: u64,
+    address: u64,
+    data: &[u32],
+) -> Result<(), MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    write_fifo_words(&mut MemoryAp::new(iface, base), address, data)
+}
+
+/// Like [`write_fifo`] through the handle `ap`.
+pub(super) fn write_fifo_words<M>(
+    ap: &mut MemoryAp<'_, M>,
+    address: u64,
+    data: &[u32],
+) -> Result<(), MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    if address % 4 != 0 {
+        return Err(ArmError::alignment_error(address, 4).into());
+    }
+    if data.is_empty() {
+        return Ok(());
+    }
+
+    let base = ap.config.base;
+    let mut ap = ap.csw_guard()?;
+    ap.configure(DataSize::U32, AddressIncrement::Off)?;
+    let max_burst_words = ap.config.max_burst_words;
+    let (iface, tar2) = ap.iface_and_tar2();
+    program_transfer_address(iface, base, address, tar2)?;
+    let drw = base + u64::from(DRW::ADDRESS);
+    let chunk = max_burst_words.map_or(data.len(), NonZeroUsize::get);
+    for burst in data.chunks(chunk) {
+        for word in burst {
+            iface.write_word_32(drw, *word)?;
+        }
+        if max_burst_words.is_some() {
+            iface.flush()?;
+        }
+    }
+
+    ap.restore()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use crate::architecture::arm::ap_v2::registers::{DataSize, Register, CFG, CSW, TAR2};
+
+    use super::{
+        fill, read_block, read_block_into, read_block_into_cancellable, read_data, read_register,
+        transfer_batch, write_block, write_block_with_progress, write_data, write_fifo,
+        write_register, write_register_checked, Transfer, AUTO_INCREMENT_BLOCK_SIZE,
+    };
+    use crate::architecture::arm::ap_v2::memory_ap::{
+        mock::MockMemoryAp, Cancellation, Endianness, MemoryApError,
+    };
+
+    const BASE: u64 = 0x2000;
+
+    #[test]
+    fn read_block_matches_memory() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let data = read_block(&mut mock, BASE, 0x10, 8).unwrap();
+
+        assert_eq!(data, mock.words(0x10, 8));
+    }
+
+    #[test]
+    fn addresses_above_4gb_need_large_addresses() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        assert!(matches!(
+            read_block(&mut mock, BASE, 0x1_0000_0000, 1),
+            Err(MemoryApError::AddressTooLarge {
+                addr: 0x1_0000_0000
+            })
+        ));
+        assert_eq!(mock.tar_writes, 0);
+    }
+
+    #[test]
+    fn nonzero_tar2_writes_need_large_addresses() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        assert!(matches!(
+            write_register(&mut mock, BASE, TAR2 { address: 1 }),
+            Err(MemoryApError::AddressTooLarge {
+                addr: 0x1_0000_0000
+            })
+        ));
+        write_register(&mut mock, BASE, TAR2 { address: 0 }).unwrap();
+
+        // With CFG.LA, the AP implements TAR2.
+        mock.set_register(CFG::ADDRESS, 0b10);
+        write_register(&mut mock, BASE, TAR2 { address: 1 }).unwrap();
+        let tar2: TAR2 = read_register(&mut mock, BASE).unwrap();
+        assert_eq!(tar2.address, 1);
+    }
+
+    #[test]
+    fn stale_tar2_is_cleared() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        // CFG.LA, and TAR2 left pointing above 4 GB by an earlier transfer.
+        mock.set_register(CFG::ADDRESS, 0b10);
+        mock.set_register(TAR2::ADDRESS, 1);
+
+        let data = read_block(&mut mock, BASE, 0x10, 2).unwrap();
+        assert_eq!(data, mock.words(0x10, 2));
+        let tar2: TAR2 = read_register(&mut mock, BASE).unwrap();
+        assert_eq!(tar2.address, 0);
+    }
+
+    #[test]
+    fn read_block_into_reprograms_tar_at_wrap_boundary() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let start = AUTO_INCREMENT_BLOCK_SIZE - 8;
+        let mut out = [0u32; 6];
+        read_block_into(&mut mock, BASE, start, &mut out).unwrap();
+
+        assert_eq!(out.as_slice(), mock.words(start, 6).as_slice());
+        assert_eq!(mock.tar_writes, 2);
+    }
+
+    #[test]
+    fn read_block_into_rejects_unaligned_address() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut out = [0u32; 1];
+
+        assert!(read_block_into(&mut mock, BASE, 0x2, &mut out).is_err());
+    }
+
+    #[test]
+    fn checked_write_rejects_changed_read_only_field() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let last_read: CSW = read_register(&mut mock, BASE).unwrap();
+
+        let mut csw = last_read;
+        csw.TrInProg = true;
+        assert!(matches!(
+            write_register_checked(&mut mock, BASE, csw, &last_read),
+            Err(MemoryApError::WroteReadOnly {
+                register: "CSW",
+                field: "TrInProg"
+            })
+        ));
+
+        let mut csw = last_read;
+        csw.SIZE = DataSize::U8;
+        write_register_checked(&mut mock, BASE, csw, &last_read).unwrap();
+    }
+
+    #[test]
+    fn data_is_swapped_only_when_requested() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+
+        write_data(&mut mock, BASE, 0x1122_3344, Endianness::Little).unwrap();
+        assert_eq!(mock.bytes(0, 4), [0x44, 0x33, 0x22, 0x11]);
+        write_data(&mut mock, BASE, 0x1122_3344, Endianness::Big).unwrap();
+        assert_eq!(mock.bytes(4, 4), [0x11, 0x22, 0x33, 0x44]);
+
+        let native = mock.words(8, 2);
+        assert_eq!(
+            read_data(&mut mock, BASE, Endianness::Little).unwrap(),
+            native[0]
+        );
+        assert_eq!(
+            read_data(&mut mock, BASE, Endianness::Big).unwrap(),
+            native[1].swap_bytes()
+        );
+    }
+
+    #[test]
+    fn cancelled_block_read_restores_csw() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let csw = CSW::builder().size(DataSize::U8).build();
+        write_register(&mut mock, BASE, csw).unwrap();
+
+        let mut out = [0u32; 512];
+        let cancellation = Cancellation::none().with_deadline(Instant::now());
+        assert!(matches!(
+            read_block_into_cancellable(&mut mock, BASE, 0, &mut out, &cancellation),
+            Err(MemoryApError::Cancelled { words_done: 0 })
+        ));
+
+        let restored: CSW = read_register(&mut mock, BASE).unwrap();
+        assert_eq!(restored.SIZE, DataSize::U8);
+    }
+
+    #[test]
+    fn uncancelled_block_read_spans_chunks() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let mut out = [0u32; 512];
+
+        read_block_into_cancellable(&mut mock, BASE, 0, &mut out, &Cancellation::none()).unwrap();
+        assert_eq!(out.as_slice(), mock.words(0, 512).as_slice());
+    }
+
+    #[test]
+    fn batch_results_keep_request_order() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let expected = mock.words(0x100, 3);
+        let far = mock.words(0x400, 1)[0];
+
+        let results = transfer_batch(
+            &mut mock,
+            BASE,
+            &[
+                Transfer::Read { addr: 0x108 },
+                Transfer::Read { addr: 0x400 },
+                Transfer::Write {
+                    addr: 0x104,
+                    data: 0xDEAD_BEEF,
+                },
+                Transfer::Read { addr: 0x100 },
+                Transfer::Read { addr: 0x3 },
+            ],
+        );
+
+        assert_eq!(results[0].as_ref().unwrap(), &expected[2]);
+        assert_eq!(results[1].as_ref().unwrap(), &far);
+        assert_eq!(results[2].as_ref().unwrap(), &0xDEAD_BEEF);
+        assert_eq!(results[3].as_ref().unwrap(), &expected[0]);
+        assert!(results[4].is_err());
+        // One TAR write for 0x100..=0x108, and one for 0x400.
+        assert_eq!(mock.tar_writes, 2);
+        assert_eq!(mock.words(0x104, 1), [0xDEAD_BEEF]);
+    }
+
+    #[test]
+    fn write_block_spans_wrap_boundary() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let data: Vec<u32> = (0..8).collect();
+        let address = AUTO_INCREMENT_BLOCK_SIZE - 16;
+
+        write_block(&mut mock, BASE, address, &data).unwrap();
+        assert_eq!(mock.words(address, 8), data);
+        assert_eq!(mock.tar_writes, 2);
+    }
+
+    #[test]
+    fn write_fifo_programs_the_tar_once() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let before: CSW = read_register(&mut mock, BASE).unwrap();
+        let mut expected = mock.words(0x40, 3);
+        expected[0] = 0x3333_3333;
+
+        write_fifo(
+            &mut mock,
+            BASE,
+            0x40,
+            &[0x1111_1111, 0x2222_2222, 0x3333_3333],
+        )
+        .unwrap();
+        // Every word went to the same address, leaving the following words alone.
+        assert_eq!(mock.words(0x40, 3), expected);
+        assert_eq!(mock.tar_writes, 1);
+        let after: CSW = read_register(&mut mock, BASE).unwrap();
+        assert_eq!(after, before);
+    }
+
+    #[test]
+    fn fill_spans_wrap_boundary() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let before: CSW = read_register(&mut mock, BASE).unwrap();
+        let address = AUTO_INCREMENT_BLOCK_SIZE - 8;
+        let following = mock.words(address + 40, 1);
+
+        fill(&mut mock, BASE, address, 0xDEAD_BEEF, 10).unwrap();
+        assert_eq!(mock.words(address, 10), [0xDEAD_BEEF; 10]);
+        assert_eq!(mock.words(address + 40, 1), following);
+        assert_eq!(mock.tar_writes, 2);
+        let after: CSW = read_register(&mut mock, BASE).unwrap();
+        assert_eq!(after, before);
+    }
+
+    #[test]
+    fn write_fifo_restores_csw_after_a_fault() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let before: CSW = read_register(&mut mock, BASE).unwrap();
+        mock.faulting_addresses.insert(0x40);
+
+        assert!(write_fifo(&mut mock, BASE, 0x40, &[1, 2]).is_err());
+        let after: CSW = read_register(&mut mock, BASE).unwrap();
+        assert_eq!(after, before);
+    }
+
+    #[test]
+    fn block_transfers_restore_csw_after_a_fault() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let csw = CSW::builder().size(DataSize::U8).build();
+        write_register(&mut mock, BASE, csw).unwrap();
+        mock.faulting_addresses.insert(0x48);
+
+        let mut out = [0u32; 4];
+        assert!(read_block_into(&mut mock, BASE, 0x40, &mut out).is_err());
+        assert_eq!(read_register::<CSW, _>(&mut mock, BASE).unwrap(), csw);
+        assert!(write_block(&mut mock, BASE, 0x40, &[1, 2, 3, 4]).is_err());
+        assert_eq!(read_register::<CSW, _>(&mut mock, BASE).unwrap(), csw);
+        let results = transfer_batch(&mut mock, BASE, &[Transfer::Read { addr: 0x48 }]);
+        assert!(results[0].is_err());
+        assert_eq!(read_register::<CSW, _>(&mut mock, BASE).unwrap(), csw);
+    }
+
+    #[test]
+    fn write_block_reports_progress_per_chunk() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let before: CSW = read_register(&mut mock, BASE).unwrap();
+        let data: Vec<u32> = (0..260).collect();
+        let mut reported = Vec::new();
+
+        let start = AUTO_INCREMENT_BLOCK_SIZE - 16;
+        write_block_with_progress(
+            &mut mock,
+            BASE,
+            start,
+            &data,
+            Some(&mut |words| reported.push(words)),
+        )
+        .unwrap();
+        assert_eq!(reported, [4, 260]);
+        assert_eq!(mock.words(start, 260), data);
+        let after: CSW = read_register(&mut mock, BASE).unwrap();
+        assert_eq!(after, before);
+    }
+}
@@ -0,0 +1,229 @@
+//! Polling a single memory location for changes.
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    architecture::arm::{
+        ap_v2::registers::{AddressIncrement, DataSize, Register, DRW},
+        ArmError,
+    },
+    MemoryInterface,
+};
+
+use super::{transfer, Cancellation, MemoryAp, MemoryApError};
+
+/// Watches the word at `address` for changes until `cancellation` is triggered.
+///
+/// The address increment is turned off, so the TAR is programmed once and every DRW read
+/// returns the word at `address`. The first read establishes the initial value; `on_change` is
+/// called with the new value whenever a read differs from the previous one. The word is read
+/// every `poll`.
+///
+/// Once cancelled, the last value read is returned. The CSW is restored afterwards, also if a
+/// read fails.
+pub fn watch<M>(
+    iface: &mut M,
+    base: u64,
+    address: u64,
+    poll: Duration,
+    cancellation: &Cancellation,
+    mut on_change: impl FnMut(u32),
+) -> Result<u32, MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    if address % 4 != 0 {
+        return Err(ArmError::alignment_error(address, 4).into());
+    }
+
+    let mut ap = MemoryAp::new(iface, base);
+    let mut ap = ap.csw_guard()?;
+    ap.configure(DataSize::U32, AddressIncrement::Off)?;
+    let (iface, tar2) = ap.iface_and_tar2();
+    transfer::program_transfer_address(iface, base, address, tar2)?;
+
+    let drw = base + u64::from(DRW::ADDRESS);
+    let mut last = iface.read_word_32(drw)?;
+    while !cancellation.is_cancelled() {
+        std::thread::sleep(poll);
+        let value = iface.read_word_32(drw)?;
+        if value != last {
+            on_change(value);
+            last = value;
+        }
+    }
+
+    ap.restore()?;
+    Ok(last)
+}
+
+/// Reads the word at `address` every `poll` until `condition` holds for it, and returns it.
+///
+/// This is the usual way to wait for a status bit: the address increment is turned off, so the
+/// TAR is programmed once and every DRW read returns the word at `address`. The CSW is restored
+/// afterwards, also if a read fails. Fails with [`ArmError::Timeout`] if the condition does not
+/// hold within `timeout`.
+pub fn poll_until<M>(
+    iface: &mut M,
+    base: u64,
+    address: u64,
+    condition: impl Fn(u32) -> bool,
+    poll: Duration,
+    timeout: Duration,
+) -> Result<u32, MemoryApError>
+where
+    M: MemoryInterface<ArmError> + ?Sized,
+{
+    if address % 4 != 0 {
+        return Err(ArmError::alignment_error(address, 4).into());
+    }
+
+    let mut ap = MemoryAp::new(iface, base);
+    let mut ap = ap.csw_guard()?;
+    ap.configure(DataSize::U32, AddressIncrement::Off)?;
+    let (iface, tar2) = ap.iface_and_tar2();
+    transfer::program_transfer_address(iface, base, address, tar2)?;
+
+    let drw = base + u64::from(DRW::ADDRESS);
+    let start = Instant::now();
+    let value = loop {
+        let value = iface.read_word_32(drw)?;
+        if condition(value) {
+            break value;
+        }
+        if start.elapsed() >= timeout {
+            return Err(ArmError::Timeout.into());
+        }
+        std::thread::sleep(poll);
+    };
+
+    ap.restore()?;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    };
+
+    use super::{poll_until, watch};
+    use crate::architecture::arm::{
+        ap_v2::{
+            memory_ap::{mock::MockMemoryAp, read_register, Cancellation, MemoryApError},
+            registers::{AddressIncrement, CSW},
+        },
+        ArmError,
+    };
+
+    const BASE: u64 = 0x2000;
+
+    #[test]
+    fn watch_programs_tar_once_and_restores_csw() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        let expected = mock.words(0x20, 1)[0];
+        let mut changes = 0;
+
+        let cancellation = Cancellation::none().with_deadline(Instant::now());
+        let last = watch(&mut mock, BASE, 0x20, Duration::ZERO, &cancellation, |_| {
+            changes += 1
+        })
+        .unwrap();
+
+        assert_eq!(last, expected);
+        assert_eq!(changes, 0);
+        assert_eq!(mock.tar_writes, 1);
+        let csw: CSW = read_register(&mut mock, BASE).unwrap();
+        assert_eq!(csw.AddrInc, AddressIncrement::Single);
+    }
+
+    #[test]
+    fn watch_reports_changes() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        // The pipelined DRW returns stale data first, so the value changes on the second read.
+        mock.pipelined_drw = true;
+        let expected = mock.words(0x20, 1)[0];
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let cancellation = Cancellation::none().with_flag(stop.clone());
+        let mut changes = vec![];
+        watch(
+            &mut mock,
+            BASE,
+            0x20,
+            Duration::ZERO,
+            &cancellation,
+            |value| {
+                changes.push(value);
+                stop.store(true, Ordering::Relaxed);
+            },
+        )
+        .unwrap();
+
+        assert_eq!(changes, [expected]);
+    }
+
+    #[test]
+    fn watch_restores_csw_after_a_fault() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.faulting_addresses.insert(0x20);
+
+        let result = watch(
+            &mut mock,
+            BASE,
+            0x20,
+            Duration::ZERO,
+            &Cancellation::none(),
+            |_| {},
+        );
+        assert!(result.is_err());
+        let csw: CSW = read_register(&mut mock, BASE).unwrap();
+        assert_eq!(csw.AddrInc, AddressIncrement::Single);
+    }
+
+    #[test]
+    fn poll_until_returns_the_first_matching_value() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.zero_drw_reads = 3;
+        let expected = mock.words(0x20, 1)[0];
+
+        let value = poll_until(
+            &mut mock,
+            BASE,
+            0x20,
+            |value| value != 0,
+            Duration::ZERO,
+            Duration::from_secs(1),
+        )
+        .unwrap();
+
+        assert_eq!(value, expected);
+        assert_eq!(mock.zero_drw_reads, 0);
+        assert_eq!(mock.tar_writes, 1);
+        let csw: CSW = read_register(&mut mock, BASE).unwrap();
+        assert_eq!(csw.AddrInc, AddressIncrement::Single);
+    }
+
+    #[test]
+    fn poll_until_times_out() {
+        let mut mock = MockMemoryAp::with_pattern(BASE);
+        mock.zero_drw_reads = usize::MAX;
+
+        let result = poll_until(
+            &mut mock,
+            BASE,
+            0x20,
+            |value| value != 0,
+            Duration::from_millis(1),
+            Duration::from_millis(5),
+        );
+
+        assert!(matches!(result, Err(MemoryApError::Arm(ArmError::Timeout))));
+        let csw: CSW = read_register(&mut mock, BASE).unwrap();
+        assert_eq!(csw.AddrInc, AddressIncrement::Single);
+    }
+}
@@ -11,6 +11,8 @@ use crate::{
     MemoryInterface,
 };
 
+use memory_ap::{ApInfo, ApList};
+
 use super::{
     communication_interface::{Initialized, SwdSequence},
     dp::DpAddress,
@@ -19,7 +21,15 @@ use super::{
     FullyQualifiedApAddress,
 };
 
+pub mod capabilities;
+pub mod compat;
+pub mod csw_word;
+pub mod memory_ap;
+pub mod prot;
 pub(crate) mod registers;
+pub mod topology;
+
+pub use registers::{diff_fields, AddressIncrement, DataSize, FieldDiff, Register, CSW, TAR, TAR2};
 
 mod root_memory_interface;
 use root_memory_interface::RootMemoryInterface;
@@ -76,7 +86,7 @@ pub fn enumerate_access_ports(
     probe: &mut ArmCommunicationInterface<Initialized>,
     dp: DpAddress,
 ) -> Result<BTreeSet<FullyQualifiedApAddress>, ArmError> {
-    enumerate_components_internal(probe, dp).map(|res| {
+    enumerate_components_internal(probe, dp, None).map(|res| {
         res.into_iter()
             .filter_map(|(k, c)| {
                 c.id()
@@ -89,21 +99,35 @@ pub fn enumerate_access_ports(
     })
 }
 
+/// Deeply scans the debug port like [`enumerate_access_ports`], and returns the memory access
+/// ports discovered together with their IDR, sorted by their address.
+pub fn enumerate_access_port_info(
+    probe: &mut ArmCommunicationInterface<Initialized>,
+    dp: DpAddress,
+) -> Result<ApList, ArmError> {
+    let mut aps = Vec::new();
+    enumerate_components_internal(probe, dp, Some(&mut aps))?;
+    Ok(aps.into())
+}
+
 /// Enumerates components attached to this debug port
 pub fn enumerate_components(
     probe: &mut ArmCommunicationInterface<Initialized>,
     dp: DpAddress,
 ) -> Result<BTreeSet<FullyQualifiedApAddress>, ArmError> {
-    enumerate_components_internal(probe, dp).map(|res| {
+    enumerate_components_internal(probe, dp, None).map(|res| {
         res.into_keys()
             .map(|addr| FullyQualifiedApAddress::v2_with_dp(dp, addr))
             .collect()
     })
 }
 
+/// Scans the debug port for components, also reading the IDR of every memory access port into
+/// `aps` if it is given.
 fn enumerate_components_internal(
     probe: &mut ArmCommunicationInterface<Initialized>,
     dp: DpAddress,
+    aps: Option<&mut Vec<ApInfo>>,
 ) -> Result<BTreeMap<ApV2Address, Component>, ArmError> {
     let mut root_ap = RootMemoryInterface::new(probe, dp)?;
     let base_addr = root_ap.base_address()?;
@@ -111,7 +135,13 @@ fn enumerate_components_internal(
     let mut result = BTreeMap::new();
 
     let component = Component::try_parse(&mut root_ap as &mut dyn ArmMemoryInterface, base_addr)?;
-    process_component(&mut root_ap, &ApV2Address::root(), &component, &mut result)?;
+    process_component(
+        &mut root_ap,
+        &ApV2Address::root(),
+        &component,
+        &mut result,
+        aps,
+    )?;
 
     Ok(result)
 }
@@ -121,6 +151,7 @@ fn process_component<'iface, M: ArmMemoryInterface + 'iface>(
     address: &ApV2Address,
     component: &Component,
     result: &mut BTreeMap<ApV2Address, Component>,
+    mut aps: Option<&mut Vec<ApInfo>>,
 ) -> Result<(), ArmError> {
     match component {
         Component::CoresightComponent(c)
@@ -131,26 +162,46 @@ fn process_component<'iface, M: ArmMemoryInterface + 'iface>(
                 RomTable::try_parse(iface as &mut dyn ArmMemoryInterface, c.component_address())?;
             // process rom table
             for e in rom_table.entries() {
-                process_component(iface, address, e.component(), result)?;
+                process_component(iface, address, e.component(), result, aps.as_deref_mut())?;
             }
         }
         Component::CoresightComponent(c) if c.peripheral_id().is_of_type(PeripheralType::MemAp) => {
             let base_address = address.clone().append(c.component_address());
+            if let Some(aps) = aps.as_deref_mut() {
+                aps.push(ApInfo::read(iface, address, c.component_address())?);
+            }
 
             let mut subiface = MemoryAccessPortInterface::new_with_ref(
                 iface as &mut dyn ArmMemoryInterface,
                 c.component_address(),
             )?;
-            let base_addr = subiface.base_address()?;
-
-            let memap_base_component =
-                Component::try_parse(&mut subiface as &mut dyn ArmMemoryInterface, base_addr)?;
-            process_component(&mut subiface, &base_address, &memap_base_component, result)?;
+            // An AP without a debug entry has nothing to enumerate behind it.
+            if let Some(rom_table) = subiface.rom_table_address()? {
+                let memap_base_component = Component::try_parse(
+                    &mut subiface as &mut dyn ArmMemoryInterface,
+                    rom_table.absolute(),
+                )?;
+                process_component(
+                    &mut subiface,
+                    &base_address,
+                    &memap_base_component,
+                    result,
+                    aps,
+                )?;
+            } else {
+                tracing::debug!("{base_address:x?} has no debug entry, skipping it");
+            }
             result.insert(base_address, component.clone());
         }
         Component::Class1RomTable(_, rom_table) => {
             for e in rom_table.entries() {
-                process_component(&mut *iface, address, e.component(), result)?;
+                process_component(
+                    &mut *iface,
+                    address,
+                    e.component(),
+                    result,
+                    aps.as_deref_mut(),
+                )?;
             }
         }
         _ => {
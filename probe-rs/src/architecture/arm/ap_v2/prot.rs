@@ -0,0 +1,129 @@
+//! Bus specific encodings of the `CSW.Prot` field.
+
+/// A bus specific interpretation of the 7 bit `CSW.Prot` field.
+pub trait ProtDecode: Sized {
+    /// Decodes the raw `CSW.Prot` value `prot`.
+    fn from_prot(prot: u8) -> Self;
+
+    /// Encodes the value for storing in `CSW.Prot`.
+    fn to_prot(&self) -> u8;
+}
+
+/// The `CSW.Prot` field of an AHB memory access port, driving the `HPROT` and `HNONSEC` signals.
+///
+/// Start from one of the presets, and adjust with the toggles:
+///
+/// ```
+/// use probe_rs::architecture::arm::ap_v2::prot::{AhbProt, ProtDecode};
+///
+/// let prot = AhbProt::privileged_data().cacheable(true).to_prot();
+/// assert_eq!(prot, 0x4B);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AhbProt {
+    /// `HPROT[0]`: data access, as opposed to an opcode fetch.
+    pub data: bool,
+    /// `HPROT[1]`: privileged access.
+    pub privileged: bool,
+    /// `HPROT[2]`: bufferable access.
+    pub bufferable: bool,
+    /// `HPROT[3]`: cacheable access.
+    pub cacheable: bool,
+    /// `HPROT[4]`: allocate on a cache miss.
+    pub allocate: bool,
+    /// Use the requester ID of the debugger instead of the default requester.
+    pub master_type: bool,
+    /// Secure access, driven as the inverse of `HNONSEC`.
+    pub secure: bool,
+}
+
+impl AhbProt {
+    /// A privileged, non-secure data access.
+    pub const fn privileged_data() -> Self {
+        Self {
+            data: true,
+            privileged: true,
+            bufferable: false,
+            cacheable: false,
+            allocate: false,
+            master_type: false,
+            secure: false,
+        }
+    }
+
+    /// An unprivileged, non-secure data access.
+    pub const fn unprivileged() -> Self {
+        Self {
+            privileged: false,
+            ..Self::privileged_data()
+        }
+    }
+
+    /// Sets whether the access is secure.
+    pub const fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    /// Sets whether the access is cacheable.
+    pub const fn cacheable(mut self, cacheable: bool) -> Self {
+        self.cacheable = cacheable;
+        self
+    }
+
+    /// Sets whether the access is bufferable.
+    pub const fn bufferable(mut self, bufferable: bool) -> Self {
+        self.bufferable = bufferable;
+        self
+    }
+}
+
+impl ProtDecode for AhbProt {
+    fn from_prot(prot: u8) -> Self {
+        Self {
+            data: prot & 0x01 != 0,
+            privileged: (prot >> 1) & 0x01 != 0,
+            bufferable: (prot >> 2) & 0x01 != 0,
+            cacheable: (prot >> 3) & 0x01 != 0,
+            allocate: (prot >> 4) & 0x01 != 0,
+            master_type: (prot >> 5) & 0x01 != 0,
+            secure: (prot >> 6) & 0x01 == 0,
+        }
+    }
+
+    fn to_prot(&self) -> u8 {
+        u8::from(self.data)
+            | (u8::from(self.privileged) << 1)
+            | (u8::from(self.bufferable) << 2)
+            | (u8::from(self.cacheable) << 3)
+            | (u8::from(self.allocate) << 4)
+            | (u8::from(self.master_type) << 5)
+            | (u8::from(!self.secure) << 6)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AhbProt, ProtDecode};
+
+    #[test]
+    fn presets_encode_hprot() {
+        assert_eq!(AhbProt::privileged_data().to_prot(), 0x43);
+        assert_eq!(AhbProt::unprivileged().to_prot(), 0x41);
+        assert_eq!(AhbProt::privileged_data().secure(true).to_prot(), 0x03);
+        assert_eq!(
+            AhbProt::unprivileged()
+                .cacheable(true)
+                .bufferable(true)
+                .to_prot(),
+            0x4D
+        );
+    }
+
+    #[test]
+    fn prot_round_trips() {
+        for prot in 0..0x80 {
+            assert_eq!(AhbProt::from_prot(prot).to_prot(), prot);
+        }
+    }
+}
@@ -1,4 +1,8 @@
+//! Register types and the register definition macro for APv2 memory access ports.
+
+use super::capabilities::MemApCapabilities;
 use crate::architecture::arm::RegisterParseError;
+pub use crate::architecture::arm::{FieldAccess, FieldInfo};
 
 /// A trait to be implemented on Access Port register types for typed device access.
 pub trait Register:
@@ -8,6 +12,286 @@ pub trait Register:
     const ADDRESS: u16;
     /// The name of the register as string.
     const NAME: &'static str;
+    /// The layout of the fields of the register.
+    const FIELDS: &'static [FieldInfo];
+    /// A raw value which parses, whose fields replace the unparseable ones in
+    /// [`Register::from_raw_lossy`]. This is the reset value if the register has one.
+    const LOSSY_FALLBACK: u32 = 0;
+
+    /// Formats the value of the field called `name` with the [`Debug`](std::fmt::Debug)
+    /// implementation of its type, e.g. `U8` for `CSW.SIZE`.
+    fn format_field(&self, name: &str) -> Option<String>;
+
+    /// Parses `value`, replacing every field which does not parse by its value in
+    /// [`Register::LOSSY_FALLBACK`], e.g. a reserved `CSW.SIZE` by `U32`.
+    ///
+    /// This never fails, for diagnostics which show as much of a register as possible. The
+    /// replaced fields are returned along with the register. Use [`TryFrom`] where an invalid
+    /// value must not go unnoticed.
+    fn from_raw_lossy(value: u32) -> (Self, LossyFields) {
+        if let Ok(register) = Self::try_from(value) {
+            return (register, LossyFields::default());
+        }
+
+        let fallback = Self::LOSSY_FALLBACK;
+        let mut lossy = LossyFields::default();
+        let mut repaired = value;
+        for (index, field) in Self::FIELDS.iter().enumerate() {
+            // Check each field on its own, against fields which are known to parse.
+            let isolated = (fallback & !field.mask()) | (value & field.mask());
+            if Self::try_from(isolated).is_err() {
+                repaired = (repaired & !field.mask()) | (fallback & field.mask());
+                lossy.0 |= 1 << index;
+            }
+        }
+
+        match Self::try_from(repaired) {
+            Ok(register) => (register, lossy),
+            // The fields are only invalid in combination, so none of them can be trusted.
+            Err(_) => {
+                let register = Self::try_from(fallback)
+                    .expect("the lossy fallback value of a register parses");
+                let all = u32::MAX.checked_shr(32 - Self::FIELDS.len() as u32);
+                (register, LossyFields(all.unwrap_or(0)))
+            }
+        }
+    }
+
+    /// Parses `value` read from the register, replacing the unknown value of every write-only
+    /// field by its value in [`Register::LOSSY_FALLBACK`], so the register can be written back.
+    ///
+    /// The register reads of the memory AP layer parse their values with this.
+    fn from_read(value: u32) -> Result<Self, RegisterParseError> {
+        let write_only = Self::FIELDS
+            .iter()
+            .filter(|field| field.access == FieldAccess::WriteOnly)
+            .fold(0, |mask, field| mask | field.mask());
+        Self::try_from((value & !write_only) | (Self::LOSSY_FALLBACK & write_only))
+    }
+
+    /// The mask of the bits which are significant when comparing values of the register: the
+    /// bits of all fields which are neither reserved nor read-only.
+    fn significant_mask() -> u32 {
+        Self::FIELDS
+            .iter()
+            .filter(|field| !field.is_reserved() && field.access != FieldAccess::ReadOnly)
+            .fold(0, |mask, field| mask | field.mask())
+    }
+
+    /// Compares the significant bits of the two register values (see
+    /// [`Register::significant_mask`]), except for the bits in `mask`.
+    ///
+    /// This tells whether writing `other` over `self` would change the configuration of the AP,
+    /// e.g. for skipping a redundant write.
+    fn eq_ignoring(&self, other: &Self, mask: u32) -> bool {
+        let (value, other) = (self.clone().into(), other.clone().into());
+        (value ^ other) & Self::significant_mask() & !mask == 0
+    }
+
+    /// Returns the name of the first read-only field whose value differs from `previous`.
+    ///
+    /// Writing such a value back to the register would push bits into read-only positions.
+    fn changed_read_only_field(&self, previous: &Self) -> Option<&'static str> {
+        let (value, previous) = (self.clone().into(), previous.clone().into());
+        Self::FIELDS
+            .iter()
+            .find(|field| {
+                field.access == FieldAccess::ReadOnly
+                    && field.extract(value) != field.extract(previous)
+            })
+            .map(|field| field.name)
+    }
+
+    /// Wraps the register to be formatted with its raw value in hex and binary ahead of its
+    /// fields, e.g. `TAR(0x00001234 = 0b...): { address: 4660 }`, for comparing it with a
+    /// datasheet. The [`Debug`](std::fmt::Debug) output of the register itself is unchanged.
+    fn hex_debug(&self) -> HexDebug<Self> {
+        HexDebug(self.clone())
+    }
+}
+
+/// A register formatted with its raw value in hex and binary, see [`Register::hex_debug`].
+///
+/// [`Debug`](std::fmt::Debug) and [`Display`](std::fmt::Display) print the same, with the
+/// fields pretty-printed for `{:#?}`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct HexDebug<R>(pub R);
+
+impl<R: Register> std::fmt::Debug for HexDebug<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let raw: u32 = self.0.clone().into();
+        let fields = if f.alternate() {
+            format!("{:#?}", self.0)
+        } else {
+            format!("{:?}", self.0)
+        };
+        let fields = fields.strip_prefix(R::NAME).unwrap_or(&fields).trim_start();
+        write!(f, "{}({raw:#010x} = {raw:#034b}): {fields}", R::NAME)
+    }
+}
+
+impl<R: Register> std::fmt::Display for HexDebug<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// The fields that were replaced while parsing a register with [`Register::from_raw_lossy`], as a
+/// bit mask of their indices in [`Register::FIELDS`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct LossyFields(pub u32);
+
+impl LossyFields {
+    /// Returns whether all fields were parsed from the raw value.
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns the replaced fields of the register `R`.
+    pub fn fields<R: Register>(self) -> impl Iterator<Item = &'static FieldInfo> {
+        R::FIELDS
+            .iter()
+            .enumerate()
+            .filter(move |(index, _)| self.0 & (1 << index) != 0)
+            .map(|(_, field)| field)
+    }
+}
+
+/// The name, address and field layout of a register, as a `const` which needs neither the
+/// register type nor an allocator to decode raw values.
+///
+/// Every register defined with [`define_apv2_register!`] has one as its `LAYOUT` constant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterLayout {
+    /// The name of the register.
+    pub name: &'static str,
+    /// The offset of the register within the AP's register file.
+    pub address: u16,
+    /// The fields of the register, from the most significant field.
+    pub fields: &'static [FieldInfo],
+}
+
+impl RegisterLayout {
+    /// Returns the layout of the register `R`.
+    pub const fn of<R: Register>() -> Self {
+        Self {
+            name: R::NAME,
+            address: R::ADDRESS,
+            fields: R::FIELDS,
+        }
+    }
+
+    /// Returns the field called `name`.
+    pub fn field(&self, name: &str) -> Option<&'static FieldInfo> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    /// Returns the name and value of each field of the raw register value `raw`.
+    pub fn decode(&self, raw: u32) -> impl Iterator<Item = (&'static str, u32)> {
+        self.fields
+            .iter()
+            .map(move |field| (field.name, field.extract(raw)))
+    }
+}
+
+/// A register of a memory access port, for tooling which only knows the register at runtime.
+///
+/// Every register defined with [`define_apv2_register!`](crate::define_apv2_register) adds its descriptor to [`REGISTERS`].
+#[derive(Debug, Clone, Copy)]
+pub struct RegisterDescriptor {
+    /// The name of the register.
+    pub name: &'static str,
+    /// The offset of the register within the AP's register file.
+    pub address: u16,
+    /// The layout of the fields of the register.
+    pub fields: &'static [FieldInfo],
+    format: fn(u32) -> Result<String, RegisterParseError>,
+}
+
+impl RegisterDescriptor {
+    /// Returns the descriptor of the register `R`.
+    pub const fn of<R: Register>() -> Self {
+        Self {
+            name: R::NAME,
+            address: R::ADDRESS,
+            fields: R::FIELDS,
+            format: format_register::<R>,
+        }
+    }
+
+    /// Parses the raw register value `raw` and formats it with its [`Debug`] implementation.
+    pub fn format(&self, raw: u32) -> Result<String, RegisterParseError> {
+        (self.format)(raw)
+    }
+}
+
+fn format_register<R: Register>(raw: u32) -> Result<String, RegisterParseError> {
+    Ok(format!("{:?}", R::try_from(raw)?))
+}
+
+/// All registers of a memory access port, in no particular order.
+///
+/// The slice is collected at link time from the registers defined with
+/// [`define_apv2_register!`](crate::define_apv2_register), so it cannot miss one.
+#[linkme::distributed_slice]
+pub static REGISTERS: [RegisterDescriptor];
+
+/// Returns the fields whose values differ between `before` and `after` as
+/// `(name, old value, new value)`, in the order of [`Register::FIELDS`].
+pub fn diff_fields<R: Register>(before: R, after: R) -> Vec<(&'static str, u32, u32)> {
+    let (before, after) = (before.into(), after.into());
+    R::FIELDS
+        .iter()
+        .map(|field| (field.name, field.extract(before), field.extract(after)))
+        .filter(|(_, old, new)| old != new)
+        .collect()
+}
+
+/// Displays the changed fields between two values of a register with their decoded values, e.g.
+/// `SIZE: U32 -> U8`, one field per line.
+///
+/// Every change is held as `(name, old value, new value)`, formatted with
+/// [`Register::format_field`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDiff(pub Vec<(&'static str, String, String)>);
+
+impl FieldDiff {
+    /// Compares the fields of `before` and `after`, see [`diff_fields`].
+    pub fn between<R: Register>(before: R, after: R) -> Self {
+        let changes = diff_fields(before.clone(), after.clone())
+            .into_iter()
+            .map(|(name, _, _)| {
+                let format = |register: &R| register.format_field(name).unwrap_or_default();
+                (name, format(&before), format(&after))
+            })
+            .collect();
+        Self(changes)
+    }
+}
+
+impl std::fmt::Display for FieldDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, old, new) in &self.0 {
+            writeln!(f, "{name}: {old} -> {new}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Maps the `#[access(..)]` annotation of a field in [`define_apv2_register!`] to its
+/// [`FieldAccess`].
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __apv2_field_access {
+    () => {
+        $crate::architecture::arm::FieldAccess::ReadWrite
+    };
+    (ro) => {
+        $crate::architecture::arm::FieldAccess::ReadOnly
+    };
+    (wo) => {
+        $crate::architecture::arm::FieldAccess::WriteOnly
+    };
 }
 
 /// Defines a new typed access port register for a specific access port.
@@ -15,16 +299,27 @@ pub trait Register:
 /// - type: The type of the port.
 /// - name: The name of the constructed type for the register. Also accepts a doc comment to be added to the type.
 /// - address: The address relative to the base address of the access port.
-/// - fields: A list of fields of the register type.
+/// - fields: A list of fields of the register type, each followed by `[offset, width]` giving the
+///   position of its least significant bit and its width in bits. A field can be marked as
+///   read-only with `#[access(ro)]` or as write-only with `#[access(wo)]` after its doc comment.
+/// - reset: An optional reset value. If given, the register implements [`Default`] with it, and
+///   [`Register::from_raw_lossy`] falls back to its fields. Otherwise, zero must parse.
 /// - from: a closure to transform from an `u32` to the typed register.
 /// - to: A closure to transform from they typed register to an `u32`.
+///
+/// The register type also gets a `LAYOUT` constant with its `RegisterLayout`.
 #[macro_export]
 macro_rules! define_apv2_register {
     (
         $(#[$outer:meta])*
         name: $name:ident,
         address: $address:expr,
-        fields: [$($(#[$inner:meta])*$field:ident: $type:ty$(,)?)*],
+        fields: [$(
+            $(#[doc = $doc:literal])*
+            $(#[access($access:ident)])?
+            $field:ident: $type:ty [$offset:literal, $width:literal]$(,)?
+        )*],
+        $(reset: $reset:expr,)?
         from: $from_param:ident => $from:expr,
         to: $to_param:ident => $to:expr
     )
@@ -32,22 +327,74 @@ macro_rules! define_apv2_register {
         $(#[$outer])*
         #[allow(non_snake_case)]
         #[allow(clippy::upper_case_acronyms)]
-        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
         pub struct $name {
-            $($(#[$inner])*pub $field: $type,)*
+            $($(#[doc = $doc])*pub $field: $type,)*
         }
 
         impl $crate::architecture::arm::ap_v2::registers::Register for $name {
             // ADDRESS is always the lower 4 bits of the register address.
             const ADDRESS: u16 = $address;
             const NAME: &'static str = stringify!($name);
+            const FIELDS: &'static [$crate::architecture::arm::FieldInfo] = &[
+                $($crate::architecture::arm::FieldInfo {
+                    name: stringify!($field),
+                    offset: $offset,
+                    width: $width,
+                    access: $crate::__apv2_field_access!($($access)?),
+                },)*
+            ];
+            $(const LOSSY_FALLBACK: u32 = $reset;)?
+
+            fn format_field(&self, name: &str) -> Option<String> {
+                match name {
+                    $(stringify!($field) => Some(format!("{:?}", self.$field)),)*
+                    _ => None,
+                }
+            }
+        }
+
+        // Every field type has to hold the bits of its field.
+        const _: () = {
+            $(assert!(
+                ::core::mem::size_of::<$type>() * 8 >= $width,
+                concat!(
+                    "The type of ",
+                    stringify!($name),
+                    ".",
+                    stringify!($field),
+                    " is narrower than the field",
+                ),
+            );)*
+        };
+
+        const _: () = {
+            #[linkme::distributed_slice($crate::architecture::arm::ap_v2::registers::REGISTERS)]
+            static DESCRIPTOR: $crate::architecture::arm::ap_v2::registers::RegisterDescriptor =
+                $crate::architecture::arm::ap_v2::registers::RegisterDescriptor::of::<$name>();
+        };
+
+        impl $name {
+            /// The name, address and field layout of this register.
+            pub const LAYOUT: $crate::architecture::arm::ap_v2::registers::RegisterLayout =
+                $crate::architecture::arm::ap_v2::registers::RegisterLayout::of::<$name>();
         }
 
         impl TryFrom<u32> for $name {
             type Error = $crate::architecture::arm::RegisterParseError;
 
             fn try_from($from_param: u32) -> Result<$name, Self::Error> {
-                $from
+                fn parse(
+                    $from_param: u32,
+                ) -> Result<$name, $crate::architecture::arm::RegisterParseError> {
+                    $from
+                }
+                // Errors converted from field errors with `?` only hold the field's encoding.
+                parse($from_param).map_err(|error| {
+                    error
+                        .in_register(stringify!($name), $from_param)
+                        .with_fields(<$name as $crate::architecture::arm::ap_v2::registers::Register>::FIELDS)
+                })
             }
         }
 
@@ -56,6 +403,15 @@ macro_rules! define_apv2_register {
                 $to
             }
         }
+
+        $(
+            impl Default for $name {
+                fn default() -> Self {
+                    <$name>::try_from($reset)
+                        .expect(concat!("The reset value of ", stringify!($name), " is valid"))
+                }
+            }
+        )?
     }
 }
 
@@ -64,7 +420,12 @@ macro_rules! define_apv2_register {
 /// This can be configured with the CSW command.
 ///
 /// ALL MCUs support `U32`. All other transfer sizes are optionally implemented.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+///
+/// Data sizes are ordered by their byte count, so `a.max(b)` yields the wider of two sizes.
+///
+/// Further sizes may be added, so matches on a `DataSize` need a wildcard arm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[non_exhaustive]
 pub enum DataSize {
     /// 1 byte transfers are supported.
     U8 = 0b000,
@@ -82,8 +443,48 @@ pub enum DataSize {
 }
 
 impl DataSize {
+    /// The position of the `SIZE` field within the CSW.
+    pub const SHIFT: u32 = 0;
+    /// The mask of the `SIZE` field within the CSW.
+    pub const MASK: u32 = 0b111 << Self::SHIFT;
+
+    /// Returns the encoding of this size at its position within the CSW.
+    pub const fn bits(self) -> u32 {
+        (self as u32) << Self::SHIFT
+    }
+
+    /// Decodes the size at its position within the CSW word `csw`, the inverse of
+    /// [`DataSize::bits`].
+    pub const fn from_bits(csw: u32) -> Result<Self, InvalidDataSizeError> {
+        Self::decode(((csw & Self::MASK) >> Self::SHIFT) as u8)
+    }
+
+    const fn decode(value: u8) -> Result<Self, InvalidDataSizeError> {
+        match value {
+            0b000 => Ok(DataSize::U8),
+            0b001 => Ok(DataSize::U16),
+            0b010 => Ok(DataSize::U32),
+            0b011 => Ok(DataSize::U64),
+            0b100 => Ok(DataSize::U128),
+            0b101 => Ok(DataSize::U256),
+            _ => Err(InvalidDataSizeError { value }),
+        }
+    }
+
+    /// Returns the number of bytes transferred by one access of this size.
     pub fn to_byte_count(self) -> usize {
-        match self {
+        self.into()
+    }
+
+    /// Returns the size of an access transferring `count` bytes.
+    pub fn from_byte_count(count: usize) -> Result<Self, InvalidByteCountError> {
+        count.try_into()
+    }
+}
+
+impl From<DataSize> for usize {
+    fn from(size: DataSize) -> usize {
+        match size {
             DataSize::U8 => 1,
             DataSize::U16 => 2,
             DataSize::U32 => 4,
@@ -94,21 +495,51 @@ impl DataSize {
     }
 }
 
-/// Invalid data size.
-pub struct InvalidDataSizeError;
+impl TryFrom<usize> for DataSize {
+    type Error = InvalidByteCountError;
+    fn try_from(count: usize) -> Result<Self, InvalidByteCountError> {
+        match count {
+            1 => Ok(DataSize::U8),
+            2 => Ok(DataSize::U16),
+            4 => Ok(DataSize::U32),
+            8 => Ok(DataSize::U64),
+            16 => Ok(DataSize::U128),
+            32 => Ok(DataSize::U256),
+            _ => Err(InvalidByteCountError { count }),
+        }
+    }
+}
+
+/// Invalid DataSize {value:#05b}.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error, docsplay::Display)]
+pub struct InvalidDataSizeError {
+    /// The invalid encoded size.
+    pub value: u8,
+}
+
+/// Converts the error of `CSW.SIZE`, keeping it as the source.
+///
+/// The raw value holds the invalid size at its position in the CSW, and the register is unknown.
+/// Register parsers generated by `define_apv2_register!` set both with
+/// [`RegisterParseError::in_register`].
+impl From<InvalidDataSizeError> for RegisterParseError {
+    fn from(error: InvalidDataSizeError) -> Self {
+        RegisterParseError::of_field("SIZE", u32::from(error.value) << DataSize::SHIFT)
+            .with_source(error)
+    }
+}
+
+/// No access size transfers {count} bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error, docsplay::Display)]
+pub struct InvalidByteCountError {
+    /// The invalid byte count.
+    pub count: usize,
+}
 
 impl TryFrom<u8> for DataSize {
     type Error = InvalidDataSizeError;
     fn try_from(value: u8) -> Result<Self, InvalidDataSizeError> {
-        match value {
-            0b000 => Ok(DataSize::U8),
-            0b001 => Ok(DataSize::U16),
-            0b010 => Ok(DataSize::U32),
-            0b011 => Ok(DataSize::U64),
-            0b100 => Ok(DataSize::U128),
-            0b101 => Ok(DataSize::U256),
-            _ => Err(InvalidDataSizeError),
-        }
+        Self::decode(value)
     }
 }
 
@@ -118,7 +549,11 @@ impl TryFrom<u8> for DataSize {
 /// This will effectively save half the bandwidth!
 ///
 /// Can be configured in the CSW.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+///
+/// Further increments may be added for the reserved encoding `0b11`, so matches on an
+/// `AddressIncrement` need a wildcard arm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
 pub enum AddressIncrement {
     /// No increments are happening after the DRW access. TAR always stays the same.
     /// Always supported.
@@ -133,19 +568,70 @@ pub enum AddressIncrement {
 }
 
 impl AddressIncrement {
-    /// Create a new `AddressIncrement` from a u8.
-    pub fn from_u8(value: u8) -> Option<Self> {
+    /// The position of the `AddrInc` field within the CSW.
+    pub const SHIFT: u32 = 4;
+    /// The mask of the `AddrInc` field within the CSW.
+    pub const MASK: u32 = 0b11 << Self::SHIFT;
+
+    /// Returns the encoding of this increment at its position within the CSW.
+    pub const fn bits(self) -> u32 {
+        (self as u32) << Self::SHIFT
+    }
+
+    /// Decodes the increment at its position within the CSW word `csw`, the inverse of
+    /// [`AddressIncrement::bits`].
+    pub const fn from_bits(csw: u32) -> Result<Self, InvalidAddressIncrement> {
+        Self::decode(((csw & Self::MASK) >> Self::SHIFT) as u8)
+    }
+
+    const fn decode(value: u8) -> Result<Self, InvalidAddressIncrement> {
         match value {
-            0b00 => Some(AddressIncrement::Off),
-            0b01 => Some(AddressIncrement::Single),
-            0b10 => Some(AddressIncrement::Packed),
-            _ => None,
+            0b00 => Ok(AddressIncrement::Off),
+            0b01 => Ok(AddressIncrement::Single),
+            0b10 => Ok(AddressIncrement::Packed),
+            _ => Err(InvalidAddressIncrement { value }),
         }
     }
+
+    /// Create a new `AddressIncrement` from a u8.
+    ///
+    /// Prefer [`AddressIncrement::try_from`], which reports the invalid value.
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Self::try_from(value).ok()
+    }
+}
+
+/// Invalid AddressIncrement {value:#04b}.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error, docsplay::Display)]
+pub struct InvalidAddressIncrement {
+    /// The invalid encoded address increment.
+    pub value: u8,
+}
+
+/// Converts the error of `CSW.AddrInc`, keeping it as the source.
+///
+/// The raw value holds the invalid increment at its position in the CSW, and the register is
+/// unknown. Register parsers generated by `define_apv2_register!` set both with
+/// [`RegisterParseError::in_register`].
+impl From<InvalidAddressIncrement> for RegisterParseError {
+    fn from(error: InvalidAddressIncrement) -> Self {
+        RegisterParseError::of_field("AddrInc", u32::from(error.value) << AddressIncrement::SHIFT)
+            .with_source(error)
+    }
+}
+
+impl TryFrom<u8> for AddressIncrement {
+    type Error = InvalidAddressIncrement;
+    fn try_from(value: u8) -> Result<Self, InvalidAddressIncrement> {
+        Self::decode(value)
+    }
 }
 
 /// The format of the BASE register (see C2.6.1).
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+///
+/// Further formats may be added, so matches on a `BaseAddrFormat` need a wildcard arm.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+#[non_exhaustive]
 pub enum BaseAddrFormat {
     /// The legacy format of very old cores. Very little cores use this.
     #[default]
@@ -154,28 +640,53 @@ pub enum BaseAddrFormat {
     ADIv5 = 1,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
-#[allow(dead_code)] // Present is not used yet.
+/// Whether a debug entry is present.
+///
+/// Further states may be added, so matches on a `DebugEntryState` need a wildcard arm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
 pub enum DebugEntryState {
+    /// The entry is not present.
     #[default]
     NotPresent = 0,
+    /// The entry is present.
     Present = 1,
 }
 
+impl DebugEntryState {
+    /// Returns `true` if the entry is present.
+    pub fn is_present(self) -> bool {
+        self == DebugEntryState::Present
+    }
+}
+
+impl From<bool> for DebugEntryState {
+    fn from(present: bool) -> Self {
+        if present {
+            DebugEntryState::Present
+        } else {
+            DebugEntryState::NotPresent
+        }
+    }
+}
+
 define_apv2_register!(
     /// Control and Status Word register
     ///
     /// The control and status word register (CSW) is used
     /// to configure memory access through the memory AP.
+    ///
+    /// The default value `0x8000_0012` enables debug software access with
+    /// single auto-increment and 32 bit transfers, the same as [`CSW::builder`].
     name: CSW,
     address: 0xD00,
     fields: [
         /// Is debug software access enabled.
-        DbgSwEnable: bool,           // 1 bit
+        DbgSwEnable: bool [31, 1],
         /// Used with the Type field to define the bus access protection protocol.
         ///
         /// This field is implementation defined. See the memory ap specific definition for details.
-        Prot: u8,                  // 7 bits
+        Prot: u8 [24, 7],
         /// Secure Debug Enabled.
         ///
         /// This field has one of the following values:
@@ -191,7 +702,8 @@ define_apv2_register!(
         /// position as CSW.SDeviceEn, and has the same meaning. From ADIv6, the name SDeviceEn is
         /// used to avoid confusion between this field and the SPIDEN signal on the authentication
         /// interface.
-        SDeviceEn: bool,                // 1 bit
+        #[access(ro)]
+        SDeviceEn: bool [23, 1],
         /// Realm and root access status.
         ///
         /// When CFG.RME == 0b1, the defined values of this field are:
@@ -200,9 +712,10 @@ define_apv2_register!(
         /// * 0b01 - Realm access is enabled. Root access is enabled.
         ///
         /// This field is read-only.
-        RMEEN: u8, //2 bits
+        #[access(ro)]
+        RMEEN: u8 [21, 2],
         /// Reserved.
-        _RES0: u8,                 // 7 bits
+        _RES0: u8 [18, 3],
 
         /// Errors prevent future memory accesses.
         ///
@@ -211,7 +724,7 @@ define_apv2_register!(
         /// - 0b1 - Memory access errors prevent future memory accesses.
         ///
         /// CFG.ERR indicates whether this field is implemented.
-        ERRSTOP: bool,
+        ERRSTOP: bool [17, 1],
 
         /// Errors are not passed upstream.
         ///
@@ -220,27 +733,30 @@ define_apv2_register!(
         /// - 0b1 - Errors are not passed upstream.
         ///
         /// CFG.ERR indicates whether this field is implemented.
-        ERRNPASS: bool,
+        ERRNPASS: bool [16, 1],
         /// `1` if memory tagging access is enabled.
-        MTE: bool,                   // 1 bits
+        MTE: bool [15, 1],
         /// Memory tagging type. Implementation defined.
-        Type: u8,                  // 3 bits
+        Type: u8 [12, 3],
         /// Mode of operation. Is set to `0b0000` normally.
-        Mode: u8,                  // 4 bits
+        Mode: u8 [8, 4],
         /// A transfer is in progress.
         /// Can be used to poll whether an aborted transaction has completed.
         /// Read only.
-        TrInProg: bool,              // 1 bit
+        #[access(ro)]
+        TrInProg: bool [7, 1],
         /// `1` if transactions can be issued through this access port at the moment.
         /// Read only.
-        DeviceEn: bool,              // 1 bit
+        #[access(ro)]
+        DeviceEn: bool [6, 1],
         /// The address increment on DRW access.
-        AddrInc: AddressIncrement, // 2 bits
+        AddrInc: AddressIncrement [4, 2],
         /// Reserved
-        _RES1: u8,                 // 1 bit
+        _RES1: u8 [3, 1],
         /// The access size of this memory AP.
-        SIZE: DataSize,            // 3 bits
+        SIZE: DataSize [0, 3],
     ],
+    reset: 0x8000_0012,
     from: value => Ok(CSW {
         DbgSwEnable: ((value >> 31) & 0x01) != 0,
         Prot: ((value >> 24) & 0x7F) as u8,
@@ -254,9 +770,9 @@ define_apv2_register!(
         Mode: ((value >> 8) & 0x0F) as u8,
         TrInProg: ((value >> 7) & 0x01) != 0,
         DeviceEn: ((value >> 6) & 0x01) != 0,
-        AddrInc: AddressIncrement::from_u8(((value >> 4) & 0x03) as u8).ok_or_else(|| RegisterParseError::new("CSW", value))?,
+        AddrInc: AddressIncrement::from_bits(value)?,
         _RES1: ((value >> 3) & 1) as u8,
-        SIZE: DataSize::try_from((value & 0x07) as u8).map_err(|_| RegisterParseError::new("CSW", value))?,
+        SIZE: DataSize::from_bits(value)?,
     }),
     to: value => (u32::from(value.DbgSwEnable) << 31)
     | (u32::from(value.Prot         ) << 24)
@@ -270,23 +786,271 @@ define_apv2_register!(
     | (u32::from(value.Mode         ) <<  8)
     | (u32::from(value.TrInProg     ) <<  7)
     | (u32::from(value.DeviceEn     ) <<  6)
-    | (u32::from(value.AddrInc as u8) << 4)
-    | (u32::from(value._RES1        ) <<  1)
-    | (value.SIZE as u32)
+    | value.AddrInc.bits()
+    | (u32::from(value._RES1        ) <<  3)
+    | value.SIZE.bits()
 );
 
+/// A combination of CSW fields which the access port cannot perform, see [`CSW::validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error, docsplay::Display)]
+pub enum CswValidationError {
+    /// Packed transfers only pack sub-word accesses, but SIZE is {size:?}.
+    PackedNeedsSubWord {
+        /// The configured transfer size.
+        size: DataSize,
+    },
+
+    /// The access port only supports word transfers, not {size:?}.
+    SubWordUnsupported {
+        /// The configured transfer size.
+        size: DataSize,
+    },
+
+    /// {size:?} transfers need the large data extension (CFG.LD) and a bus of at least that width, but the largest supported size is {max_size:?}.
+    SizeUnsupported {
+        /// The configured transfer size.
+        size: DataSize,
+        /// The largest transfer size the access port supports, at least a word.
+        max_size: DataSize,
+    },
+}
+
+impl CSW {
+    /// Checks that the access port described by `caps` can perform transfers with the size and
+    /// address increment of this CSW.
+    ///
+    /// - [`AddressIncrement::Packed`] needs a sub-word `SIZE` and sub-word support. Whether the AP
+    ///   supports packing is only known after probing it, so this is checked when the CSW is
+    ///   written, see [`MemoryAp::write_register`](super::memory_ap::MemoryAp::write_register).
+    /// - A sub-word `SIZE` needs sub-word support.
+    /// - A `SIZE` above [`DataSize::U32`] needs the large data extension, reflected in
+    ///   [`MemApCapabilities::max_size`].
+    #[must_use = "a CSW which fails validation must not be written"]
+    pub fn validate(&self, caps: &MemApCapabilities) -> Result<(), CswValidationError> {
+        let size = self.SIZE;
+        if self.AddrInc == AddressIncrement::Packed && size >= DataSize::U32 {
+            return Err(CswValidationError::PackedNeedsSubWord { size });
+        }
+        if size < DataSize::U32 && caps.sub_word == Some(false) {
+            return Err(CswValidationError::SubWordUnsupported { size });
+        }
+        // Word transfers are always supported, even through a narrower bus.
+        let max_size = caps.max_size.max(DataSize::U32);
+        if size > max_size {
+            return Err(CswValidationError::SizeUnsupported { size, max_size });
+        }
+        Ok(())
+    }
+
+    /// Returns the effective value of `SDeviceEn`, i.e. whether secure accesses are enabled.
+    ///
+    /// If `DeviceEn` is clear, `SDeviceEn` is ignored and its effective value is `0b1`.
+    pub fn effective_secure_access(&self) -> bool {
+        !self.DeviceEn || self.SDeviceEn
+    }
+
+    /// Returns whether the `TrInProg` bit is set in the raw CSW value `word`.
+    ///
+    /// Unlike parsing the full register, this cannot fail on unrelated fields.
+    #[must_use]
+    pub fn tr_in_progress(word: u32) -> bool {
+        (word >> 7) & 0x01 != 0
+    }
+
+    /// Returns the memory tagging mode selected by `Type`, or `None` if `MTE` is clear.
+    ///
+    /// The meaning of `Type` is implementation defined, so this uses
+    /// `TaggingModeTable::DEFAULT`, which reports every value as
+    /// `TaggingMode::ImplementationDefined`. Use [`CSW::tagging_mode_with`] with the table of
+    /// the AP type at hand to get the actual modes.
+    pub fn tagging_mode(&self) -> Option<TaggingMode> {
+        self.tagging_mode_with(&TaggingModeTable::DEFAULT)
+    }
+
+    /// Returns the memory tagging mode selected by `Type` according to `table`, or `None` if
+    /// `MTE` is clear.
+    pub fn tagging_mode_with(&self, table: &TaggingModeTable) -> Option<TaggingMode> {
+        self.MTE.then(|| table.get(self.Type))
+    }
+
+    /// Returns a builder starting from the architectural default: 32 bit accesses, single address
+    /// increment and debug software access enabled, with all other fields zero.
+    pub const fn builder() -> CswBuilder {
+        CswBuilder {
+            csw: CSW {
+                DbgSwEnable: true,
+                Prot: 0,
+                SDeviceEn: false,
+                RMEEN: 0,
+                _RES0: 0,
+                ERRSTOP: false,
+                ERRNPASS: false,
+                MTE: false,
+                Type: 0,
+                Mode: 0,
+                TrInProg: false,
+                DeviceEn: false,
+                AddrInc: AddressIncrement::Single,
+                _RES1: 0,
+                SIZE: DataSize::U32,
+            },
+        }
+    }
+
+    /// Word accesses with the address incremented after each, for reading or writing blocks of
+    /// memory. This is the same as [`CSW::builder`].
+    pub const fn new_word_burst() -> CSW {
+        CSW::builder().build()
+    }
+
+    /// Single byte accesses without address increment.
+    pub const fn new_byte_access() -> CSW {
+        CSW::builder()
+            .size(DataSize::U8)
+            .addr_inc(AddressIncrement::Off)
+            .build()
+    }
+
+    /// Single halfword accesses without address increment.
+    pub const fn new_halfword_access() -> CSW {
+        CSW::builder()
+            .size(DataSize::U16)
+            .addr_inc(AddressIncrement::Off)
+            .build()
+    }
+
+    /// Word accesses to the same address, for feeding a FIFO or the data register of a
+    /// peripheral, see [`write_fifo`](super::memory_ap::write_fifo).
+    pub const fn new_fifo_write() -> CSW {
+        CSW::builder().addr_inc(AddressIncrement::Off).build()
+    }
+
+    /// Byte accesses packed into words, with the address incremented after each byte. The AP has
+    /// to support packed transfers, see [`CSW::validate`].
+    pub const fn new_packed_bytes() -> CSW {
+        CSW::builder()
+            .size(DataSize::U8)
+            .addr_inc(AddressIncrement::Packed)
+            .build()
+    }
+}
+
+/// The memory tagging access semantics selected by `CSW.Type` while `CSW.MTE` is set, see
+/// [`CSW::tagging_mode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TaggingMode {
+    /// Data accesses which do not check the allocation tags.
+    Unchecked,
+    /// Data accesses which are checked against the allocation tags.
+    Checked,
+    /// Accesses to the allocation tags instead of the data.
+    Tags,
+    /// A `CSW.Type` value the [`TaggingModeTable`] has no interpretation for.
+    ImplementationDefined(u8),
+}
+
+/// Maps the values of `CSW.Type` to [`TaggingMode`]s for one AP type.
+///
+/// `CSW.Type` is implementation defined, so each AP type, e.g. a vendor's AXI AP with memory
+/// tagging, can provide its own table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaggingModeTable([TaggingMode; 8]);
+
+impl TaggingModeTable {
+    /// The table used by [`CSW::tagging_mode`], which has no interpretation for any value.
+    pub const DEFAULT: Self = Self([
+        TaggingMode::ImplementationDefined(0),
+        TaggingMode::ImplementationDefined(1),
+        TaggingMode::ImplementationDefined(2),
+        TaggingMode::ImplementationDefined(3),
+        TaggingMode::ImplementationDefined(4),
+        TaggingMode::ImplementationDefined(5),
+        TaggingMode::ImplementationDefined(6),
+        TaggingMode::ImplementationDefined(7),
+    ]);
+
+    /// Returns this table with the `CSW.Type` value `ty` mapped to `mode`.
+    ///
+    /// Only the lower 3 bits of `ty` are used, as the field is 3 bits wide.
+    pub const fn with(mut self, ty: u8, mode: TaggingMode) -> Self {
+        self.0[(ty & 0x07) as usize] = mode;
+        self
+    }
+
+    /// The mode of the `CSW.Type` value `ty`.
+    pub const fn get(&self, ty: u8) -> TaggingMode {
+        self.0[(ty & 0x07) as usize]
+    }
+}
+
+impl Default for TaggingModeTable {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// A builder for a [`CSW`] value, created with [`CSW::builder`].
+///
+/// Only the writable configuration fields can be set. Reserved and read-only fields stay zero.
+#[derive(Debug, Clone, Copy)]
+pub struct CswBuilder {
+    csw: CSW,
+}
+
+impl CswBuilder {
+    /// Sets the access size.
+    pub const fn size(mut self, size: DataSize) -> Self {
+        self.csw.SIZE = size;
+        self
+    }
+
+    /// Sets the address increment on DRW access.
+    pub const fn addr_inc(mut self, addr_inc: AddressIncrement) -> Self {
+        self.csw.AddrInc = addr_inc;
+        self
+    }
+
+    /// Sets the bus access protection.
+    pub const fn prot(mut self, prot: u8) -> Self {
+        self.csw.Prot = prot;
+        self
+    }
+
+    /// Sets the mode of operation.
+    pub const fn mode(mut self, mode: u8) -> Self {
+        self.csw.Mode = mode;
+        self
+    }
+
+    /// Sets whether debug software access is enabled.
+    pub const fn dbg_sw_enable(mut self, dbg_sw_enable: bool) -> Self {
+        self.csw.DbgSwEnable = dbg_sw_enable;
+        self
+    }
+
+    /// Builds the CSW value.
+    pub const fn build(self) -> CSW {
+        self.csw
+    }
+}
+
 define_apv2_register!(
     /// Transfer Address Register
     ///
     /// The transfer address register (TAR) holds the memory
     /// address which will be accessed through a read or
     /// write of the DRW register.
+    ///
+    /// The TAR resets to an UNKNOWN value, so it must be programmed before the first DRW access.
+    /// [`TAR::default`] is address zero.
     name: TAR,
     address: 0xD04,
     fields: [
         /// The register address to be used for the next access to DRW.
-        address: u32,
+        address: u32 [0, 32],
     ],
+    reset: 0,
     from: value => Ok(TAR { address: value }),
     to: value => value.address
 );
@@ -297,16 +1061,72 @@ define_apv2_register!(
     /// The transfer address register (TAR) holds the memory
     /// address which will be accessed through a read or
     /// write of the DRW register.
+    ///
+    /// [`TAR2::default`] is zero, as for every address below 4 GiB. APs without the large
+    /// physical address extension (`CFG.LA`) do not implement it.
     name: TAR2,
     address: 0xD08,
     fields: [
         /// The upper 32-bits of the register address to be used for the next access to DRW.
-        address: u32,
+        address: u32 [0, 32],
     ],
+    reset: 0,
     from: value => Ok(TAR2 { address: value }),
     to: value => value.address
 );
 
+/// The address {address:#x} is above 4 GB, but the access port does not implement the large
+/// physical address extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error, docsplay::Display)]
+pub struct AddressTooLargeError {
+    /// The rejected address.
+    pub address: u64,
+}
+
+/// A transfer address, held by [`TAR`] and [`TAR2`] together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct TransferAddress(u64);
+
+impl TransferAddress {
+    /// Creates the transfer address `address` for the access port configured by `cfg`.
+    ///
+    /// Addresses above 4 GB need TAR2, which is only implemented if `CFG.LA` is set.
+    pub fn new(address: u64, cfg: &CFG) -> Result<Self, AddressTooLargeError> {
+        if address > u64::from(u32::MAX) && !cfg.LA {
+            return Err(AddressTooLargeError { address });
+        }
+        Ok(Self(address))
+    }
+
+    /// Combines the lower word in `tar` and the upper word in `tar2`, which is zero if absent.
+    pub fn from_parts(tar: TAR, tar2: Option<TAR2>) -> Self {
+        let upper = tar2.map_or(0, |tar2| tar2.address);
+        Self((u64::from(upper) << 32) | u64::from(tar.address))
+    }
+
+    /// Splits the address into the values of TAR and TAR2, the latter being `None` if the upper
+    /// word is zero.
+    pub fn split(self) -> (TAR, Option<TAR2>) {
+        let tar = TAR {
+            address: self.0 as u32,
+        };
+        let upper = (self.0 >> 32) as u32;
+        (tar, (upper != 0).then_some(TAR2 { address: upper }))
+    }
+}
+
+impl From<u32> for TransferAddress {
+    fn from(address: u32) -> Self {
+        Self(address.into())
+    }
+}
+
+impl From<TransferAddress> for u64 {
+    fn from(address: TransferAddress) -> Self {
+        address.0
+    }
+}
+
 define_apv2_register!(
     /// Data Read/Write register
     ///
@@ -317,64 +1137,99 @@ define_apv2_register!(
     /// to the address specified in the TAR register.
     ///
     /// A read from the *DRW* register is translated to a memory read
+    /// from the address specified in the TAR register.
+    ///
+    /// Both update the TAR as configured by `CSW.AddrInc`.
     name: DRW,
     address: 0xD0C,
     fields: [
         /// The data held in the DRW corresponding to the address held in TAR.
-        data: u32,
+        data: u32 [0, 32],
     ],
+    reset: 0,
     from: value => Ok(DRW { data: value }),
     to: value => value.data
 );
 
 define_apv2_register!(
     /// Banked Data 0 register
+    ///
+    /// Accesses the word at offset 0x0 of the 16 byte aligned window the TAR points
+    /// into, without updating the TAR.
     name: BD0,
     address: 0xD10,
     fields: [
         /// The data held in this bank.
-        data: u32,
+        data: u32 [0, 32],
     ],
+    reset: 0,
     from: value => Ok(BD0 { data: value }),
     to: value => value.data
 );
 
 define_apv2_register!(
     /// Banked Data 1 register
+    ///
+    /// Accesses the word at offset 0x4 of the 16 byte aligned window the TAR points
+    /// into, without updating the TAR.
     name: BD1,
     address: 0xD14,
     fields: [
         /// The data held in this bank.
-        data: u32,
+        data: u32 [0, 32],
     ],
+    reset: 0,
     from: value => Ok(BD1 { data: value }),
     to: value => value.data
 );
 
 define_apv2_register!(
     /// Banked Data 2 register
+    ///
+    /// Accesses the word at offset 0x8 of the 16 byte aligned window the TAR points
+    /// into, without updating the TAR.
     name: BD2,
     address: 0xD18,
     fields: [
         /// The data held in this bank.
-        data: u32,
+        data: u32 [0, 32],
     ],
+    reset: 0,
     from: value => Ok(BD2 { data: value }),
     to: value => value.data
 );
 
 define_apv2_register!(
     /// Banked Data 3 register
+    ///
+    /// Accesses the word at offset 0xc of the 16 byte aligned window the TAR points
+    /// into, without updating the TAR.
     name: BD3,
     address: 0xD1C,
     fields: [
         /// The data held in this bank.
-        data: u32,
+        data: u32 [0, 32],
     ],
+    reset: 0,
     from: value => Ok(BD3 { data: value }),
     to: value => value.data
 );
 
+define_apv2_register!(
+    /// Tag 0 Transfer register
+    ///
+    /// When `CSW.MTE` is set, this register holds the allocation tag of the memory granule
+    /// addressed by the TAR.
+    name: T0TR,
+    address: 0xD30,
+    fields: [
+        /// The allocation tags, with the tag of the granule addressed by the TAR in bits `[3:0]`.
+        tags: u32 [0, 32],
+    ],
+    from: value => Ok(T0TR { tags: value }),
+    to: value => value.tags
+);
+
 define_apv2_register!(
     /// Memory Barrier Transfer register
     ///
@@ -388,19 +1243,35 @@ define_apv2_register!(
     address: 0xD20,
     fields: [
         /// This value is implementation defined and the ADIv5.2 spec does not explain what it does for targets with the Barrier Operations Extension implemented.
-        data: u32,
+        data: u32 [0, 32],
     ],
     from: value => Ok(MBT { data: value }),
     to: value => value.data
 );
 
+define_apv2_register!(
+    /// Transfer Response register
+    ///
+    /// Reports whether a memory access received an error response, if `CFG.ERR` reports error
+    /// control. While `TRR.ERR` and `CSW.ERRSTOP` are set, the AP does not perform any memory
+    /// accesses.
+    name: TRR,
+    address: 0xD24,
+    fields: [
+        /// A memory access received an error response. Writing `1` clears it.
+        ERR: bool [0, 1],
+    ],
+    from: value => Ok(TRR { ERR: (value & 0b1) != 0 }),
+    to: value => u32::from(value.ERR)
+);
+
 define_apv2_register!(
     /// Base register
     name: BASE2,
     address: 0xDF0,
     fields: [
         /// The second part of the base address of this access point if required.
-        BASEADDR: u32
+        BASEADDR: u32 [0, 32],
     ],
     from: value => Ok(BASE2 { BASEADDR: value }),
     to: value => value.BASEADDR
@@ -414,72 +1285,197 @@ define_apv2_register!(
     name: CFG,
     address: 0xDF4,
     fields: [
+        /// The size of the TAR incrementer. See [`CFG::auto_increment_block_size`].
+        TARINC: u8 [16, 4],
+        /// The error reporting features of this access port. See [`CFG::supports_error_control`].
+        ERR: u8 [12, 4],
+        /// The size of the Direct Access Register space. See [`CFG::dar_size`].
+        DARSIZE: u8 [8, 4],
+        /// Specifies whether this access port implements the Realm Management Extension, in which
+        /// case `CSW.RMEEN` is implemented.
+        RME: bool [3, 1],
         /// Specifies whether this access port includes the large data extension (access larger than 32 bits).
-        LD: bool,
+        LD: bool [2, 1],
         /// Specifies whether this access port includes the large address extension (64 bit addressing).
-        LA: bool,
+        LA: bool [1, 1],
         /// Specifies whether this architecture uses big endian. Must always be zero for modern chips as the ADI v5.2 deprecates big endian.
-        BE: bool,
+        BE: bool [0, 1],
     ],
     from: value => Ok(CFG {
+        TARINC: ((value >> 16) & 0x0F) as u8,
+        ERR: ((value >> 12) & 0x0F) as u8,
+        DARSIZE: ((value >> 8) & 0x0F) as u8,
+        RME: ((value >> 3) & 0x01) != 0,
         LD: ((value >> 2) & 0x01) != 0,
         LA: ((value >> 1) & 0x01) != 0,
         BE: (value & 0x01) != 0,
     }),
-    to: value => ((value.LD as u32) << 2) | ((value.LA as u32) << 1) | (value.BE as u32)
+    to: value => (u32::from(value.TARINC & 0x0F) << 16)
+        | (u32::from(value.ERR & 0x0F) << 12)
+        | (u32::from(value.DARSIZE & 0x0F) << 8)
+        | ((value.RME as u32) << 3)
+        | ((value.LD as u32) << 2)
+        | ((value.LA as u32) << 1)
+        | (value.BE as u32)
 );
 
+impl CFG {
+    /// The size of the address block within which the TAR auto-increment works, in bytes.
+    ///
+    /// An incrementer size of zero means the architectural minimum of 10 bits.
+    #[must_use]
+    pub const fn auto_increment_block_size(&self) -> u64 {
+        match self.TARINC {
+            0 => 1 << 10,
+            tarinc => 1 << (tarinc as u32 + 9),
+        }
+    }
+
+    /// Whether `CSW.ERRNPASS` and `CSW.ERRSTOP` are implemented.
+    #[must_use]
+    pub const fn supports_error_control(&self) -> bool {
+        self.ERR != 0
+    }
+
+    /// The size of the Direct Access Register space in bytes, or `None` if the access port does
+    /// not implement the DAR registers.
+    #[must_use]
+    pub const fn dar_size(&self) -> Option<u64> {
+        match self.DARSIZE {
+            0 => None,
+            darsize => Some(1 << darsize),
+        }
+    }
+}
+
 define_apv2_register!(
     /// Base register
+    ///
+    /// In the [`BaseAddrFormat::ADIv5`] format, bit 0 indicates whether a debug entry is present
+    /// and the upper word of the address is held in [`BASE2`]. In the [`BaseAddrFormat::Legacy`]
+    /// format, the value `0xFFFF_FFFF` indicates that no debug entry is present. Either way, the
+    /// debug entry is 4 KB aligned, so only bits 31:12 are kept as [`BASE::BASEADDR`], and bits
+    /// 11:0 of a legacy value are dropped.
     name: BASE,
     address: 0xDF8,
     fields: [
-        /// The base address of this access point.
-        BASEADDR: u32,
+        /// The base address of this access point, shifted right by 12 bits.
+        ///
+        /// This must fit in 20 bits. Higher bits are dropped when encoding the register, use
+        /// [`BASE::new`] to check the value.
+        BASEADDR: u32 [12, 20],
         /// Reserved.
-        _RES0: u8,
+        _RES0: u16 [2, 10],
         /// The base address format of this access point.
-        Format: BaseAddrFormat,
-        /// Does this access point exists?
-        /// This field can be used to detect access points by iterating over all possible ones until one is found which has `exists == false`.
-        present: bool,
+        Format: BaseAddrFormat [1, 1],
+        /// Whether a debug entry is present at the base address.
+        /// This field can be used to detect access points by iterating over all possible ones until one is found which has no entry.
+        present: DebugEntryState [0, 1],
     ],
-    from: value => Ok(BASE {
-        BASEADDR: (value & 0xFFFF_F000) >> 12,
-        _RES0: 0,
-        Format: match ((value >> 1) & 0x01) as u8 {
-            0 => BaseAddrFormat::Legacy,
-            1 => BaseAddrFormat::ADIv5,
-            _ => panic!("This is a bug. Please report it."),
-        },
-        present: match (value & 0x01) as u8 {
-            0 => false,
-            1 => true,
-            _ => panic!("This is a bug. Please report it."),
-        },
-    }),
-   to: value =>
-        (value.BASEADDR << 12)
-        // _RES0
-        | (u32::from(value.Format as u8) << 1)
-        | u32::from(value.present)
+    from: value => {
+        // All ones is the legacy encoding for "no debug entry", despite bit 1 being set.
+        let format = if value != 0xFFFF_FFFF && value & 0b10 != 0 {
+            BaseAddrFormat::ADIv5
+        } else {
+            BaseAddrFormat::Legacy
+        };
+        Ok(BASE {
+            BASEADDR: (value & 0xFFFF_F000) >> 12,
+            _RES0: 0,
+            Format: format,
+            present: match format {
+                BaseAddrFormat::Legacy => value != 0xFFFF_FFFF,
+                BaseAddrFormat::ADIv5 => (value & 0x01) != 0,
+            }
+            .into(),
+        })
+    },
+   to: value => match value.Format {
+        BaseAddrFormat::Legacy if !value.present.is_present() => 0xFFFF_FFFF,
+        BaseAddrFormat::Legacy => (value.BASEADDR & BASE::BASEADDR_MASK) << 12,
+        BaseAddrFormat::ADIv5 => ((value.BASEADDR & BASE::BASEADDR_MASK) << 12)
+            // _RES0
+            | (u32::from(value.Format as u8) << 1)
+            | value.present as u32,
+    }
 );
 
+impl BASE {
+    /// The bits of [`BASE::BASEADDR`] that fit into the register.
+    const BASEADDR_MASK: u32 = 0xF_FFFF;
+
+    /// Creates a BASE register value, rejecting a `base_addr` that does not fit in 20 bits.
+    ///
+    /// `base_addr` is the base address shifted right by 12 bits. The error for a rejected
+    /// `base_addr` names the `BASEADDR` field and holds the register value it would encode to,
+    /// with the bits beyond the field dropped.
+    pub fn new(
+        base_addr: u32,
+        format: BaseAddrFormat,
+        present: DebugEntryState,
+    ) -> Result<Self, RegisterParseError> {
+        let base = BASE {
+            BASEADDR: base_addr,
+            _RES0: 0,
+            Format: format,
+            present,
+        };
+        if base_addr & !Self::BASEADDR_MASK != 0 {
+            return Err(RegisterParseError::of_field("BASEADDR", base_addr)
+                .in_register("BASE", base.into())
+                .with_fields(Self::FIELDS));
+        }
+        Ok(base)
+    }
+}
+
+/// The address of the debug entry, usually a ROM table, held by [`BASE`] and [`BASE2`] together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RomTableAddress {
+    address: u64,
+}
+
+impl RomTableAddress {
+    /// Combines `base` with the upper word in `base2`, returning `None` if no debug entry is
+    /// present.
+    ///
+    /// `base2` is only used for the [`BaseAddrFormat::ADIv5`] format, as the legacy format only
+    /// supports 32 bit addresses. Without `base2`, e.g. because `CFG.LA` is clear, the upper word
+    /// is zero.
+    pub fn from_base(base: BASE, base2: Option<BASE2>) -> Option<Self> {
+        if !base.present.is_present() {
+            return None;
+        }
+        let upper = match (base.Format, base2) {
+            (BaseAddrFormat::ADIv5, Some(base2)) => u64::from(base2.BASEADDR) << 32,
+            _ => 0,
+        };
+        Some(Self {
+            address: upper | (u64::from(base.BASEADDR & BASE::BASEADDR_MASK) << 12),
+        })
+    }
+
+    /// The absolute address of the debug entry.
+    pub fn absolute(self) -> u64 {
+        self.address
+    }
+}
+
 define_apv2_register!(
     /// Identification register
     name: IDR,
     address: 0xDFC,
     fields: [
         /// This component’s revision.
-        REVISION: u8,
+        REVISION: u8 [28, 4],
         /// This component’s designer.
-        DESIGNER: u16,
+        DESIGNER: u16 [17, 11],
         /// This component’s class.
-        CLASS: u8,
+        CLASS: u8 [13, 4],
         /// This component’s variant.
-        VARIANT: u8,
+        VARIANT: u8 [4, 4],
         /// This component’s type.
-        TYPE: u8,
+        TYPE: u8 [0, 4],
     ],
     from: value => Ok(IDR {
         REVISION: (value >> 28) as u8 & 0xF,
@@ -495,3 +1491,762 @@ define_apv2_register!(
         | (u32::from(value.VARIANT) << 4)
         | u32::from(value.TYPE)
 );
+
+impl IDR {
+    /// The value of [`IDR::CLASS`] which identifies a memory access port.
+    pub const MEM_AP_CLASS: u8 = 0x8;
+
+    /// Returns the bus behind the AP if it is a memory access port, decoded from `IDR.TYPE`.
+    ///
+    /// Returns `None` for other classes of access ports, and for bus types which are reserved.
+    pub fn bus_type(&self) -> Option<MemApBusType> {
+        if self.CLASS != Self::MEM_AP_CLASS {
+            return None;
+        }
+        MemApBusType::try_from(self.TYPE).ok()
+    }
+}
+
+/// The bus behind a memory access port, as encoded in `IDR.TYPE`.
+///
+/// Matches need a wildcard arm for bus types added by future versions of the architecture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum MemApBusType {
+    /// AMBA AHB3.
+    AmbaAhb3 = 0x1,
+    /// AMBA APB2 or APB3.
+    AmbaApb2Apb3 = 0x2,
+    /// AMBA AXI3 or AXI4, with optional ACE-Lite support.
+    AmbaAxi3Axi4 = 0x4,
+    /// AMBA AHB5.
+    AmbaAhb5 = 0x5,
+    /// AMBA APB4 or APB5.
+    AmbaApb4Apb5 = 0x6,
+    /// AMBA AXI5.
+    AmbaAxi5 = 0x7,
+    /// AMBA AHB5 with enhanced HPROT.
+    AmbaAhb5Hprot = 0x8,
+}
+
+impl MemApBusType {
+    /// Returns whether the bus is an APB bus.
+    pub fn is_apb(self) -> bool {
+        matches!(
+            self,
+            MemApBusType::AmbaApb2Apb3 | MemApBusType::AmbaApb4Apb5
+        )
+    }
+
+    /// Returns whether the bus is an AXI bus.
+    pub fn is_axi(self) -> bool {
+        matches!(self, MemApBusType::AmbaAxi3Axi4 | MemApBusType::AmbaAxi5)
+    }
+}
+
+/// Reserved memory AP bus type {value:#x}.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error, docsplay::Display)]
+pub struct InvalidBusTypeError {
+    /// The reserved encoded bus type.
+    pub value: u8,
+}
+
+impl TryFrom<u8> for MemApBusType {
+    type Error = InvalidBusTypeError;
+    fn try_from(value: u8) -> Result<Self, InvalidBusTypeError> {
+        match value {
+            0x1 => Ok(MemApBusType::AmbaAhb3),
+            0x2 => Ok(MemApBusType::AmbaApb2Apb3),
+            0x4 => Ok(MemApBusType::AmbaAxi3Axi4),
+            0x5 => Ok(MemApBusType::AmbaAhb5),
+            0x6 => Ok(MemApBusType::AmbaApb4Apb5),
+            0x7 => Ok(MemApBusType::AmbaAxi5),
+            0x8 => Ok(MemApBusType::AmbaAhb5Hprot),
+            _ => Err(InvalidBusTypeError { value }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::FieldInfo;
+    use super::{
+        diff_fields, AddressIncrement, BaseAddrFormat, CswValidationError, DataSize,
+        DebugEntryState, FieldAccess, FieldDiff, InvalidAddressIncrement, InvalidBusTypeError,
+        InvalidDataSizeError, MemApBusType, Register, RegisterLayout, RomTableAddress, TaggingMode,
+        TaggingModeTable, TransferAddress, BASE, BASE2, BD0, CFG, CSW, DRW, IDR, MBT, T0TR, TAR,
+        TAR2, TRR,
+    };
+    use crate::architecture::arm::ap_v2::capabilities::MemApCapabilities;
+    use crate::architecture::arm::ap_v2::memory_ap::{mock::MockMemoryAp, read_register};
+    use crate::architecture::arm::RegisterParseError;
+
+    #[test]
+    fn data_size_is_ordered_by_byte_count() {
+        let sizes = [
+            DataSize::U8,
+            DataSize::U16,
+            DataSize::U32,
+            DataSize::U64,
+            DataSize::U128,
+            DataSize::U256,
+        ];
+        for pair in sizes.windows(2) {
+            assert!(pair[0] < pair[1]);
+            assert!(pair[0].to_byte_count() < pair[1].to_byte_count());
+        }
+
+        assert_eq!(DataSize::U64.min(DataSize::U32), DataSize::U32);
+        assert_eq!(DataSize::U8.max(DataSize::U16), DataSize::U16);
+    }
+
+    #[test]
+    fn decode_legacy_base() {
+        let base = BASE::try_from(0xE00F_F000).unwrap();
+        assert_eq!(base.Format, BaseAddrFormat::Legacy);
+        assert_eq!(base.present, DebugEntryState::Present);
+        assert_eq!(u32::from(base), 0xE00F_F000);
+
+        let absent = BASE::try_from(0xFFFF_FFFF).unwrap();
+        assert_eq!(absent.Format, BaseAddrFormat::Legacy);
+        assert_eq!(absent.present, DebugEntryState::NotPresent);
+        assert_eq!(u32::from(absent), 0xFFFF_FFFF);
+    }
+
+    #[test]
+    fn decode_legacy_base_drops_the_low_bits() {
+        let base = BASE::try_from(0xE00F_F0F1).unwrap();
+        assert_eq!(base.Format, BaseAddrFormat::Legacy);
+        assert_eq!(base.present, DebugEntryState::Present);
+        assert_eq!(base.BASEADDR, 0xE00FF);
+        assert_eq!(u32::from(base), 0xE00F_F000);
+
+        let address = RomTableAddress::from_base(base, None).unwrap();
+        assert_eq!(address.absolute(), 0xE00F_F000);
+    }
+
+    #[test]
+    fn decode_adiv5_base() {
+        let base = BASE::try_from(0xE00F_F003).unwrap();
+        assert_eq!(base.Format, BaseAddrFormat::ADIv5);
+        assert_eq!(base.present, DebugEntryState::Present);
+
+        let absent = BASE::try_from(0xE00F_F002).unwrap();
+        assert_eq!(absent.present, DebugEntryState::NotPresent);
+    }
+
+    #[test]
+    fn rom_table_address_from_base() {
+        let base2 = Some(BASE2 { BASEADDR: 0x1 });
+
+        let legacy = BASE::try_from(0xE00F_F000).unwrap();
+        let address = RomTableAddress::from_base(legacy, base2).unwrap();
+        // BASE2 is ignored for the legacy format.
+        assert_eq!(address.absolute(), 0xE00F_F000);
+        let absent = BASE::try_from(0xFFFF_FFFF).unwrap();
+        assert_eq!(RomTableAddress::from_base(absent, base2), None);
+
+        let adiv5 = BASE::try_from(0xE00F_F003).unwrap();
+        let address = RomTableAddress::from_base(adiv5, base2).unwrap();
+        assert_eq!(address.absolute(), 0x1_E00F_F000);
+        // Without BASE2, the address is below 4 GB.
+        let address = RomTableAddress::from_base(adiv5, None).unwrap();
+        assert_eq!(address.absolute(), 0xE00F_F000);
+        let absent = BASE::try_from(0xE00F_F002).unwrap();
+        assert_eq!(RomTableAddress::from_base(absent, base2), None);
+    }
+
+    #[test]
+    fn tagging_mode_needs_mte() {
+        let csw = CSW::try_from(0x8000_2012).unwrap();
+        assert_eq!(csw.Type, 2);
+        assert_eq!(csw.tagging_mode(), None);
+
+        let csw = CSW::try_from(0x8000_A012).unwrap();
+        assert_eq!(
+            csw.tagging_mode(),
+            Some(TaggingMode::ImplementationDefined(2))
+        );
+
+        let table = TaggingModeTable::DEFAULT
+            .with(0, TaggingMode::Unchecked)
+            .with(2, TaggingMode::Tags);
+        assert_eq!(csw.tagging_mode_with(&table), Some(TaggingMode::Tags));
+        assert_eq!(table.get(0), TaggingMode::Unchecked);
+        assert_eq!(table.get(1), TaggingMode::ImplementationDefined(1));
+    }
+
+    #[test]
+    fn tr_in_progress_from_raw_word() {
+        assert!(CSW::tr_in_progress(0x8000_00D2));
+        assert!(!CSW::tr_in_progress(0x8000_0052));
+        // Still works when other fields hold invalid values.
+        assert!(CSW::tr_in_progress(0x0000_0087));
+    }
+
+    #[test]
+    fn csw_fields_cover_the_register() {
+        let mut covered = 0u32;
+        for field in CSW::FIELDS {
+            assert_eq!(covered & field.mask(), 0, "{} overlaps", field.name);
+            covered |= field.mask();
+        }
+        assert_eq!(covered, u32::MAX);
+    }
+
+    #[test]
+    fn csw_res1_round_trips_at_bit_3() {
+        let raw = 0x8000_0052 | (1 << 3);
+        let csw = CSW::try_from(raw).unwrap();
+        assert_eq!(csw._RES1, 1);
+        assert_eq!(u32::from(csw), raw);
+    }
+
+    #[test]
+    fn parse_errors_chain_to_the_field_error() {
+        use std::error::Error;
+
+        // SIZE = 0b111 is reserved.
+        let error = CSW::try_from(0x8000_0047).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "failed to parse CSW register from 0x80000047"
+        );
+        assert_eq!((error.name(), error.value()), (Some("CSW"), 0x8000_0047));
+        assert_eq!(error.field(), Some("SIZE"));
+        let source = error.source().unwrap();
+        assert_eq!(
+            source.downcast_ref::<InvalidDataSizeError>(),
+            Some(&InvalidDataSizeError { value: 0b111 })
+        );
+    }
+
+    #[test]
+    fn parse_errors_explain_the_invalid_field() {
+        let error = CSW::try_from(0x8000_0047).unwrap_err();
+        assert_eq!(
+            error.explain(),
+            "failed to parse CSW register from 0x80000047\n\
+             1|0000000|0|00|000|0|0|0|000|0000|0|1|00|0|111\n\
+             \x20                                          ^^^ SIZE"
+        );
+
+        // Without a layout, the value is a single group.
+        let error = RegisterParseError::new("CSW", 0x47);
+        assert_eq!(
+            error.explain(),
+            "failed to parse CSW register from 0x00000047\n00000000000000000000000001000111"
+        );
+    }
+
+    #[test]
+    fn field_errors_convert_to_register_parse_errors() {
+        let error = RegisterParseError::from(InvalidAddressIncrement { value: 0b11 });
+        assert_eq!((error.name(), error.value()), (None, 0x30));
+        assert_eq!(
+            error.to_string(),
+            "failed to parse register from 0x00000030"
+        );
+        assert_eq!(error.field(), Some("AddrInc"));
+
+        let error = CSW::try_from(0x8000_0032).unwrap_err();
+        assert_eq!(
+            (error.field(), error.value()),
+            (Some("AddrInc"), 0x8000_0032)
+        );
+    }
+
+    #[test]
+    fn secure_access_is_effective_while_the_device_is_disabled() {
+        let csw = |device_enabled, secure_enabled| CSW {
+            DeviceEn: device_enabled,
+            SDeviceEn: secure_enabled,
+            ..CSW::builder().build()
+        };
+
+        assert!(csw(false, false).effective_secure_access());
+        assert!(csw(false, true).effective_secure_access());
+        assert!(!csw(true, false).effective_secure_access());
+        assert!(csw(true, true).effective_secure_access());
+    }
+
+    #[test]
+    fn lossy_parsing_replaces_invalid_fields() {
+        // SIZE = 0b111 is reserved, the other fields are kept.
+        let (csw, lossy) = CSW::from_raw_lossy(0x8000_0047);
+        assert_eq!(csw.SIZE, DataSize::U32);
+        assert_eq!(csw.AddrInc, AddressIncrement::Off);
+        assert!(csw.DbgSwEnable && csw.DeviceEn);
+        let lossy: Vec<_> = lossy.fields::<CSW>().map(|field| field.name).collect();
+        assert_eq!(lossy, ["SIZE"]);
+
+        let (csw, lossy) = CSW::from_raw_lossy(0x8000_0042);
+        assert_eq!(csw, CSW::try_from(0x8000_0042).unwrap());
+        assert!(lossy.is_empty());
+    }
+
+    #[test]
+    fn hex_debug_shows_the_raw_value() {
+        let tar = TAR { address: 0x1234 };
+        assert_eq!(format!("{tar:?}"), "TAR { address: 4660 }");
+        assert_eq!(
+            format!("{:?}", tar.hex_debug()),
+            "TAR(0x00001234 = 0b00000000000000000001001000110100): { address: 4660 }"
+        );
+        assert_eq!(
+            tar.hex_debug().to_string(),
+            format!("{:?}", tar.hex_debug())
+        );
+        assert_eq!(
+            format!("{:#?}", tar.hex_debug()),
+            "TAR(0x00001234 = 0b00000000000000000001001000110100): {\n    address: 4660,\n}"
+        );
+
+        let csw = CSW::try_from(0x2300_0052).unwrap();
+        assert!(format!("{:?}", csw.hex_debug())
+            .starts_with("CSW(0x23000052 = 0b00100011000000000000000001010010): { "));
+    }
+
+    #[test]
+    fn lossy_fallback_values_parse() {
+        fn parses<R: Register>() {
+            R::try_from(R::LOSSY_FALLBACK).unwrap();
+        }
+        parses::<CSW>();
+        parses::<TAR>();
+        parses::<TAR2>();
+        parses::<DRW>();
+        parses::<BD0>();
+        parses::<T0TR>();
+        parses::<MBT>();
+        parses::<TRR>();
+        parses::<CFG>();
+        parses::<BASE>();
+        parses::<BASE2>();
+        parses::<IDR>();
+    }
+
+    #[test]
+    fn bus_types_are_decoded_for_memory_aps() {
+        let idr = |class: u8, ty: u8| IDR {
+            REVISION: 0,
+            DESIGNER: 0x23B,
+            CLASS: class,
+            VARIANT: 0,
+            TYPE: ty,
+        };
+
+        let known = [
+            (0x1, MemApBusType::AmbaAhb3),
+            (0x2, MemApBusType::AmbaApb2Apb3),
+            (0x4, MemApBusType::AmbaAxi3Axi4),
+            (0x5, MemApBusType::AmbaAhb5),
+            (0x6, MemApBusType::AmbaApb4Apb5),
+            (0x7, MemApBusType::AmbaAxi5),
+            (0x8, MemApBusType::AmbaAhb5Hprot),
+        ];
+        for (ty, bus_type) in known {
+            assert_eq!(MemApBusType::try_from(ty), Ok(bus_type));
+            assert_eq!(idr(IDR::MEM_AP_CLASS, ty).bus_type(), Some(bus_type));
+            // Other classes of APs have no memory bus.
+            assert_eq!(idr(0x0, ty).bus_type(), None);
+        }
+        for ty in [0x0, 0x3, 0x9, 0xF] {
+            assert_eq!(
+                MemApBusType::try_from(ty),
+                Err(InvalidBusTypeError { value: ty })
+            );
+            assert_eq!(idr(IDR::MEM_AP_CLASS, ty).bus_type(), None);
+        }
+        assert!(MemApBusType::AmbaApb4Apb5.is_apb());
+        assert!(MemApBusType::AmbaAxi5.is_axi());
+        assert!(!MemApBusType::AmbaAhb5.is_apb());
+    }
+
+    #[test]
+    fn csw_presets_are_usable_in_const_context() {
+        const PRESETS: [CSW; 5] = [
+            CSW::new_word_burst(),
+            CSW::new_byte_access(),
+            CSW::new_halfword_access(),
+            CSW::new_fifo_write(),
+            CSW::new_packed_bytes(),
+        ];
+        let fields: Vec<_> = PRESETS.iter().map(|csw| (csw.SIZE, csw.AddrInc)).collect();
+        assert_eq!(
+            fields,
+            [
+                (DataSize::U32, AddressIncrement::Single),
+                (DataSize::U8, AddressIncrement::Off),
+                (DataSize::U16, AddressIncrement::Off),
+                (DataSize::U32, AddressIncrement::Off),
+                (DataSize::U8, AddressIncrement::Packed),
+            ]
+        );
+        assert!(PRESETS.iter().all(|csw| csw.DbgSwEnable));
+        assert_eq!(u32::from(CSW::new_word_burst()), 0x8000_0012);
+    }
+
+    #[test]
+    fn csw_validation_rules() {
+        let word_only = MemApCapabilities {
+            sub_word: Some(false),
+            ..MemApCapabilities::default()
+        };
+        let packed = MemApCapabilities {
+            packed: Some(true),
+            ..MemApCapabilities::default()
+        };
+        let csw = |size, increment| CSW::builder().size(size).addr_inc(increment).build();
+
+        assert_eq!(
+            csw(DataSize::U32, AddressIncrement::Packed).validate(&packed),
+            Err(CswValidationError::PackedNeedsSubWord {
+                size: DataSize::U32
+            })
+        );
+        assert_eq!(
+            csw(DataSize::U8, AddressIncrement::Packed).validate(&word_only),
+            Err(CswValidationError::SubWordUnsupported { size: DataSize::U8 })
+        );
+        assert_eq!(
+            csw(DataSize::U16, AddressIncrement::Single).validate(&word_only),
+            Err(CswValidationError::SubWordUnsupported {
+                size: DataSize::U16
+            })
+        );
+        assert_eq!(
+            csw(DataSize::U64, AddressIncrement::Single).validate(&packed),
+            Err(CswValidationError::SizeUnsupported {
+                size: DataSize::U64,
+                max_size: DataSize::U32
+            })
+        );
+        // Word transfers are supported through a narrow bus, so that is the reported limit.
+        let narrow = MemApCapabilities {
+            max_size: DataSize::U8,
+            ..MemApCapabilities::default()
+        };
+        assert_eq!(
+            csw(DataSize::U64, AddressIncrement::Single).validate(&narrow),
+            Err(CswValidationError::SizeUnsupported {
+                size: DataSize::U64,
+                max_size: DataSize::U32
+            })
+        );
+        assert_eq!(
+            csw(DataSize::U32, AddressIncrement::Single).validate(&narrow),
+            Ok(())
+        );
+
+        assert_eq!(
+            csw(DataSize::U8, AddressIncrement::Packed).validate(&packed),
+            Ok(())
+        );
+        assert_eq!(
+            csw(DataSize::U32, AddressIncrement::Off).validate(&word_only),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn diff_reports_only_changed_fields() {
+        let before = CSW::builder().build();
+        let after = CSW::builder()
+            .size(DataSize::U8)
+            .addr_inc(AddressIncrement::Off)
+            .build();
+
+        assert_eq!(
+            diff_fields(before, after),
+            [("AddrInc", 0b01, 0b00), ("SIZE", 0b010, 0b000)]
+        );
+        assert!(diff_fields(before, before).is_empty());
+        assert_eq!(
+            FieldDiff::between(before, after).to_string(),
+            "AddrInc: Single -> Off\nSIZE: U32 -> U8\n"
+        );
+    }
+
+    #[test]
+    fn csw_fields_carry_their_access() {
+        let access = |name| CSW::FIELDS.iter().find(|f| f.name == name).unwrap().access;
+        assert_eq!(access("TrInProg"), FieldAccess::ReadOnly);
+        assert_eq!(access("DeviceEn"), FieldAccess::ReadOnly);
+        assert_eq!(access("SIZE"), FieldAccess::ReadWrite);
+    }
+
+    /// A register with a write-only field, which none of the memory AP registers has.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct Command {
+        start: bool,
+        mode: u8,
+    }
+
+    impl TryFrom<u32> for Command {
+        type Error = RegisterParseError;
+
+        fn try_from(value: u32) -> Result<Self, Self::Error> {
+            Ok(Command {
+                start: value & 1 != 0,
+                mode: (value >> 4) as u8,
+            })
+        }
+    }
+
+    impl From<Command> for u32 {
+        fn from(command: Command) -> u32 {
+            u32::from(command.start) | (u32::from(command.mode) << 4)
+        }
+    }
+
+    impl Register for Command {
+        const ADDRESS: u16 = 0x40;
+        const NAME: &'static str = "Command";
+        const FIELDS: &'static [FieldInfo] = &[
+            FieldInfo {
+                name: "mode",
+                offset: 4,
+                width: 8,
+                access: FieldAccess::ReadWrite,
+            },
+            FieldInfo {
+                name: "start",
+                offset: 0,
+                width: 1,
+                access: FieldAccess::WriteOnly,
+            },
+        ];
+
+        fn format_field(&self, name: &str) -> Option<String> {
+            match name {
+                "start" => Some(format!("{:?}", self.start)),
+                "mode" => Some(format!("{:?}", self.mode)),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn reads_replace_write_only_fields() {
+        // The unknown value read from `start` is not taken over.
+        let command = Command::from_read(0x351).unwrap();
+        assert_eq!(
+            command,
+            Command {
+                start: false,
+                mode: 0x35
+            }
+        );
+        assert!(Command::try_from(0x351).unwrap().start);
+
+        let mut mock = MockMemoryAp::with_pattern(0x2000);
+        mock.set_register(Command::ADDRESS, 0x351);
+        let command: Command = read_register(&mut mock, 0x2000).unwrap();
+        assert!(!command.start);
+    }
+
+    #[test]
+    fn field_info_extracts_field() {
+        let designer = IDR::FIELDS.iter().find(|f| f.name == "DESIGNER").unwrap();
+        assert_eq!(designer.extract((0x23B << 17) | 0x04), 0x23B);
+    }
+
+    #[test]
+    fn csw_builder_starts_from_architectural_default() {
+        assert_eq!(u32::from(CSW::builder().build()), 0x8000_0012);
+
+        let csw = CSW::builder()
+            .size(DataSize::U8)
+            .addr_inc(AddressIncrement::Off)
+            .prot(0x23)
+            .mode(0x1)
+            .dbg_sw_enable(false)
+            .build();
+        assert_eq!(u32::from(csw), 0x2300_0100);
+    }
+
+    #[test]
+    fn data_size_converts_to_and_from_byte_count() {
+        for count in [1usize, 2, 4, 8, 16, 32] {
+            let size = DataSize::try_from(count).unwrap();
+            assert_eq!(usize::from(size), count);
+            assert_eq!(DataSize::from_byte_count(count), Ok(size));
+        }
+        assert!(DataSize::try_from(3usize).is_err());
+    }
+
+    #[test]
+    fn base_address_must_fit_in_20_bits() {
+        let base = BASE::new(0xF_FFFF, BaseAddrFormat::ADIv5, DebugEntryState::Present).unwrap();
+        assert_eq!(u32::from(base), 0xFFFF_F003);
+
+        let error =
+            BASE::new(0x10_0001, BaseAddrFormat::ADIv5, DebugEntryState::Present).unwrap_err();
+        assert_eq!(error.name(), Some("BASE"));
+        assert_eq!(error.field(), Some("BASEADDR"));
+        assert_eq!(error.value(), 0x0000_1003);
+
+        // Bits of a manually constructed value beyond the 20 bit field are dropped.
+        let base = BASE {
+            BASEADDR: 0x10_0001,
+            ..base
+        };
+        assert_eq!(u32::from(base), 0x0000_1003);
+    }
+
+    #[test]
+    fn csw_parse_error_reports_invalid_data_size() {
+        use std::error::Error;
+
+        let error = CSW::try_from(0x8000_0016).unwrap_err();
+        let source = error.source().unwrap();
+        assert_eq!(
+            source.downcast_ref::<InvalidDataSizeError>(),
+            Some(&InvalidDataSizeError { value: 0b110 })
+        );
+        assert_eq!(source.to_string(), "Invalid DataSize 0b110.");
+    }
+
+    #[test]
+    fn csw_parse_error_reports_invalid_address_increment() {
+        use std::error::Error;
+
+        assert_eq!(
+            AddressIncrement::try_from(0b11),
+            Err(InvalidAddressIncrement { value: 0b11 })
+        );
+
+        let error = CSW::try_from(0x8000_0032).unwrap_err();
+        let source = error.source().unwrap();
+        assert_eq!(
+            source.downcast_ref::<InvalidAddressIncrement>(),
+            Some(&InvalidAddressIncrement { value: 0b11 })
+        );
+        assert_eq!(source.to_string(), "Invalid AddressIncrement 0b11.");
+    }
+
+    #[test]
+    fn default_is_the_reset_value() {
+        assert_eq!(CSW::default(), CSW::builder().build());
+        assert_eq!(u32::from(CSW::default()), 0x8000_0012);
+        assert_eq!(CSW::default().SIZE, DataSize::U32);
+        assert_eq!(u32::from(TAR::default()), 0);
+        assert_eq!(u32::from(DRW::default()), 0);
+    }
+
+    #[test]
+    fn equal_registers_hash_equally() {
+        let mut configurations = HashSet::new();
+        configurations.insert(CSW::default());
+        configurations.insert(CSW::builder().build());
+        configurations.insert(CSW::builder().size(DataSize::U8).build());
+
+        assert_eq!(configurations.len(), 2);
+        assert!(configurations.contains(&CSW::try_from(0x8000_0012).unwrap()));
+    }
+
+    #[test]
+    fn decode_adiv6_cfg() {
+        // 4 KB TAR incrementer, error control, 1 KB DAR space, RME and large address extension.
+        let cfg = CFG::try_from(0x0003_1A0A).unwrap();
+        assert_eq!(cfg.TARINC, 3);
+        assert_eq!(cfg.auto_increment_block_size(), 4096);
+        assert!(cfg.supports_error_control());
+        assert_eq!(cfg.dar_size(), Some(1024));
+        assert!(cfg.RME);
+        assert!(!cfg.LD);
+        assert!(cfg.LA);
+        assert!(!cfg.BE);
+        assert_eq!(u32::from(cfg), 0x0003_1A0A);
+
+        let cfg = CFG::try_from(0).unwrap();
+        assert_eq!(cfg.auto_increment_block_size(), 1024);
+        assert!(!cfg.supports_error_control());
+        assert_eq!(cfg.dar_size(), None);
+    }
+
+    #[test]
+    fn field_bits_match_csw_layout() {
+        const PRESET: u32 = AddressIncrement::Single.bits() | DataSize::U16.bits();
+        assert_eq!(PRESET, 0x11);
+
+        let csw = CSW::try_from(0x8000_0000 | PRESET).unwrap();
+        assert_eq!(csw.AddrInc, AddressIncrement::Single);
+        assert_eq!(csw.SIZE, DataSize::U16);
+        assert_eq!(u32::from(csw) & DataSize::MASK, DataSize::U16.bits());
+        assert_eq!(
+            u32::from(csw) & AddressIncrement::MASK,
+            AddressIncrement::Single.bits()
+        );
+    }
+
+    #[test]
+    fn transfer_address_round_trips_through_tar_and_tar2() {
+        for address in [
+            0,
+            0x2000_0004,
+            0xFFFF_FFFC,
+            0x1_0000_0000,
+            0xFFFF_FFFF_FFFF_FFFC,
+        ] {
+            let (tar, tar2) = TransferAddress(address).split();
+            assert_eq!(tar2.is_some(), address > 0xFFFF_FFFF, "{address:#x}");
+            assert_eq!(
+                TransferAddress::from_parts(tar, tar2),
+                TransferAddress(address)
+            );
+        }
+        assert_eq!(
+            TransferAddress::from_parts(TAR { address: 4 }, Some(TAR2 { address: 0 })),
+            TransferAddress(4)
+        );
+    }
+
+    #[test]
+    fn transfer_address_above_4gb_needs_large_addresses() {
+        let mut cfg = CFG::try_from(0).unwrap();
+        assert!(TransferAddress::new(0xFFFF_FFFF, &cfg).is_ok());
+        assert!(TransferAddress::new(0x1_0000_0000, &cfg).is_err());
+
+        cfg.LA = true;
+        assert!(TransferAddress::new(0x1_0000_0000, &cfg).is_ok());
+    }
+
+    #[test]
+    fn layouts_decode_raw_values() {
+        const LAYOUT: RegisterLayout = CSW::LAYOUT;
+        assert_eq!(LAYOUT, RegisterLayout::of::<CSW>());
+        assert_eq!(LAYOUT.name, "CSW");
+        assert_eq!(LAYOUT.address, 0xD00);
+
+        let fields: Vec<_> = LAYOUT.decode(0x8000_0052).collect();
+        assert_eq!(fields.len(), CSW::FIELDS.len());
+        assert!(fields.contains(&("DbgSwEnable", 1)));
+        assert!(fields.contains(&("DeviceEn", 1)));
+        assert!(fields.contains(&("AddrInc", 1)));
+        assert!(fields.contains(&("SIZE", 2)));
+        assert_eq!(
+            LAYOUT.field("Prot").map(|field| field.mask()),
+            Some(0x7F00_0000)
+        );
+        assert_eq!(LAYOUT.field("NotAField"), None);
+    }
+
+    #[test]
+    fn comparisons_ignore_reserved_and_read_only_bits() {
+        let csw = CSW::try_from(0x8000_0052).unwrap();
+        // _RES0, _RES1 and the read-only DeviceEn differ.
+        let other = CSW::try_from(0x801C_001A).unwrap();
+        assert_ne!(csw, other);
+        assert!(csw.eq_ignoring(&other, 0));
+
+        let prot = CSW { Prot: 0x23, ..csw };
+        assert!(!csw.eq_ignoring(&prot, 0));
+        assert!(csw.eq_ignoring(&prot, 0x7F00_0000));
+        assert_eq!(CSW::significant_mask(), !0x00FC_00C8);
+        assert_eq!(TAR::significant_mask(), u32::MAX);
+    }
+}
@@ -0,0 +1,597 @@
+//! Discovery of the complete CoreSight topology behind an ADIv6 debug port.
+
+use std::{collections::BTreeSet, fmt};
+
+use crate::architecture::arm::{
+    communication_interface::Initialized,
+    dp::DpAddress,
+    memory::{
+        romtable::{Component, PeripheralID, RomTable, CORESIGHT_ROM_TABLE_ARCHID},
+        ArmMemoryInterface, PeripheralType,
+    },
+    ApV2Address, ArmCommunicationInterface, ArmError,
+};
+
+use super::{MemoryAccessPortInterface, RootMemoryInterface};
+
+/// The identification of a CoreSight component.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentIdentity {
+    /// The component class from the CIDR1 register.
+    pub class: u8,
+    /// The peripheral ID from the PIDR, DEVTYPE and DEVARCH registers.
+    pub peripheral_id: PeripheralID,
+}
+
+impl ComponentIdentity {
+    /// Returns the name of the part, if it is known.
+    pub fn name(&self) -> Option<&'static str> {
+        self.peripheral_id.determine_part().map(|part| part.name())
+    }
+}
+
+/// What a [`TopologyNode`] is.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TopologyNodeKind {
+    /// A ROM table. Its entries are the children of the node.
+    RomTable(ComponentIdentity),
+    /// A memory access port. The components in its address space are the children of the node.
+    MemoryAp(ComponentIdentity),
+    /// Any other component.
+    Component(ComponentIdentity),
+    /// An entry of a class 0x9 ROM table pointing back to a ROM table which is already being
+    /// walked. Such entries of class 0x1 ROM tables are skipped, see [`RomTable::try_parse`].
+    Cycle,
+}
+
+impl TopologyNodeKind {
+    /// The identification of the component, unless this is a [`TopologyNodeKind::Cycle`].
+    pub fn identity(&self) -> Option<&ComponentIdentity> {
+        match self {
+            TopologyNodeKind::RomTable(id)
+            | TopologyNodeKind::MemoryAp(id)
+            | TopologyNodeKind::Component(id) => Some(id),
+            TopologyNodeKind::Cycle => None,
+        }
+    }
+}
+
+/// A component of the CoreSight topology and the components below it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopologyNode {
+    /// The base address of the component in the address space of its parent.
+    pub address: u64,
+    /// What the component is.
+    pub kind: TopologyNodeKind,
+    /// The entries of a ROM table, or the components behind a memory access port.
+    pub children: Vec<TopologyNode>,
+}
+
+impl TopologyNode {
+    /// The identification of the component, unless the node is a [`TopologyNodeKind::Cycle`].
+    pub fn identity(&self) -> Option<&ComponentIdentity> {
+        self.kind.identity()
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        write!(
+            f,
+            "{:indent$}{:#010x} ",
+            "",
+            self.address,
+            indent = depth * 2
+        )?;
+        match &self.kind {
+            TopologyNodeKind::RomTable(_) => writeln!(f, "ROM table")?,
+            TopologyNodeKind::MemoryAp(id) | TopologyNodeKind::Component(id) => match id.name() {
+                Some(name) => writeln!(f, "{name}")?,
+                None => writeln!(
+                    f,
+                    "Unknown component (class {:#x}, part {:#05x})",
+                    id.class,
+                    id.peripheral_id.part()
+                )?,
+            },
+            TopologyNodeKind::Cycle => writeln!(f, "(cycle)")?,
+        }
+        for child in &self.children {
+            child.fmt_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// The CoreSight topology behind a debug port, rooted at the debug port's ROM table.
+///
+/// The [`Display`](fmt::Display) implementation prints the topology as an indented tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoreSightTopology {
+    /// The component the debug port's BASEPTR points to.
+    pub root: TopologyNode,
+}
+
+impl CoreSightTopology {
+    /// Iterates over all nodes of the topology, see [`TopologyIter`].
+    pub fn iter(&self) -> TopologyIter<'_> {
+        TopologyIter {
+            stack: vec![(0, ApV2Address::root(), &self.root)],
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a CoreSightTopology {
+    type Item = TopologyComponent<'a>;
+    type IntoIter = TopologyIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for CoreSightTopology {
+    type Item = OwnedTopologyComponent;
+    type IntoIter = TopologyIntoIter;
+
+    /// Iterates over all nodes of the topology in the order of [`CoreSightTopology::iter`],
+    /// taking them apart.
+    fn into_iter(self) -> Self::IntoIter {
+        TopologyIntoIter {
+            stack: vec![(0, ApV2Address::root(), self.root)],
+        }
+    }
+}
+
+/// Returns the memory access ports the children of `node` are behind, given the ones `node` is
+/// behind.
+fn child_ap(ap: &ApV2Address, node: &TopologyNode) -> ApV2Address {
+    match node.kind {
+        TopologyNodeKind::MemoryAp(_) => ap.clone().append(node.address),
+        _ => ap.clone(),
+    }
+}
+
+/// A node of a [`CoreSightTopology`] visited by a [`TopologyIter`].
+#[derive(Debug, Clone)]
+pub struct TopologyComponent<'a> {
+    /// The node, including its children.
+    pub node: &'a TopologyNode,
+    /// The memory access ports the component is behind, from the debug port down. Empty for the
+    /// components in the address space of the debug port.
+    pub ap: ApV2Address,
+    /// The number of nodes above this one, zero for the root.
+    pub depth: usize,
+}
+
+impl<'a> TopologyComponent<'a> {
+    /// The absolute base address of the component in the address space of the memory access
+    /// port [`TopologyComponent::ap`] ends with, or of the debug port if it is empty.
+    pub fn address(&self) -> u64 {
+        self.node.address
+    }
+
+    /// The identification of the component, see [`TopologyNode::identity`].
+    pub fn identity(&self) -> Option<&'a ComponentIdentity> {
+        self.node.identity()
+    }
+
+    /// Returns the name of the part, if it is known.
+    pub fn part_name(&self) -> Option<&'static str> {
+        self.identity().and_then(ComponentIdentity::name)
+    }
+}
+
+/// Iterates over the nodes of a [`CoreSightTopology`] in pre-order.
+///
+/// Every node is visited before its children, and the children in the order of their ROM table
+/// entries, so the order matches the [`Display`](fmt::Display) output of the topology.
+#[derive(Debug, Clone)]
+pub struct TopologyIter<'a> {
+    stack: Vec<(usize, ApV2Address, &'a TopologyNode)>,
+}
+
+impl<'a> Iterator for TopologyIter<'a> {
+    type Item = TopologyComponent<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (depth, ap, node) = self.stack.pop()?;
+        let children_ap = child_ap(&ap, node);
+        self.stack.extend(
+            node.children
+                .iter()
+                .rev()
+                .map(|child| (depth + 1, children_ap.clone(), child)),
+        );
+        Some(TopologyComponent { node, ap, depth })
+    }
+}
+
+/// A node of a [`CoreSightTopology`] visited by a [`TopologyIntoIter`], without its children,
+/// which are visited on their own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnedTopologyComponent {
+    /// The absolute base address of the component, see [`TopologyComponent::address`].
+    pub address: u64,
+    /// What the component is.
+    pub kind: TopologyNodeKind,
+    /// The memory access ports the component is behind, see [`TopologyComponent::ap`].
+    pub ap: ApV2Address,
+    /// The number of nodes above this one, zero for the root.
+    pub depth: usize,
+}
+
+impl OwnedTopologyComponent {
+    /// The identification of the component, see [`TopologyNode::identity`].
+    pub fn identity(&self) -> Option<&ComponentIdentity> {
+        self.kind.identity()
+    }
+
+    /// Returns the name of the part, if it is known.
+    pub fn part_name(&self) -> Option<&'static str> {
+        self.identity().and_then(ComponentIdentity::name)
+    }
+}
+
+/// Iterates over the nodes of an owned [`CoreSightTopology`] in pre-order, like
+/// [`TopologyIter`].
+#[derive(Debug, Clone)]
+pub struct TopologyIntoIter {
+    stack: Vec<(usize, ApV2Address, TopologyNode)>,
+}
+
+impl Iterator for TopologyIntoIter {
+    type Item = OwnedTopologyComponent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (depth, ap, node) = self.stack.pop()?;
+        let children_ap = child_ap(&ap, &node);
+        self.stack.extend(
+            node.children
+                .into_iter()
+                .rev()
+                .map(|child| (depth + 1, children_ap.clone(), child)),
+        );
+        Some(OwnedTopologyComponent {
+            address: node.address,
+            kind: node.kind,
+            ap,
+            depth,
+        })
+    }
+}
+
+impl fmt::Display for CoreSightTopology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.root.fmt_indented(f, 0)
+    }
+}
+
+/// Walks all ROM tables reachable from the debug port `dp`, including those behind nested memory
+/// access ports, and returns the discovered components as a tree.
+///
+/// With the `ap-tracing` feature, the walk is traced with a `tracing` span for every memory
+/// access port and every component, nested like the topology, so a stalled or failed discovery
+/// can be attributed to the component being read. Failures are reported as an error event
+/// within the failing span.
+#[cfg_attr(
+    feature = "ap-tracing",
+    tracing::instrument(level = "debug", skip(probe), err(Display))
+)]
+pub fn discover_topology(
+    probe: &mut ArmCommunicationInterface<Initialized>,
+    dp: DpAddress,
+) -> Result<CoreSightTopology, ArmError> {
+    let mut root_ap = RootMemoryInterface::new(probe, dp)?;
+    let base_addr = root_ap.base_address()?;
+    discover(&mut root_ap, base_addr)
+}
+
+/// Discovers the topology rooted at the component at `base_addr` in `iface`.
+fn discover(
+    iface: &mut dyn ArmMemoryInterface,
+    base_addr: u64,
+) -> Result<CoreSightTopology, ArmError> {
+    let component = Component::try_parse(iface, base_addr)?;
+    let root = walk(iface, &component, &mut BTreeSet::new())?;
+    Ok(CoreSightTopology { root })
+}
+
+/// Walks the address space of the memory access port `node` in `iface`.
+#[cfg_attr(
+    feature = "ap-tracing",
+    tracing::instrument(
+        level = "debug",
+        name = "memory_ap",
+        skip_all,
+        fields(address = format_args!("{:#x}", node.address), base = tracing::field::Empty),
+        err(Display)
+    )
+)]
+fn walk_memory_ap(
+    iface: &mut dyn ArmMemoryInterface,
+    node: &mut TopologyNode,
+) -> Result<(), ArmError> {
+    let mut subiface = MemoryAccessPortInterface::new_with_ref(iface, node.address)?;
+    // An AP without a debug entry has nothing to walk behind it.
+    let Some(rom_table) = subiface.rom_table_address()? else {
+        tracing::debug!("The memory AP has no debug entry, skipping it");
+        return Ok(());
+    };
+    let base_addr = rom_table.absolute();
+    #[cfg(feature = "ap-tracing")]
+    tracing::Span::current().record("base", format_args!("{base_addr:#x}"));
+
+    let component = Component::try_parse(&mut subiface, base_addr)?;
+    let child = walk(&mut subiface, &component, &mut BTreeSet::new())?;
+    node.children.push(child);
+    Ok(())
+}
+
+/// Turns the parsed `component` into a node and walks what is below it: the entries of a ROM
+/// table, or the address space of a memory access port.
+///
+/// Class 0x1 ROM tables are parsed together with their entries by [`Component::try_parse`],
+/// and class 0x9 ROM tables with [`RomTable::try_parse`]. `visited` holds the ROM tables
+/// currently being walked, so that an entry referencing one of them ends in a
+/// [`TopologyNodeKind::Cycle`] node.
+#[cfg_attr(
+    feature = "ap-tracing",
+    tracing::instrument(
+        level = "debug",
+        name = "component",
+        skip_all,
+        fields(
+            address = format_args!("{:#x}", component.id().component_address()),
+            class = tracing::field::Empty,
+            part = tracing::field::Empty,
+            arch_id = tracing::field::Empty,
+            designer = tracing::field::Empty,
+        ),
+        err(Display)
+    )
+)]
+fn walk(
+    iface: &mut dyn ArmMemoryInterface,
+    component: &Component,
+    visited: &mut BTreeSet<u64>,
+) -> Result<TopologyNode, ArmError> {
+    let address = component.id().component_address();
+    if visited.contains(&address) {
+        tracing::warn!("ROM table at {address:#x} is referenced by one of its own entries.");
+        return Ok(TopologyNode {
+            address,
+            kind: TopologyNodeKind::Cycle,
+            children: vec![],
+        });
+    }
+
+    let id = ComponentIdentity {
+        class: component_class(component),
+        peripheral_id: component.id().peripheral_id().clone(),
+    };
+    #[cfg(feature = "ap-tracing")]
+    {
+        let span = tracing::Span::current();
+        span.record("class", format_args!("{:#x}", id.class));
+        span.record("part", format_args!("{:#05x}", id.peripheral_id.part()));
+        span.record(
+            "arch_id",
+            format_args!("{:#06x}", id.peripheral_id.arch_id()),
+        );
+        if let Some(designer) = id.peripheral_id.designer() {
+            span.record("designer", designer);
+        }
+    }
+
+    let mut node = TopologyNode {
+        address,
+        kind: TopologyNodeKind::Component(id.clone()),
+        children: vec![],
+    };
+
+    match component {
+        Component::Class1RomTable(_, rom_table) => {
+            visited.insert(address);
+            for entry in rom_table.entries() {
+                node.children.push(walk(iface, entry.component(), visited)?);
+            }
+            visited.remove(&address);
+            node.kind = TopologyNodeKind::RomTable(id);
+        }
+        Component::CoresightComponent(_)
+            if id.peripheral_id.arch_id() == CORESIGHT_ROM_TABLE_ARCHID =>
+        {
+            visited.insert(address);
+            let rom_table = RomTable::try_parse(iface, address)?;
+            for entry in rom_table.entries() {
+                node.children.push(walk(iface, entry.component(), visited)?);
+            }
+            visited.remove(&address);
+            node.kind = TopologyNodeKind::RomTable(id);
+        }
+        Component::CoresightComponent(_) if id.peripheral_id.is_of_type(PeripheralType::MemAp) => {
+            walk_memory_ap(iface, &mut node)?;
+            node.kind = TopologyNodeKind::MemoryAp(id);
+        }
+        _ => {}
+    }
+
+    Ok(node)
+}
+
+/// The component class of `component`, as read from CIDR1.
+fn component_class(component: &Component) -> u8 {
+    match component {
+        Component::GenericVerificationComponent(_) => 0x0,
+        Component::Class1RomTable(..) => 0x1,
+        Component::CoresightComponent(_) => 0x9,
+        Component::PeripheralTestBlock(_) => 0xB,
+        Component::GenericIPComponent(_) => 0xE,
+        Component::CoreLinkOrPrimeCellOrSystemComponent(_) => 0xF,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{discover, CoreSightTopology, TopologyNode, TopologyNodeKind};
+    use crate::architecture::arm::memory::sparse::SparseMemory;
+
+    /// PIDR0..=PIDR4 of the Cortex-M4 SCS, designed by ARM Ltd.
+    const CORTEX_M4_SCS: [u32; 5] = [0x0C, 0xB0, 0x0B, 0x00, 0x04];
+    /// PIDR0..=PIDR4 of an architected component designed by ARM Ltd, identified by DEVARCH.
+    const ARM_ARCHITECTED: [u32; 5] = [0x00, 0xB0, 0x0B, 0x00, 0x04];
+
+    /// A class 0x9 ROM table at 0x0 with a memory AP without a debug entry at 0x2000, a
+    /// class 0x1 ROM table at 0xE00F_F000 holding an SCS, and an entry pointing back to itself,
+    /// which is skipped when parsing the table.
+    ///
+    /// Apart from that, a class 0x9 ROM table at 0x1000_0000 references a class 0x9 ROM table at
+    /// 0x1000_1000, which references a class 0x1 ROM table at 0x1000_2000 whose entry points back
+    /// to 0x1000_0000.
+    fn topology_memory() -> SparseMemory {
+        let mut memory = SparseMemory::default();
+        memory.component(0x0, 0x9, ARM_ARCHITECTED);
+        memory.devarch(0x0, 0x0AF7);
+        memory.0.insert(0x0, 0x0000_2003);
+        memory.0.insert(0x4, 0xE00F_F003);
+        memory.0.insert(0x8, 0x0000_0003);
+
+        memory.component(0x2000, 0x9, ARM_ARCHITECTED);
+        memory.devarch(0x2000, 0x0A17);
+        // BASE: ADIv5 format, no debug entry present.
+        memory.0.insert(0x2DF8, 0x0000_0002);
+
+        memory.component(0xE00F_F000, 0x1, [0; 5]);
+        // SCS at 0xE000_E000, an entry pointing to the table itself, and the terminator.
+        memory.0.insert(0xE00F_F000, 0xFFF0_F003);
+        memory.0.insert(0xE00F_F004, 0x0000_0003);
+        memory.component(0xE000_E000, 0x9, CORTEX_M4_SCS);
+
+        memory.component(0x1000_0000, 0x9, ARM_ARCHITECTED);
+        memory.devarch(0x1000_0000, 0x0AF7);
+        memory.0.insert(0x1000_0000, 0x0000_1003);
+        memory.component(0x1000_1000, 0x9, ARM_ARCHITECTED);
+        memory.devarch(0x1000_1000, 0x0AF7);
+        memory.0.insert(0x1000_1000, 0x0000_1003);
+        memory.component(0x1000_2000, 0x1, [0; 5]);
+        memory.0.insert(0x1000_2000, 0xFFFF_E003);
+        memory
+    }
+
+    #[test]
+    fn discover_topology_walks_nested_rom_tables() {
+        let topology = discover(&mut topology_memory(), 0x0).unwrap();
+        let root = &topology.root;
+
+        assert!(matches!(root.kind, TopologyNodeKind::RomTable(_)));
+        assert_eq!(root.children.len(), 2);
+
+        // The memory AP has no debug entry, so nothing is below it.
+        let ap = &root.children[0];
+        assert_eq!(ap.address, 0x2000);
+        assert!(matches!(ap.kind, TopologyNodeKind::MemoryAp(_)));
+        assert!(ap.children.is_empty());
+
+        // The class 0x1 table skips its entry pointing to itself.
+        let table = &root.children[1];
+        assert!(matches!(table.kind, TopologyNodeKind::RomTable(_)));
+        assert_eq!(table.identity().unwrap().class, 0x1);
+        assert_eq!(table.children.len(), 1);
+        assert_eq!(table.children[0].address, 0xE000_E000);
+
+        assert_eq!(
+            topology.to_string(),
+            "0x00000000 ROM table\n  \
+             0x00002000 Memory Access Port v2\n  \
+             0xe00ff000 ROM table\n    \
+             0xe000e000 Cortex-M4 SCS\n"
+        );
+    }
+
+    #[test]
+    fn discover_topology_stops_at_cycles_through_both_table_classes() {
+        let topology = discover(&mut topology_memory(), 0x1000_0000).unwrap();
+
+        assert_eq!(
+            topology.to_string(),
+            "0x10000000 ROM table\n  \
+             0x10001000 ROM table\n    \
+             0x10002000 ROM table\n      \
+             0x10000000 (cycle)\n"
+        );
+        let table = &topology.root.children[0].children[0];
+        assert_eq!(table.identity().unwrap().class, 0x1);
+        assert_eq!(table.children[0].kind, TopologyNodeKind::Cycle);
+    }
+
+    #[test]
+    fn iteration_is_pre_order() {
+        let table = discover(&mut topology_memory(), 0xE00F_F000).unwrap().root;
+
+        let ap = TopologyNode {
+            address: 0x1000,
+            kind: TopologyNodeKind::MemoryAp(table.identity().unwrap().clone()),
+            children: vec![table.clone()],
+        };
+        let topology = CoreSightTopology {
+            root: TopologyNode {
+                address: 0x0,
+                kind: table.kind.clone(),
+                children: vec![
+                    ap,
+                    table.children[0].clone(),
+                    TopologyNode {
+                        address: 0x0,
+                        kind: TopologyNodeKind::Cycle,
+                        children: vec![],
+                    },
+                ],
+            },
+        };
+
+        let visited: Vec<_> = topology
+            .iter()
+            .map(|component| (component.address(), component.depth))
+            .collect();
+        assert_eq!(
+            visited,
+            [
+                (0x0, 0),
+                (0x1000, 1),
+                (0xE00F_F000, 2),
+                (0xE000_E000, 3),
+                (0xE000_E000, 1),
+                (0x0, 1),
+            ]
+        );
+
+        let scs = (&topology)
+            .into_iter()
+            .find(|component| component.part_name() == Some("Cortex-M4 SCS"))
+            .unwrap();
+        // The first SCS in pre-order is the one behind the memory AP.
+        assert_eq!((scs.address(), scs.depth), (0xE000_E000, 3));
+        assert!(topology
+            .iter()
+            .any(|component| component.identity().is_none()));
+
+        // The components behind the memory AP are accessed through it.
+        let behind: Vec<_> = topology
+            .iter()
+            .filter(|component| component.ap.as_slice() == [0x1000])
+            .map(|component| component.address())
+            .collect();
+        assert_eq!(behind, [0xE00F_F000, 0xE000_E000]);
+
+        // Taking the topology apart visits the same components.
+        let owned: Vec<_> = topology
+            .clone()
+            .into_iter()
+            .map(|component| (component.address, component.ap, component.depth))
+            .collect();
+        let borrowed: Vec<_> = topology
+            .iter()
+            .map(|component| (component.address(), component.ap, component.depth))
+            .collect();
+        assert_eq!(owned, borrowed);
+    }
+}
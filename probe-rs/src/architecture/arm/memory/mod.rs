@@ -2,6 +2,8 @@
 
 mod adi_v5_memory_interface;
 pub mod romtable;
+#[cfg(test)]
+pub(crate) mod sparse;
 
 pub(crate) use adi_v5_memory_interface::ADIMemoryInterface;
 
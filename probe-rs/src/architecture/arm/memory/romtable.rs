@@ -100,8 +100,7 @@ impl Iterator for RomTableIterator<'_, '_, '_> {
             return None;
         }
 
-        let entry_data =
-            RomTableEntryRaw::new(self.rom_table_reader.base_address as u32, entry_data);
+        let entry_data = RomTableEntryRaw::from(entry_data);
 
         tracing::debug!("ROM Table Entry: {:#x?}", entry_data);
         Some(Ok(entry_data))
@@ -121,9 +120,23 @@ impl RomTable {
     ///
     /// This does not check whether the data actually signalizes
     /// to contain a ROM table but assumes this was checked beforehand.
+    ///
+    /// Nested class 0x1 ROM tables are parsed as well. An entry pointing back to a table which
+    /// is already being parsed, including the one at `base_address`, as some buggy ROM tables
+    /// do, is skipped.
     pub fn try_parse(
         memory: &mut dyn ArmMemoryInterface,
         base_address: u64,
+    ) -> Result<RomTable, RomTableError> {
+        Self::parse(memory, base_address, &mut vec![base_address])
+    }
+
+    /// Parses the ROM table at `base_address`, which is nested in the class 0x1 ROM tables at
+    /// `ancestors`.
+    fn parse(
+        memory: &mut dyn ArmMemoryInterface,
+        base_address: u64,
+        ancestors: &mut Vec<u64>,
     ) -> Result<RomTable, RomTableError> {
         // This is required for the collect down below.
         let mut entries = vec![];
@@ -140,12 +153,20 @@ impl RomTable {
 
         // Iterate all entries and get their data.
         for (i, raw_entry) in reader.into_iter().enumerate() {
-            let entry_base_addr = raw_entry.component_address();
+            let entry_base_addr = raw_entry.component_address(base_address);
 
             tracing::debug!("Parsing entry at {:#010x}", entry_base_addr);
 
-            if raw_entry.entry_present {
-                let component = Component::try_parse(memory, u64::from(entry_base_addr))?;
+            if !raw_entry.entry_present {
+                tracing::debug!("Entry #{} is not present, skipping.", i);
+            } else if ancestors.contains(&entry_base_addr) {
+                tracing::warn!(
+                    "Entry #{} points back to the ROM table at {:#010x}, skipping.",
+                    i,
+                    entry_base_addr
+                );
+            } else {
+                let component = Component::parse(memory, entry_base_addr, ancestors)?;
 
                 // Finally remember the entry.
                 entries.push(RomTableEntry {
@@ -154,8 +175,6 @@ impl RomTable {
                     power_domain_valid: raw_entry.power_domain_valid,
                     component: CoresightComponent::new(component, memory.fully_qualified_address()),
                 });
-            } else {
-                tracing::debug!("Entry #{} is not present, skipping.", i);
             }
         }
 
@@ -168,58 +187,56 @@ impl RomTable {
     }
 }
 
-/// A ROM table entry with raw information parsed.
+/// A ROM table entry word with raw information parsed.
 ///
 /// Described in section D3.4.4 of the ADIv5.2 specification.
 ///
-/// This should only be used for parsing the raw memory structures of the entry.
-/// Don't use this in the public API.
-///
-/// For advanced usages, see [RomTableEntry].
-#[derive(Debug, PartialEq)]
-struct RomTableEntryRaw {
-    /// The offset from the BASEADDR at which the CoreSight component
-    /// behind this ROM table entry is located.
-    address_offset: i32,
+/// This is the decoded entry word as read from memory, e.g. to follow the entries of a ROM table
+/// without parsing the components behind them. For the entries of a parsed [`RomTable`], see
+/// [`RomTableEntry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RomTableEntryRaw {
+    /// The signed offset from the base address of the ROM table at which the CoreSight
+    /// component behind this ROM table entry is located, in bytes.
+    ///
+    /// This is the signed 20 bit `OFFSET` field, shifted left by 12 bits.
+    pub address_offset: i32,
     /// The power domain ID of the CoreSight component behind the ROM table entry.
-    power_domain_id: u8,
+    pub power_domain_id: u8,
     /// The power domain is valid if this is true.
-    power_domain_valid: bool,
+    pub power_domain_valid: bool,
     /// Reads one if the ROM table has 32bit format.
     ///
     /// It is unsure if it can have a RAZ value.
-    format: bool,
+    pub format: bool,
     /// Indicates whether the ROM table behind the address offset is present.
     pub entry_present: bool,
-    // Base address of the rom table
-    base_address: u32,
 }
 
 impl RomTableEntryRaw {
-    /// Create a new RomTableEntryRaw from raw ROM table entry data in memory.
-    fn new(base_address: u32, raw: u32) -> Self {
-        tracing::debug!("Parsing raw rom table entry: {:#07x}", raw);
+    /// Returns the address of the CoreSight component behind a ROM table entry of the ROM table
+    /// at `table_base`.
+    ///
+    /// The address wraps around within the 32 bit address space the ROM table is in, so an entry
+    /// of a table at 0x0 can point to a component at the top of the address space.
+    pub fn component_address(&self, table_base: u64) -> u64 {
+        u64::from((table_base as u32).wrapping_add_signed(self.address_offset))
+    }
+}
 
-        let address_offset = ((raw >> 12) & 0xf_ff_ff) as i32;
-        let power_domain_id = ((raw >> 4) & 0xf) as u8;
-        let power_domain_valid = (raw & 4) == 4;
-        let format = (raw & 2) == 2;
-        let entry_present = (raw & 1) == 1;
+impl From<u32> for RomTableEntryRaw {
+    fn from(raw: u32) -> Self {
+        tracing::debug!("Parsing raw rom table entry: {:#07x}", raw);
 
         RomTableEntryRaw {
-            address_offset,
-            power_domain_id,
-            power_domain_valid,
-            format,
-            entry_present,
-            base_address,
+            // The offset occupies the top 20 bits, so masking keeps its sign.
+            address_offset: (raw & 0xFFFF_F000) as i32,
+            power_domain_id: ((raw >> 4) & 0xf) as u8,
+            power_domain_valid: (raw & 4) == 4,
+            format: (raw & 2) == 2,
+            entry_present: (raw & 1) == 1,
         }
     }
-
-    /// Returns the address of the CoreSight component behind a ROM table entry.
-    pub fn component_address(&self) -> u32 {
-        (i64::from(self.base_address) + (i64::from(self.address_offset << 12))) as u32
-    }
 }
 
 /// A completely finished-parsing romtable entry.
@@ -441,9 +458,21 @@ pub enum Component {
 
 impl Component {
     /// Tries to parse a CoreSight component table.
+    ///
+    /// A class 0x1 ROM table is parsed with its entries, see [`RomTable::try_parse`].
     pub fn try_parse<'probe: 'memory, 'memory>(
         memory: &'memory mut (dyn ArmMemoryInterface + 'probe),
         baseaddr: u64,
+    ) -> Result<Component, RomTableError> {
+        Self::parse(memory, baseaddr, &mut vec![])
+    }
+
+    /// Parses the component at `baseaddr`, which is nested in the class 0x1 ROM tables at
+    /// `ancestors`.
+    fn parse<'probe: 'memory, 'memory>(
+        memory: &'memory mut (dyn ArmMemoryInterface + 'probe),
+        baseaddr: u64,
+        ancestors: &mut Vec<u64>,
     ) -> Result<Component, RomTableError> {
         tracing::debug!("\tReading component data at: {:#010x}", baseaddr);
 
@@ -477,7 +506,10 @@ impl Component {
                 Component::GenericVerificationComponent(component_id)
             }
             RawComponent::RomTable => {
-                let rom_table = RomTable::try_parse(memory, component_id.component_address)?;
+                ancestors.push(component_id.component_address);
+                let rom_table = RomTable::parse(memory, component_id.component_address, ancestors);
+                ancestors.pop();
+                let rom_table = rom_table?;
 
                 Component::Class1RomTable(component_id, rom_table)
             }
@@ -662,7 +694,7 @@ pub struct PeripheralID {
 
 impl PeripheralID {
     /// Extracts the peripheral ID of the CoreSight component table data.
-    fn from_raw(data: &[u32; 8], dev_type: u8, arch_id: u16) -> Self {
+    pub(crate) fn from_raw(data: &[u32; 8], dev_type: u8, arch_id: u16) -> Self {
         let jep106id = (((data[2] & 0x07) << 4) | ((data[1] >> 4) & 0x0F)) as u8;
         let jep106 = jep106::JEP106Code::new((data[4] & 0x0F) as u8, jep106id);
         let legacy = (data[2] & 0x8) > 1;
@@ -895,3 +927,62 @@ impl std::fmt::Display for PeripheralType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{RomTable, RomTableEntryRaw};
+    use crate::architecture::arm::memory::sparse::SparseMemory;
+
+    #[test]
+    fn decode_positive_offset() {
+        let entry = RomTableEntryRaw::from(0x0000_1003);
+
+        assert!(entry.entry_present);
+        assert!(entry.format);
+        assert_eq!(entry.address_offset, 0x1000);
+        assert_eq!(entry.component_address(0xE00F_F000), 0xE010_0000);
+    }
+
+    #[test]
+    fn decode_negative_offset() {
+        let entry = RomTableEntryRaw::from(0xFFF0_F033);
+
+        assert_eq!(entry.address_offset, -0xF_1000);
+        assert_eq!(entry.component_address(0xE00F_F000), 0xE000_E000);
+        assert_eq!(entry.power_domain_id, 3);
+        assert!(!entry.power_domain_valid);
+    }
+
+    #[test]
+    fn component_address_wraps_around() {
+        let entry = RomTableEntryRaw::from(0xE00F_F003);
+
+        assert_eq!(entry.component_address(0x0), 0xE00F_F000);
+    }
+
+    #[test]
+    fn decode_absent_entry() {
+        let entry = RomTableEntryRaw::from(0x0000_0002);
+
+        assert!(!entry.entry_present);
+        assert!(entry.format);
+    }
+
+    #[test]
+    fn self_referencing_table_is_parsed_once() {
+        let mut memory = SparseMemory::default();
+        memory.component(0xE00F_F000, 0x1, [0; 5]);
+        // An SCS at 0xE000_E000, an entry pointing to the table itself, and the terminator.
+        memory.0.insert(0xE00F_F000, 0xFFF0_F003);
+        memory.0.insert(0xE00F_F004, 0x0000_0003);
+        memory.component(0xE000_E000, 0x9, [0x0C, 0xB0, 0x0B, 0x00, 0x04]);
+
+        let table = RomTable::try_parse(&mut memory, 0xE00F_F000).unwrap();
+
+        let addresses: Vec<_> = table
+            .entries()
+            .map(|entry| entry.component().id().component_address())
+            .collect();
+        assert_eq!(addresses, [0xE000_E000]);
+    }
+}
@@ -0,0 +1,120 @@
+//! A sparse memory for testing the parsing of ROM tables and components.
+
+use std::collections::HashMap;
+
+use crate::{
+    architecture::arm::{
+        communication_interface::{ArmProbeInterface, SwdSequence},
+        memory::{ArmMemoryInterface, Status},
+        ApV2Address, ArmError, DapAccess, FullyQualifiedApAddress,
+    },
+    probe::DebugProbeError,
+    MemoryInterface,
+};
+
+/// Sparse memory which reads as zero where nothing was stored.
+#[derive(Default)]
+pub(crate) struct SparseMemory(pub(crate) HashMap<u64, u32>);
+
+impl SparseMemory {
+    /// Stores the CIDRs of a component of class `class` and its PIDRs `pidr` at `address`.
+    pub(crate) fn component(&mut self, address: u64, class: u32, pidr: [u32; 5]) {
+        // CIDR0..=CIDR3 with the preamble around the class.
+        for (i, value) in [0x0D, class << 4, 0x05, 0xB1].iter().enumerate() {
+            self.0.insert(address + 0xFF0 + i as u64 * 4, *value);
+        }
+        for (i, value) in pidr[..4].iter().enumerate() {
+            self.0.insert(address + 0xFE0 + i as u64 * 4, *value);
+        }
+        self.0.insert(address + 0xFD0, pidr[4]);
+    }
+
+    /// Sets DEVARCH of the component at `address` to the present architecture `arch_id`.
+    pub(crate) fn devarch(&mut self, address: u64, arch_id: u32) {
+        self.0.insert(address + 0xFBC, (1 << 20) | arch_id);
+    }
+}
+
+impl MemoryInterface<ArmError> for SparseMemory {
+    fn supports_native_64bit_access(&mut self) -> bool {
+        false
+    }
+
+    fn read_64(&mut self, _address: u64, _data: &mut [u64]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(64))
+    }
+
+    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), ArmError> {
+        for (i, d) in data.iter_mut().enumerate() {
+            *d = self.0.get(&(address + i as u64 * 4)).copied().unwrap_or(0);
+        }
+        Ok(())
+    }
+
+    fn read_16(&mut self, _address: u64, _data: &mut [u16]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(16))
+    }
+
+    fn read_8(&mut self, _address: u64, _data: &mut [u8]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(8))
+    }
+
+    fn write_64(&mut self, _address: u64, _data: &[u64]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(64))
+    }
+
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), ArmError> {
+        for (i, d) in data.iter().enumerate() {
+            self.0.insert(address + i as u64 * 4, *d);
+        }
+        Ok(())
+    }
+
+    fn write_16(&mut self, _address: u64, _data: &[u16]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(16))
+    }
+
+    fn write_8(&mut self, _address: u64, _data: &[u8]) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(8))
+    }
+
+    fn supports_8bit_transfers(&self) -> Result<bool, ArmError> {
+        Ok(false)
+    }
+
+    fn flush(&mut self) -> Result<(), ArmError> {
+        Ok(())
+    }
+}
+
+impl ArmMemoryInterface for SparseMemory {
+    fn fully_qualified_address(&self) -> FullyQualifiedApAddress {
+        FullyQualifiedApAddress::v2_with_default_dp(ApV2Address::root())
+    }
+
+    fn base_address(&mut self) -> Result<u64, ArmError> {
+        Ok(0)
+    }
+
+    fn get_swd_sequence(&mut self) -> Result<&mut dyn SwdSequence, DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "get_swd_sequence",
+        })
+    }
+
+    fn get_arm_probe_interface(&mut self) -> Result<&mut dyn ArmProbeInterface, DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "get_arm_probe_interface",
+        })
+    }
+
+    fn get_dap_access(&mut self) -> Result<&mut dyn DapAccess, DebugProbeError> {
+        Err(DebugProbeError::NotImplemented {
+            function_name: "get_dap_access",
+        })
+    }
+
+    fn generic_status(&mut self) -> Result<Status, ArmError> {
+        Err(ArmError::NotImplemented("generic_status"))
+    }
+}
@@ -32,18 +32,181 @@ pub use communication_interface::{
 pub use swo::{SwoAccess, SwoConfig, SwoMode, SwoReader};
 pub use traits::*;
 
+/// How a field of a register can be accessed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FieldAccess {
+    /// The field can be read and written.
+    #[default]
+    ReadWrite,
+    /// Writes to the field are ignored.
+    ReadOnly,
+    /// Reads of the field return an unknown value, so a read value must not be written back.
+    WriteOnly,
+}
+
+/// The position and access of a field within a register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldInfo {
+    /// The name of the field.
+    pub name: &'static str,
+    /// The bit offset of the least significant bit of the field.
+    pub offset: u8,
+    /// The width of the field in bits.
+    pub width: u8,
+    /// How the field can be accessed.
+    pub access: FieldAccess,
+}
+
+impl FieldInfo {
+    /// Returns whether the field is reserved, which its name starts with `_RES` for.
+    pub fn is_reserved(&self) -> bool {
+        self.name.starts_with("_RES")
+    }
+
+    /// The mask of the field's bits within the register value.
+    pub const fn mask(&self) -> u32 {
+        (u32::MAX >> (32 - self.width as u32)) << self.offset
+    }
+
+    /// Extracts the value of the field from the raw register value `value`.
+    pub const fn extract(&self, value: u32) -> u32 {
+        (value & self.mask()) >> self.offset
+    }
+}
+
 /// A error that occured while parsing a raw register value.
+///
+/// If a field of the register holds an invalid encoding, the error of that field is the
+/// [`source`](std::error::Error::source) of this error.
 #[derive(Debug, thiserror::Error)]
-#[error("Failed to parse register {name} from {value:#010x}")]
+#[error(
+    "failed to parse {}register from {value:#010x}",
+    .name.map_or(String::new(), |name| format!("{name} "))
+)]
 pub struct RegisterParseError {
-    name: &'static str,
+    name: Option<&'static str>,
     value: u32,
+    field: Option<&'static str>,
+    fields: &'static [FieldInfo],
+    #[source]
+    source: Option<Box<dyn std::error::Error + Send + Sync>>,
 }
 
 impl RegisterParseError {
     /// Creates a new instance of error.
     pub fn new(name: &'static str, value: u32) -> Self {
-        RegisterParseError { name, value }
+        RegisterParseError {
+            name: Some(name),
+            value,
+            field: None,
+            fields: &[],
+            source: None,
+        }
+    }
+
+    /// Creates an error for the invalid encoding of `field`, at its position in `value`, before
+    /// the register it occurred in is known, see [`RegisterParseError::in_register`].
+    pub fn of_field(field: &'static str, value: u32) -> Self {
+        RegisterParseError {
+            name: None,
+            value,
+            field: Some(field),
+            fields: &[],
+            source: None,
+        }
+    }
+
+    /// The name of the register, if known.
+    pub fn name(&self) -> Option<&'static str> {
+        self.name
+    }
+
+    /// The raw value that could not be parsed.
+    pub fn value(&self) -> u32 {
+        self.value
+    }
+
+    /// The name of the field that could not be parsed, if known.
+    pub fn field(&self) -> Option<&'static str> {
+        self.field
+    }
+
+    /// Records the name of the field that could not be parsed.
+    pub fn with_field(mut self, field: &'static str) -> Self {
+        self.field = Some(field);
+        self
+    }
+
+    /// Records the register and the raw value the error occurred in, e.g. for an error converted
+    /// from the error of a single field, which only knows the field's encoding.
+    pub fn in_register(mut self, name: &'static str, value: u32) -> Self {
+        self.name = Some(name);
+        self.value = value;
+        self
+    }
+
+    /// Records the layout of the register, which [`RegisterParseError::explain`] uses to break
+    /// the value down into its fields.
+    pub fn with_fields(mut self, fields: &'static [FieldInfo]) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    /// Renders the raw value in binary, grouped by the fields of the register and with the field
+    /// which could not be parsed marked, e.g. for a log line:
+    ///
+    /// ```text
+    /// failed to parse CSW register from 0x80000047
+    /// 1|0000000|0|00|000|0|0|0|000|0000|0|1|00|0|111
+    ///                                            ^^^ SIZE
+    /// ```
+    ///
+    /// Bits outside of the known fields form groups of their own. Without the layout of the
+    /// register, the value is rendered as a single group.
+    pub fn explain(&self) -> String {
+        let mut fields: Vec<_> = self.fields.iter().collect();
+        fields.sort_by(|a, b| b.offset.cmp(&a.offset));
+
+        // The groups of bits from the most significant one, as (field name, offset, width).
+        let mut groups = Vec::new();
+        let mut next = 32;
+        for field in fields {
+            let top = field.offset + field.width;
+            if top < next {
+                groups.push((None, top, next - top));
+            }
+            groups.push((Some(field.name), field.offset, field.width));
+            next = field.offset;
+        }
+        if next > 0 {
+            groups.push((None, 0, next));
+        }
+
+        let mut bits = String::new();
+        let mut marker = None;
+        for (name, offset, width) in groups {
+            if !bits.is_empty() {
+                bits.push('|');
+            }
+            if name.is_some() && name == self.field {
+                marker = Some((bits.len(), width));
+            }
+            let value = (u64::from(self.value) >> offset) & ((1 << width) - 1);
+            bits.push_str(&format!("{value:0width$b}", width = width as usize));
+        }
+
+        let mut explanation = format!("{self}\n{bits}");
+        if let (Some((column, width)), Some(field)) = (marker, self.field) {
+            let carets = "^".repeat(width as usize);
+            explanation.push_str(&format!("\n{:column$}{carets} {field}", ""));
+        }
+        explanation
+    }
+
+    /// Attaches the error of the field that could not be parsed.
+    pub fn with_source(mut self, source: impl std::error::Error + Send + Sync + 'static) -> Self {
+        self.source = Some(Box::new(source));
+        self
     }
 }
 